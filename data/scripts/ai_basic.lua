@@ -698,6 +698,17 @@ function compute_weight(parent, target)
     -- hostiles that have hurt us are higher priority
     modifiers = modifiers + parent:get_num_flag("__damage_taken_from" .. target:id())
 
+    -- hostiles that have hurt our group, or that the group is focusing on, are higher priority
+    local group = parent:ai_group()
+    if group ~= nil and base ~= 1 then
+        modifiers = modifiers + group:threat(target) / (4 * parent:stats().max_hp)
+
+        local focus = group:focus()
+        if focus ~= nil and focus:id() == target:id() then
+            modifiers = modifiers + 0.25
+        end
+    end
+
     -- hostiles that are difficult to damage with our regular attack are lower priority
     modifiers = modifiers + parent:get_num_flag("__hard_target_for" .. target:id())
 
@@ -758,4 +769,10 @@ function after_attack(parent, targets, hit)
 
     game:debug("Added hard_target " .. tostring(hard_target_factor) .. " for " .. target:id() .. " on "
         .. parent:id())
+
+    -- the rest of the group follows up on the first target attacked
+    local group = parent:ai_group()
+    if group ~= nil and group:focus() == nil and not target:is_dead() then
+        group:set_focus(target)
+    end
 end
@@ -0,0 +1,119 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Prints the final YAML of a single resource after the base data, a campaign,
+//! and any mods have been merged, annotated with the file that set each value.
+//! Keys set by more than one mod are reported on stderr.
+
+use std::process;
+
+use sulis_core::resource::{YamlResourceKind, YamlResourceSet};
+use sulis_core::util::ActiveResources;
+
+const USAGE: &str = "Usage: sulis-dump --campaign <dir> [--mod <dir>]... <kind> <id>
+
+Options:
+  --campaign <dir>        Campaign directory to load
+  --mod <dir>             Mod directory to load on top of the campaign; may be
+                          specified multiple times
+
+<kind> is the name of the directory the resource is read from, such as
+'abilities', 'actors', or 'images/simple'.
+";
+
+struct Args {
+    active: ActiveResources,
+    kind: YamlResourceKind,
+    id: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut active = ActiveResources::default();
+    let mut positional = Vec::new();
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            return Err(String::new());
+        }
+
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for '{arg}'"))?;
+
+        match arg.as_str() {
+            "--campaign" => active.campaign = Some(value),
+            "--mod" => active.mods.push(value),
+            _ => return Err(format!("Unknown argument '{arg}'")),
+        }
+    }
+
+    if active.campaign.is_none() {
+        return Err("--campaign must be specified".to_string());
+    }
+
+    let (kind, id) = match positional.as_slice() {
+        [kind, id] => (kind, id),
+        _ => return Err("Expected a resource kind and ID".to_string()),
+    };
+
+    let kind = YamlResourceKind::from_dir_name(kind)
+        .ok_or_else(|| format!("Unknown resource kind '{kind}'"))?;
+
+    Ok(Args {
+        active,
+        kind,
+        id: id.to_string(),
+    })
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprint!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let yaml = match YamlResourceSet::from_dirs(&args.active.directories()) {
+        Ok(yaml) => yaml,
+        Err(e) => {
+            eprintln!("Unable to read resources: {e}");
+            process::exit(1);
+        }
+    };
+
+    for conflict in yaml.conflicts.iter() {
+        eprintln!("Conflict: {conflict}");
+    }
+
+    match yaml.dump_annotated(args.kind, &args.id) {
+        Some(dump) => print!("{dump}"),
+        None => {
+            eprintln!("No {:?} with ID '{}' found", args.kind, args.id);
+            process::exit(1);
+        }
+    }
+}
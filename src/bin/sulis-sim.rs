@@ -0,0 +1,236 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Runs a campaign headlessly for a fixed number of ticks and prints a summary.
+//! Useful for smoke testing content and for reproducing combat with a fixed seed.
+
+use std::process;
+use std::rc::Rc;
+
+use log::{error, info};
+
+use sulis_core::benchmark;
+use sulis_core::config::Config;
+use sulis_core::util::{self, ActiveResources};
+use sulis_module::{Actor, Module};
+use sulis_state::simulation::{PartyTurn, DEFAULT_TICK_MILLIS};
+use sulis_state::{GameState, Simulation};
+
+const USAGE: &str = "Usage: sulis-sim --campaign <dir> --pc <actor_id> [options]
+
+Options:
+  --campaign <dir>        Campaign directory to load
+  --mod <dir>             Mod directory to load; may be specified multiple times
+  --pc <actor_id>         Actor to use as the player character; this should
+                          normally be a Friendly faction actor
+  --party <id,id,...>     Additional actors to add to the party
+  --ticks <n>             Number of ticks to run (default 1000)
+  --tick-millis <n>       Length of each tick in milliseconds (default 16)
+  --seed <n>              Seed for all game rolls
+  --script <id:func>      Script function to call with the player once the game starts
+  --party-script <id:func>
+                          Script function called at the start of each party member's
+                          combat turn.  By default party turns are ended immediately.
+  --profile               Print the time taken by each script function and benchmark
+";
+
+struct Args {
+    campaign: String,
+    mods: Vec<String>,
+    pc: String,
+    party: Vec<String>,
+    ticks: u64,
+    tick_millis: u32,
+    seed: Option<u64>,
+    script: Option<(String, String)>,
+    party_script: Option<(String, String)>,
+    profile: bool,
+}
+
+fn parse_script(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((id, func)) if !id.is_empty() && !func.is_empty() => {
+            Ok((id.to_string(), func.to_string()))
+        }
+        _ => Err(format!("Invalid script '{value}', expected <id:func>")),
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut campaign = None;
+    let mut pc = None;
+    let mut args = Args {
+        campaign: String::new(),
+        mods: Vec::new(),
+        pc: String::new(),
+        party: Vec::new(),
+        ticks: 1000,
+        tick_millis: DEFAULT_TICK_MILLIS,
+        seed: None,
+        script: None,
+        party_script: None,
+        profile: false,
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            return Err(String::new());
+        }
+
+        if arg == "--profile" {
+            args.profile = true;
+            continue;
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for '{arg}'"))?;
+
+        match arg.as_str() {
+            "--campaign" => campaign = Some(value),
+            "--mod" => args.mods.push(value),
+            "--pc" => pc = Some(value),
+            "--party" => args.party = value.split(',').map(|s| s.trim().to_string()).collect(),
+            "--ticks" => {
+                args.ticks = value
+                    .parse()
+                    .map_err(|_| format!("Invalid ticks '{value}'"))?
+            }
+            "--tick-millis" => {
+                args.tick_millis = value
+                    .parse()
+                    .map_err(|_| format!("Invalid tick millis '{value}'"))?
+            }
+            "--seed" => {
+                args.seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed '{value}'"))?,
+                )
+            }
+            "--script" => args.script = Some(parse_script(&value)?),
+            "--party-script" => args.party_script = Some(parse_script(&value)?),
+            _ => return Err(format!("Unknown argument '{arg}'")),
+        }
+    }
+
+    args.campaign = campaign.ok_or("--campaign must be specified")?;
+    args.pc = pc.ok_or("--pc must be specified")?;
+    Ok(args)
+}
+
+fn find_actor(id: &str) -> Result<Rc<Actor>, String> {
+    Module::actor(id).ok_or_else(|| format!("Actor '{id}' not found"))
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    let active = ActiveResources {
+        campaign: Some(args.campaign.clone()),
+        mods: args.mods.clone(),
+    };
+
+    if args.profile {
+        let mut config = Config::get_clone();
+        config.debug.profile_scripts = true;
+        Config::set(config);
+    }
+
+    let dirs = active.directories();
+    info!("Reading resources from '{:?}'", dirs);
+    Simulation::load_resources(dirs).map_err(|e| e.to_string())?;
+
+    let pc = find_actor(&args.pc)?;
+    let party = args
+        .party
+        .iter()
+        .map(|id| find_actor(id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut sim = Simulation::new_campaign(pc, party, args.seed).map_err(|e| e.to_string())?;
+    sim.set_tick_millis(args.tick_millis);
+    if let Some((id, func)) = args.party_script {
+        sim.set_party_turn(PartyTurn::Script { id, func });
+    }
+
+    if let Some((id, func)) = args.script {
+        // run one tick first so that the area has been loaded and visibility computed
+        sim.tick();
+        sim.run_script(&id, &func);
+    }
+
+    sim.run_until(args.ticks, |sim| sim.is_party_defeated());
+
+    let mgr = GameState::turn_manager();
+    let time = mgr.borrow().current_time();
+    println!("Ran {} ticks of {} millis", sim.ticks(), args.tick_millis);
+    println!(
+        "Game time: day {} hour {} round {}",
+        time.day, time.hour, time.round
+    );
+    println!("Area: {}", GameState::area_state().borrow().area.area.id);
+    println!("Combat active: {}", GameState::is_combat_active());
+    for member in GameState::party() {
+        let member = member.borrow();
+        println!(
+            "  {} ({}): {}/{} hp",
+            member.actor.actor.name,
+            member.unique_id(),
+            member.actor.hp(),
+            member.actor.stats.max_hp
+        );
+    }
+
+    for trigger in sim.unhandled_triggers() {
+        println!("Unhandled UI trigger: {trigger:?}");
+    }
+
+    if args.profile {
+        print!("{}", benchmark::profile_report(None));
+    }
+
+    Ok(!sim.is_party_defeated())
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprint!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    // Don't drop the returned handle while the program is running
+    let _logger_handle = util::setup_logger();
+    info!("Starting headless simulation");
+
+    match run(args) {
+        Ok(true) => (),
+        Ok(false) => {
+            println!("The party was defeated");
+            process::exit(1);
+        }
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Runs the `test_*` functions in the `*_test.lua` scripts of a campaign and
+//! any mods headlessly, and reports the result of each.
+
+use std::process;
+
+use log::{error, info};
+
+use sulis_core::util::{self, ActiveResources};
+use sulis_state::script::test_runner;
+use sulis_state::Simulation;
+
+const USAGE: &str = "Usage: sulis-test --campaign <dir> --pc <actor_id> [options] [script_id]...
+
+Options:
+  --campaign <dir>        Campaign directory to load
+  --mod <dir>             Mod directory to load; may be specified multiple times
+  --pc <actor_id>         Actor to use as the player character in tests whose
+                          fixture does not specify a player
+  --seed <n>              Seed for all game rolls (default 0)
+
+If any script IDs are given, only those test scripts are run.
+";
+
+struct Args {
+    active: ActiveResources,
+    pc: String,
+    seed: u64,
+    scripts: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut pc = None;
+    let mut args = Args {
+        active: ActiveResources::default(),
+        pc: String::new(),
+        seed: 0,
+        scripts: Vec::new(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            return Err(String::new());
+        }
+
+        if !arg.starts_with("--") {
+            args.scripts.push(arg);
+            continue;
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for '{arg}'"))?;
+
+        match arg.as_str() {
+            "--campaign" => args.active.campaign = Some(value),
+            "--mod" => args.active.mods.push(value),
+            "--pc" => pc = Some(value),
+            "--seed" => {
+                args.seed = value
+                    .parse()
+                    .map_err(|_| format!("Invalid seed '{value}'"))?
+            }
+            _ => return Err(format!("Unknown argument '{arg}'")),
+        }
+    }
+
+    if args.active.campaign.is_none() {
+        return Err("--campaign must be specified".to_string());
+    }
+    args.pc = pc.ok_or("--pc must be specified")?;
+    Ok(args)
+}
+
+/// Runs the tests, returning the number of passed and failed test functions
+fn run(args: Args) -> Result<(usize, usize), String> {
+    let dirs = args.active.directories();
+    info!("Reading resources from '{:?}'", dirs);
+    Simulation::load_resources(dirs).map_err(|e| e.to_string())?;
+
+    let scripts = if args.scripts.is_empty() {
+        test_runner::test_scripts()
+    } else {
+        args.scripts
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for id in scripts {
+        let results = match test_runner::run(&id, &args.pc, Some(args.seed)) {
+            Ok(results) => results,
+            Err(e) => {
+                println!("{id}: {e}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        for result in results {
+            match result.error {
+                None => {
+                    println!("{id}:{} ... ok", result.name);
+                    passed += 1;
+                }
+                Some(e) => {
+                    println!(
+                        "{id}:{} ... FAILED\n  {}",
+                        result.name,
+                        e.replace('\n', "\n  ")
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    Ok((passed, failed))
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprint!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    // Don't drop the returned handle while the program is running
+    let _logger_handle = util::setup_logger();
+    info!("Starting script tests");
+
+    match run(args) {
+        Ok((passed, failed)) => {
+            println!("{passed} passed, {failed} failed");
+            if failed > 0 {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            error!("{}", e);
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Loads the base data, a campaign, and any mods, and reports every resource that
+//! fails to load or references something that does not exist.  Exits with a non-zero
+//! status if any problems are found.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use sulis_core::resource::{self, ResourceError, ResourceSet, YamlResourceSet};
+use sulis_core::serde_yaml::Value;
+use sulis_core::util::ActiveResources;
+use sulis_module::{validation, Module};
+use sulis_state::Script;
+
+const USAGE: &str = "Usage: sulis-validate --campaign <dir> [--mod <dir>]...
+
+Options:
+  --campaign <dir>        Campaign directory to validate
+  --mod <dir>             Mod directory to load on top of the campaign; may be
+                          specified multiple times
+";
+
+/// Collects all warnings logged while loading, tagged with the number of resource
+/// errors recorded at that point.  Warnings tagged with `n` were logged while
+/// creating the resource for error `n`, and are printed as its details.
+struct WarningLogger {
+    warnings: Mutex<Vec<(usize, String)>>,
+}
+
+impl Log for WarningLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let index = resource::resource_error_count();
+        let mut warnings = self.warnings.lock().unwrap();
+        warnings.push((index, record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: WarningLogger = WarningLogger {
+    warnings: Mutex::new(Vec::new()),
+};
+
+fn parse_args() -> Result<ActiveResources, String> {
+    let mut active = ActiveResources::default();
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--help" || arg == "-h" {
+            return Err(String::new());
+        }
+
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("Missing value for '{arg}'"))?;
+
+        match arg.as_str() {
+            "--campaign" => active.campaign = Some(value),
+            "--mod" => active.mods.push(value),
+            _ => return Err(format!("Unknown argument '{arg}'")),
+        }
+    }
+
+    if active.campaign.is_none() {
+        return Err("--campaign must be specified".to_string());
+    }
+
+    Ok(active)
+}
+
+/// Converts a resource kind name, either the `YamlResourceKind` name or the name
+/// used when inserting into the resource set, into a common form
+fn kind_name(kind: &str) -> String {
+    let mut out = String::new();
+    for (index, c) in kind.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            out.push('_');
+        }
+
+        if c == ' ' {
+            out.push('_');
+        } else {
+            out.extend(c.to_lowercase());
+        }
+    }
+
+    if out.ends_with("_image") {
+        "image".to_string()
+    } else {
+        out
+    }
+}
+
+/// Builds a map of (kind, id) to the files each resource was read from
+fn file_index(dirs: &[String]) -> HashMap<(String, String), Vec<String>> {
+    let mut index = HashMap::new();
+
+    let yaml = match YamlResourceSet::from_dirs(dirs) {
+        Ok(yaml) => yaml,
+        Err(_) => return index,
+    };
+
+    let file_key = Value::String(resource::yaml_resource_set::FILE_VAL_STR.to_string());
+    for (kind, resources) in yaml.resources {
+        let kind = kind_name(&format!("{kind:?}"));
+        for (id, value) in resources {
+            let files = match value.get(&file_key) {
+                Some(Value::Sequence(files)) => files
+                    .iter()
+                    .filter_map(|f| f.as_str().map(|f| f.to_string()))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            index.entry((kind.clone(), id)).or_default().extend(files);
+        }
+    }
+
+    for dir in dirs {
+        index_scripts(&[dir, "scripts"].iter().collect::<PathBuf>(), &mut index);
+    }
+
+    index
+}
+
+fn index_scripts(dir: &Path, index: &mut HashMap<(String, String), Vec<String>>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            index_scripts(&path, index);
+        } else if path.extension().is_some_and(|ext| ext == "lua") {
+            let id = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            index
+                .entry(("script".to_string(), id))
+                .or_default()
+                .push(path.to_string_lossy().to_string());
+        }
+    }
+}
+
+fn check_script_syntax() -> Vec<ResourceError> {
+    let mut errors = Vec::new();
+    for id in Module::all_scripts() {
+        let script = match Module::script(&id) {
+            None => continue,
+            Some(script) => script,
+        };
+
+        if let Err(e) = Script::check_syntax(&id, &script) {
+            errors.push(ResourceError {
+                kind: "script".to_string(),
+                id,
+                files: Vec::new(),
+                message: e.to_string(),
+            });
+        }
+    }
+    errors
+}
+
+/// Takes the warnings logged while creating each recorded load error
+fn load_error_details(errors: &[ResourceError]) -> Vec<Vec<String>> {
+    let warnings = std::mem::take(&mut *LOGGER.warnings.lock().unwrap());
+
+    let mut details = vec![Vec::new(); errors.len()];
+    for (index, warning) in warnings {
+        let error = match errors.get(index) {
+            None => continue,
+            Some(error) => error,
+        };
+
+        // skip the lines that only repeat the error itself
+        if warning != error.message && !warning.starts_with("Error in ") {
+            details[index].push(warning);
+        }
+    }
+    details
+}
+
+fn print_report(mut entries: Vec<(ResourceError, Vec<String>)>, fatal: Option<String>) {
+    entries.sort_by(|(a, _), (b, _)| {
+        (&a.kind, &a.id, &a.files, &a.message).cmp(&(&b.kind, &b.id, &b.files, &b.message))
+    });
+
+    for (error, lines) in entries.iter() {
+        if error.id.is_empty() {
+            println!("{}: {}", error.kind, error.message);
+        } else {
+            println!("{} '{}': {}", error.kind, error.id, error.message);
+        }
+
+        if !error.files.is_empty() {
+            println!("    in {}", error.files.join(", "));
+        }
+
+        for line in lines {
+            println!("    {line}");
+        }
+    }
+
+    if let Some(fatal) = fatal {
+        println!("Unable to load module: {fatal}");
+    }
+
+    println!("{} problem(s) found", entries.len());
+}
+
+fn run(active: ActiveResources) -> bool {
+    let dirs = active.directories();
+    let index = file_index(&dirs);
+
+    resource::record_resource_errors();
+
+    let fatal = match ResourceSet::load_resources(dirs.clone()) {
+        Err(e) => Some(e.to_string()),
+        Ok(yaml) => Module::load_resources(yaml, dirs)
+            .err()
+            .map(|e| e.to_string()),
+    };
+
+    let errors = resource::take_resource_errors();
+    let details = load_error_details(&errors);
+    let mut entries: Vec<_> = errors.into_iter().zip(details).collect();
+
+    if fatal.is_none() {
+        let mut checks = validation::check_references();
+        checks.append(&mut check_script_syntax());
+        entries.extend(checks.into_iter().map(|error| (error, Vec::new())));
+    }
+
+    for (error, _) in entries.iter_mut() {
+        error.kind = kind_name(&error.kind);
+        if error.files.is_empty() {
+            let key = (error.kind.clone(), error.id.clone());
+            if let Some(files) = index.get(&key) {
+                error.files = files.clone();
+            }
+        }
+    }
+
+    let ok = entries.is_empty() && fatal.is_none();
+    print_report(entries, fatal);
+    ok
+}
+
+fn main() {
+    let active = match parse_args() {
+        Ok(active) => active,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprint!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
+    }
+
+    if !run(active) {
+        process::exit(1);
+    }
+}
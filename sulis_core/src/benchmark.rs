@@ -15,14 +15,140 @@
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Error;
+use std::path::PathBuf;
 use std::time::Instant;
 
 use rlua::{self, UserData, UserDataMethods};
 
-use crate::config::Config;
+use crate::config::{Config, USER_DIR};
+
+const PROFILE_FILE: &str = "profile.txt";
 
 thread_local! {
     static BENCH: RefCell<Vec<Bench>> = const { RefCell::new(Vec::new()) };
+    static PROFILE: RefCell<Profile> = RefCell::new(Profile::default());
+}
+
+/// Counters for one script function or benchmark tag, collected over the
+/// session when `profile_scripts` is set in the debug config
+#[derive(Default, Clone, Debug)]
+pub struct ProfileStats {
+    pub calls: u64,
+    pub total_millis: f64,
+    pub max_millis: f64,
+    pub instructions: u64,
+    pub max_memory_kb: f64,
+}
+
+impl ProfileStats {
+    pub fn avg_millis(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.total_millis / self.calls as f64
+        }
+    }
+
+    fn add(&mut self, millis: f64, instructions: u64, memory_kb: f64) {
+        self.calls += 1;
+        self.total_millis += millis;
+        self.max_millis = self.max_millis.max(millis);
+        self.instructions += instructions;
+        self.max_memory_kb = self.max_memory_kb.max(memory_kb);
+    }
+}
+
+#[derive(Default)]
+struct Profile {
+    scripts: HashMap<String, ProfileStats>,
+    benches: HashMap<String, ProfileStats>,
+}
+
+/// Returns whether script and benchmark profiling is enabled
+pub fn profiling() -> bool {
+    Config::debug().profile_scripts
+}
+
+/// Adds one call of the script function to the profile, if profiling is enabled
+pub fn profile_script(script: &str, func: &str, millis: f64, instructions: u64, memory_kb: f64) {
+    if !profiling() {
+        return;
+    }
+
+    PROFILE.with(|profile| {
+        profile
+            .borrow_mut()
+            .scripts
+            .entry(format!("{script}:{func}"))
+            .or_default()
+            .add(millis, instructions, memory_kb);
+    });
+}
+
+/// Removes all collected profile data
+pub fn clear_profile() {
+    PROFILE.with(|profile| *profile.borrow_mut() = Profile::default());
+}
+
+/// Returns the collected profile of script functions and benchmarks, sorted by
+/// total time, limited to `max_entries` of each if specified
+pub fn profile_report(max_entries: Option<usize>) -> String {
+    PROFILE.with(|profile| {
+        let profile = profile.borrow();
+        let mut out = String::new();
+        write_section(&mut out, "Scripts", &profile.scripts, max_entries);
+        write_section(&mut out, "Benchmarks", &profile.benches, max_entries);
+        out
+    })
+}
+
+/// Writes the full profile to the user directory if profiling is enabled,
+/// returning the file written
+pub fn write_profile() -> Result<Option<PathBuf>, Error> {
+    if !profiling() {
+        return Ok(None);
+    }
+
+    let path = USER_DIR.join(PROFILE_FILE);
+    fs::write(&path, profile_report(None))?;
+    info!("Wrote script profile to {:?}", path);
+    Ok(Some(path))
+}
+
+fn write_section(
+    out: &mut String,
+    title: &str,
+    stats: &HashMap<String, ProfileStats>,
+    max_entries: Option<usize>,
+) {
+    let mut entries: Vec<_> = stats.iter().collect();
+    entries.sort_by(|(_, a), (_, b)| b.total_millis.total_cmp(&a.total_millis));
+    if let Some(max) = max_entries {
+        entries.truncate(max);
+    }
+
+    let _ = writeln!(out, "{title}");
+    let _ = writeln!(
+        out,
+        "{:>8} {:>10} {:>8} {:>8} {:>12} {:>8}  name",
+        "calls", "total ms", "avg ms", "max ms", "instructions", "max KB"
+    );
+    for (id, s) in entries {
+        let _ = writeln!(
+            out,
+            "{:>8} {:>10.3} {:>8.3} {:>8.3} {:>12} {:>8.1}  {id}",
+            s.calls,
+            s.total_millis,
+            s.avg_millis(),
+            s.max_millis,
+            s.instructions,
+            s.max_memory_kb
+        );
+    }
 }
 
 pub fn start_bench(tag: Option<String>) -> Handle {
@@ -95,5 +221,16 @@ impl Bench {
         let millis = micros as f64 / 1000.0;
 
         log!(Config::bench_log_level(), "BENCHMARK '{}': {:.3} millis", id, millis);
+
+        if profiling() {
+            PROFILE.with(|profile| {
+                profile
+                    .borrow_mut()
+                    .benches
+                    .entry(id)
+                    .or_default()
+                    .add(millis, 0, 0.0);
+            });
+        }
     }
 }
@@ -48,6 +48,9 @@ pub struct Config {
     pub logging: LoggingConfig,
     pub editor: EditorConfig,
 
+    #[serde(default)]
+    pub saves: SavesConfig,
+
     #[serde(default)]
     pub debug: DebugConfig,
 }
@@ -141,12 +144,17 @@ impl Config {
         CONFIG.with(|c| c.borrow().resources.clone())
     }
 
+    pub fn saves_config() -> SavesConfig {
+        CONFIG.with(|c| c.borrow().saves.clone())
+    }
+
     pub fn get_keybindings() -> HashMap<InputActionKind, Key> {
         CONFIG.with(|c| {
             c.borrow()
                 .input
                 .keybindings
                 .iter()
+                .chain(c.borrow().input.console_keybindings.iter())
                 .map(|(k, v)| (*v, *k))
                 .collect()
         })
@@ -159,7 +167,13 @@ impl Config {
     pub fn get_input_action(k: KeyboardEvent) -> Option<InputAction> {
         debug!("Got keyboard input '{:?}'", k);
         CONFIG.with(|c| {
-            let kind = c.borrow().input.keybindings.get(&k.key).copied();
+            let c = c.borrow();
+            let kind = c
+                .input
+                .keybindings
+                .get(&k.key)
+                .or_else(|| c.input.console_keybindings.get(&k.key))
+                .copied();
 
             kind.map(|kind| InputAction { kind, state: k.state })
         })
@@ -191,6 +205,23 @@ impl Config {
 pub struct DebugConfig {
     pub encounter_spawning: bool,
     pub limit_line_of_sight: bool,
+
+    #[serde(default)]
+    pub rand_seed: Option<u64>,
+
+    /// Whether changed scripts, abilities, and items are reloaded automatically
+    /// while the game is running
+    #[serde(default)]
+    pub hot_reload: bool,
+
+    /// The local port the Lua debugger listens on, if it is enabled
+    #[serde(default)]
+    pub lua_debugger_port: Option<u16>,
+
+    /// Whether the time taken by script functions and benchmarks is collected
+    /// into a profile over the session
+    #[serde(default)]
+    pub profile_scripts: bool,
 }
 
 impl Default for DebugConfig {
@@ -198,10 +229,49 @@ impl Default for DebugConfig {
         DebugConfig {
             encounter_spawning: true,
             limit_line_of_sight: true,
+            rand_seed: None,
+            hot_reload: false,
+            lua_debugger_port: None,
+            profile_scripts: false,
         }
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SavesConfig {
+    pub compression: SaveCompression,
+
+    /// The number of autosaves kept before the oldest is deleted.  Zero disables
+    /// autosaving entirely
+    pub autosave_count: usize,
+    pub autosave_on_area_transition: bool,
+    pub autosave_on_rest: bool,
+
+    /// Minutes of play between autosaves, or zero to disable timed autosaves
+    pub autosave_interval_minutes: u32,
+}
+
+impl Default for SavesConfig {
+    fn default() -> Self {
+        SavesConfig {
+            compression: SaveCompression::Gzip,
+            autosave_count: 3,
+            autosave_on_area_transition: true,
+            autosave_on_rest: true,
+            autosave_interval_minutes: 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum SaveCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct EditorConfig {
@@ -306,10 +376,24 @@ pub struct InputConfig {
     pub edge_scrolling: bool,
     pub scroll_speed: f32,
     pub keybindings: HashMap<Key, InputActionKind>,
+
+    /// Keybindings for actions that are only used while the console is open
+    #[serde(default = "InputConfig::default_console_keybindings")]
+    pub console_keybindings: HashMap<Key, InputActionKind>,
     pub click_actions: HashMap<RawClick, ClickKind>,
     pub crit_screen_shake: bool,
 }
 
+impl InputConfig {
+    fn default_console_keybindings() -> HashMap<Key, InputActionKind> {
+        let mut keys = HashMap::new();
+        keys.insert(Key::KeyTab, InputActionKind::ConsoleComplete);
+        keys.insert(Key::KeyHome, InputActionKind::ConsoleScrollUp);
+        keys.insert(Key::KeyEnd, InputActionKind::ConsoleScrollDown);
+        keys
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 #[serde(deny_unknown_fields)]
 pub enum RawClick {
@@ -28,6 +28,9 @@ pub use self::input_action::{InputAction, InputActionKind, InputActionState};
 pub mod keyboard_event;
 pub use self::keyboard_event::KeyboardEvent;
 
+mod software_renderer;
+pub use self::software_renderer::SoftwareRenderer;
+
 use std::cell::RefCell;
 use std::io::Error;
 use std::rc::Rc;
@@ -83,6 +86,10 @@ pub trait GraphicsRenderer {
 
     fn has_texture(&self, id: &str) -> bool;
 
+    /// Reads back the contents of the specified texture, with the top row first.
+    /// Returns `None` if the texture does not exist or cannot be read.
+    fn read_texture(&self, id: &str) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>>;
+
     fn set_scissor(&mut self, pos: Point, size: Size);
 
     fn clear_scissor(&mut self);
@@ -112,6 +112,12 @@ impl Audio {
         Audio::enqueue(sound, kind);
     }
 
+    /// Discards any queued sounds.  Used when running without an audio device
+    /// or main loop, such as in the headless simulation.
+    pub fn clear_queue() {
+        AUDIO_QUEUE.with(|q| q.borrow_mut().clear());
+    }
+
     pub(crate) fn update(device: Option<&mut AudioDevice>, elapsed_millis: u32) {
         match device {
             None => AUDIO_QUEUE.with(|q| q.borrow_mut().clear()),
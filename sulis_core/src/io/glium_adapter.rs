@@ -18,6 +18,7 @@ use std::time;
 use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
 
+use crate::benchmark;
 use crate::config::{Config, DisplayMode};
 use crate::io::keyboard_event::Key;
 use crate::io::*;
@@ -282,6 +283,15 @@ impl<'a> GraphicsRenderer for GliumRenderer<'a> {
         self.display.textures.contains_key(id)
     }
 
+    fn read_texture(&self, id: &str) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        let texture = self.display.textures.get(id)?;
+        let raw: RawImage2d<u8> = texture.texture.read();
+        let image = ImageBuffer::from_raw(raw.width, raw.height, raw.data.into_owned())?;
+
+        // glium textures are stored bottom row first
+        Some(crate::extern_image::imageops::flip_vertical(&image))
+    }
+
     fn draw_to_texture(&mut self, texture_id: &str, draw_list: DrawList) {
         self.create_texture_if_missing(&draw_list.texture, &draw_list);
         let texture = self.display.textures.get(texture_id).unwrap();
@@ -623,6 +633,10 @@ pub(crate) fn main_loop(
                     "Average frame render time: {:.2} milliseconds",
                     1000.0 * secs / frames as f64
                 );
+
+                if let Err(e) = benchmark::write_profile() {
+                    warn!("Unable to write the script profile: {}", e);
+                }
             },
             event => {
                 if let Event::WindowEvent { event, .. } = event {
@@ -40,6 +40,9 @@ pub enum InputActionKind {
     ToggleConsole,
     ConsoleHistoryPrevious,
     ConsoleHistoryNext,
+    ConsoleComplete,
+    ConsoleScrollUp,
+    ConsoleScrollDown,
     ToggleInventory,
     ToggleCharacter,
     ToggleMap,
@@ -0,0 +1,493 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! A CPU rasterizer implementing `GraphicsRenderer`.  It needs no window or
+//! OpenGL context, so the UI tree and area views may be drawn on headless
+//! machines and the output written to PNG, for example to compare against
+//! golden images.
+//!
+//! The coordinate transforms, color filter, color swap, blending, and scissor
+//! behavior follow the glium adapter.  Output is close to, but not guaranteed
+//! to be bit identical with, the OpenGL renderer.
+
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::extern_image::{self, imageops, ImageBuffer, Rgba, RgbaImage};
+use crate::io::{
+    DrawList, DrawListKind, GraphicsRenderer, TextureMagFilter, TextureMinFilter, Vertex,
+};
+use crate::resource::ResourceSet;
+use crate::ui::Widget;
+use crate::util::{Point, Size};
+
+struct SoftwareTexture {
+    // rows are stored bottom first, matching OpenGL texture coordinates
+    image: RgbaImage,
+    min_filter: TextureMinFilter,
+    mag_filter: TextureMagFilter,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ScissorRect {
+    left: i32,
+    bottom: i32,
+    width: i32,
+    height: i32,
+}
+
+pub struct SoftwareRenderer {
+    // rows are stored bottom first, see `frame` for a top down image
+    frame: RgbaImage,
+    textures: HashMap<String, SoftwareTexture>,
+    scissor: Option<ScissorRect>,
+}
+
+impl SoftwareRenderer {
+    /// Creates a new renderer with an output frame of the specified size in
+    /// pixels.  The UI is scaled to fit the frame in the same way as the
+    /// window in the glium adapter.
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer {
+            frame: ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 255])),
+            textures: HashMap::new(),
+            scissor: None,
+        }
+    }
+
+    /// Creates a new renderer with a frame of the configured UI size
+    pub fn with_ui_size() -> SoftwareRenderer {
+        let (width, height) = Config::ui_size();
+        SoftwareRenderer::new(width as u32, height as u32)
+    }
+
+    /// Clears the output frame to opaque black
+    pub fn clear(&mut self) {
+        for pixel in self.frame.pixels_mut() {
+            *pixel = Rgba([0, 0, 0, 255]);
+        }
+    }
+
+    /// Clears the frame and draws the specified widget tree to it.  The mouse
+    /// cursor is not drawn.
+    pub fn render(&mut self, root: &Widget, millis: u32) {
+        self.clear();
+        let pixel_size = Point::new(self.frame.width() as i32, self.frame.height() as i32);
+        root.draw(self, pixel_size, millis);
+    }
+
+    /// Returns a copy of the current output frame, with the top row first
+    pub fn frame(&self) -> RgbaImage {
+        imageops::flip_vertical(&self.frame)
+    }
+
+    /// Returns a copy of the specified texture, with the top row first, or `None`
+    /// if no texture with that ID has been created
+    pub fn texture(&self, id: &str) -> Option<RgbaImage> {
+        self.textures
+            .get(id)
+            .map(|texture| imageops::flip_vertical(&texture.image))
+    }
+
+    /// Writes the current output frame to a PNG file at the specified path
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.frame()
+            .save_with_format(path, extern_image::ImageFormat::Png)
+            .map_err(Error::other)
+    }
+
+    /// Compares the current output frame with the PNG at the specified path.
+    /// Returns true if the images are the same size and no color channel of any
+    /// pixel differs by more than `tolerance`.
+    pub fn matches_png<P: AsRef<Path>>(&self, path: P, tolerance: u8) -> Result<bool, Error> {
+        let expected = extern_image::open(path).map_err(Error::other)?.to_rgba8();
+        let actual = self.frame();
+
+        if expected.dimensions() != actual.dimensions() {
+            return Ok(false);
+        }
+
+        let matches = expected.pixels().zip(actual.pixels()).all(|(e, a)| {
+            e.0.iter()
+                .zip(a.0.iter())
+                .all(|(e, a)| e.abs_diff(*a) <= tolerance)
+        });
+        Ok(matches)
+    }
+
+    fn create_texture_if_missing(&mut self, texture_id: &str, draw_list: &DrawList) {
+        if self.textures.contains_key(texture_id) {
+            return;
+        }
+
+        trace!(
+            "Creating texture for ID '{}' of type '{:?}'",
+            texture_id,
+            draw_list.kind
+        );
+        let image = match draw_list.kind {
+            DrawListKind::Sprite => ResourceSet::spritesheet(texture_id).map(|s| s.image.clone()),
+            DrawListKind::Font => ResourceSet::font(texture_id).map(|f| f.image.clone()),
+        };
+
+        let image = match image {
+            Some(image) => image,
+            None => {
+                warn!("Unable to create texture for invalid ID '{}'", texture_id);
+                return;
+            }
+        };
+
+        self.register_texture(
+            texture_id,
+            image,
+            draw_list.texture_min_filter,
+            draw_list.texture_mag_filter,
+        );
+    }
+
+    fn draw_to_target(&mut self, target_id: Option<&str>, draw_list: DrawList) {
+        match target_id {
+            None => {
+                if let Some(texture) = self.textures.get(&draw_list.texture) {
+                    rasterize(&mut self.frame, texture, &draw_list, self.scissor);
+                }
+            }
+            Some(id) => {
+                // Take the target out of the map so that the source texture may be
+                // borrowed at the same time.  As with OpenGL, drawing a texture onto
+                // itself is not supported.
+                let mut target = match self.textures.remove(id) {
+                    None => {
+                        warn!("Attempted to draw to invalid texture '{}'", id);
+                        return;
+                    }
+                    Some(target) => target,
+                };
+
+                if let Some(texture) = self.textures.get(&draw_list.texture) {
+                    rasterize(&mut target.image, texture, &draw_list, self.scissor);
+                }
+
+                self.textures.insert(id.to_string(), target);
+            }
+        }
+    }
+}
+
+impl GraphicsRenderer for SoftwareRenderer {
+    fn draw(&mut self, draw_list: DrawList) {
+        if draw_list.texture.is_empty() {
+            return;
+        }
+        self.create_texture_if_missing(&draw_list.texture, &draw_list);
+
+        self.draw_to_target(None, draw_list);
+    }
+
+    fn draw_to_texture(&mut self, texture_id: &str, draw_list: DrawList) {
+        self.create_texture_if_missing(&draw_list.texture, &draw_list);
+
+        self.draw_to_target(Some(texture_id), draw_list);
+    }
+
+    fn register_texture(
+        &mut self,
+        id: &str,
+        image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+        min_filter: TextureMinFilter,
+        mag_filter: TextureMagFilter,
+    ) {
+        let dims = image.dimensions();
+        trace!("Registering texture '{}', {}x{}", id, dims.0, dims.1);
+
+        self.textures.insert(
+            id.to_string(),
+            SoftwareTexture {
+                image: imageops::flip_vertical(&image),
+                min_filter,
+                mag_filter,
+            },
+        );
+    }
+
+    fn clear_texture(&mut self, id: &str) {
+        if let Some(texture) = self.textures.get_mut(id) {
+            for pixel in texture.image.pixels_mut() {
+                *pixel = Rgba([0, 0, 0, 0]);
+            }
+        }
+    }
+
+    fn clear_texture_region(&mut self, id: &str, min_x: i32, min_y: i32, max_x: i32, max_y: i32) {
+        let texture = match self.textures.get_mut(id) {
+            None => return,
+            Some(texture) => texture,
+        };
+
+        let (width, height) = texture.image.dimensions();
+        let (width, height) = (width as i32, height as i32);
+
+        // min_y and max_y are measured from the top of the texture
+        let bottom = (height - max_y).max(0);
+        let top = (height - min_y).min(height);
+        for y in bottom..top {
+            for x in min_x.max(0)..max_x.min(width) {
+                texture
+                    .image
+                    .put_pixel(x as u32, y as u32, Rgba([0, 0, 0, 0]));
+            }
+        }
+    }
+
+    fn has_texture(&self, id: &str) -> bool {
+        self.textures.contains_key(id)
+    }
+
+    fn read_texture(&self, id: &str) -> Option<RgbaImage> {
+        self.texture(id)
+    }
+
+    fn set_scissor(&mut self, pos: Point, size: Size) {
+        let (res_x, res_y) = Config::ui_size();
+        let scale_x = self.frame.width() as f64 / res_x as f64;
+        let scale_y = self.frame.height() as f64 / res_y as f64;
+
+        self.scissor = Some(ScissorRect {
+            left: (pos.x as f64 * scale_x) as i32,
+            bottom: ((res_y - (pos.y + size.height)) as f64 * scale_y) as i32,
+            width: (size.width as f64 * scale_x) as i32,
+            height: (size.height as f64 * scale_y) as i32,
+        });
+    }
+
+    fn clear_scissor(&mut self) {
+        self.scissor = None;
+    }
+}
+
+fn rasterize(
+    target: &mut RgbaImage,
+    texture: &SoftwareTexture,
+    draw_list: &DrawList,
+    scissor: Option<ScissorRect>,
+) {
+    let (ui_x, ui_y) = Config::ui_size();
+    let (width, height) = (target.width() as f32, target.height() as f32);
+    let scale = draw_list.scale;
+
+    // maps a vertex to target pixel coordinates, with y increasing upwards.  This is the
+    // scale * matrix transform of the vertex shader followed by the viewport transform
+    let to_pixel = |vertex: &Vertex| {
+        let ndc_x = 2.0 * vertex.position[0] / ui_x as f32 - 1.0;
+        let ndc_y = 2.0 * vertex.position[1] / ui_y as f32 - 1.0;
+        let ndc_x = scale[0] * ndc_x + scale[0] - 1.0;
+        let ndc_y = scale[1] * ndc_y + 1.0 - scale[1];
+        [(ndc_x + 1.0) * 0.5 * width, (ndc_y + 1.0) * 0.5 * height]
+    };
+
+    let (mut min_x, mut min_y) = (0, 0);
+    let (mut max_x, mut max_y) = (target.width() as i32, target.height() as i32);
+    if let Some(rect) = scissor {
+        min_x = min_x.max(rect.left);
+        min_y = min_y.max(rect.bottom);
+        max_x = max_x.min(rect.left + rect.width);
+        max_y = max_y.min(rect.bottom + rect.height);
+    }
+
+    for tri in draw_list.quads.chunks_exact(3) {
+        let mut p = [to_pixel(&tri[0]), to_pixel(&tri[1]), to_pixel(&tri[2])];
+        let mut tc = [tri[0].tex_coords, tri[1].tex_coords, tri[2].tex_coords];
+
+        let mut area = edge(p[0], p[1], p[2]);
+        if area == 0.0 {
+            continue;
+        }
+        if area < 0.0 {
+            p.swap(1, 2);
+            tc.swap(1, 2);
+            area = -area;
+        }
+
+        let filter_linear = is_linear_filter(texture, &tc, area);
+
+        let x_start = p.iter().map(|v| v[0]).fold(f32::MAX, f32::min).floor() as i32;
+        let x_end = p.iter().map(|v| v[0]).fold(f32::MIN, f32::max).ceil() as i32;
+        let y_start = p.iter().map(|v| v[1]).fold(f32::MAX, f32::min).floor() as i32;
+        let y_end = p.iter().map(|v| v[1]).fold(f32::MIN, f32::max).ceil() as i32;
+
+        for y in y_start.max(min_y)..y_end.min(max_y) {
+            for x in x_start.max(min_x)..x_end.min(max_x) {
+                let center = [x as f32 + 0.5, y as f32 + 0.5];
+                let w0 = edge(p[1], p[2], center);
+                let w1 = edge(p[2], p[0], center);
+                let w2 = edge(p[0], p[1], center);
+
+                if !covers(w0, p[1], p[2]) || !covers(w1, p[2], p[0]) || !covers(w2, p[0], p[1]) {
+                    continue;
+                }
+
+                let (w0, w1, w2) = (w0 / area, w1 / area, w2 / area);
+                let u = w0 * tc[0][0] + w1 * tc[1][0] + w2 * tc[2][0];
+                let v = w0 * tc[0][1] + w1 * tc[1][1] + w2 * tc[2][1];
+
+                let tex_color = sample(&texture.image, u, v, filter_linear);
+                let src = shade(tex_color, draw_list);
+                let dst = target.get_pixel_mut(x as u32, y as u32);
+                blend(dst, src);
+            }
+        }
+    }
+}
+
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+// pixels exactly on an edge shared by two triangles are only drawn by one of them, so
+// that quads do not blend their diagonal twice
+fn covers(w: f32, a: [f32; 2], b: [f32; 2]) -> bool {
+    if w != 0.0 {
+        return w > 0.0;
+    }
+
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    dy < 0.0 || (dy == 0.0 && dx > 0.0)
+}
+
+// Chooses between the texture minify and magnify filters by comparing the texel
+// area covered by the triangle with its pixel area
+fn is_linear_filter(texture: &SoftwareTexture, tc: &[[f32; 2]; 3], area: f32) -> bool {
+    let (tex_w, tex_h) = texture.image.dimensions();
+    let texel = |t: [f32; 2]| [t[0] * tex_w as f32, t[1] * tex_h as f32];
+    let texel_area = edge(texel(tc[0]), texel(tc[1]), texel(tc[2])).abs();
+
+    if texel_area > area {
+        use TextureMinFilter::*;
+        matches!(
+            texture.min_filter,
+            Linear | LinearMipmapNearest | LinearMipmapLinear
+        )
+    } else {
+        matches!(texture.mag_filter, TextureMagFilter::Linear)
+    }
+}
+
+fn texel(image: &RgbaImage, x: i32, y: i32) -> [f32; 4] {
+    let x = x.clamp(0, image.width() as i32 - 1) as u32;
+    let y = y.clamp(0, image.height() as i32 - 1) as u32;
+    let pixel = image.get_pixel(x, y).0;
+    [
+        pixel[0] as f32 / 255.0,
+        pixel[1] as f32 / 255.0,
+        pixel[2] as f32 / 255.0,
+        pixel[3] as f32 / 255.0,
+    ]
+}
+
+fn sample(image: &RgbaImage, u: f32, v: f32, linear: bool) -> [f32; 4] {
+    let x = u * image.width() as f32;
+    let y = v * image.height() as f32;
+
+    if !linear {
+        return texel(image, x.floor() as i32, y.floor() as i32);
+    }
+
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+
+    let c00 = texel(image, x0, y0);
+    let c10 = texel(image, x0 + 1, y0);
+    let c01 = texel(image, x0, y0 + 1);
+    let c11 = texel(image, x0 + 1, y0 + 1);
+
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let top = c00[i] * (1.0 - fx) + c10[i] * fx;
+        let bottom = c01[i] * (1.0 - fx) + c11[i] * fx;
+        out[i] = top * (1.0 - fy) + bottom * fy;
+    }
+    out
+}
+
+// equivalent of the base and color swap fragment shaders
+fn shade(tex: [f32; 4], draw_list: &DrawList) -> [f32; 4] {
+    let filter = draw_list.color_filter;
+
+    if draw_list.color_swap_enabled {
+        let (hue, sat, val) = rgb_to_hsv(tex[0], tex[1], tex[2]);
+        if hue < 0.9 && hue > 0.8 {
+            let (r, g, b) = hsv_to_rgb(draw_list.swap_hue, sat, val);
+            return [r, g, b, tex[3]];
+        }
+
+        return [
+            filter[0] * tex[0],
+            filter[1] * tex[1],
+            filter[2] * tex[2],
+            filter[3] * tex[3],
+        ];
+    }
+
+    let sec = draw_list.color_sec;
+    [
+        filter[0] * tex[0] + sec[0],
+        filter[1] * tex[1] + sec[1],
+        filter[2] * tex[2] + sec[2],
+        filter[3] * tex[3] + sec[3],
+    ]
+}
+
+// standard alpha blending, applied to both the color and alpha channels
+fn blend(dst: &mut Rgba<u8>, src: [f32; 4]) {
+    let alpha = src[3].clamp(0.0, 1.0);
+    for (d, s) in dst.0.iter_mut().zip(src.iter()) {
+        let out = s.clamp(0.0, 1.0) * alpha + (*d as f32 / 255.0) * (1.0 - alpha);
+        *d = (out * 255.0).round() as u8;
+    }
+}
+
+fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+
+    let sat = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, sat, max)
+}
+
+fn hsv_to_rgb(hue: f32, sat: f32, val: f32) -> (f32, f32, f32) {
+    let channel = |offset: f32| {
+        let p = ((hue + offset).fract() * 6.0 - 3.0).abs();
+        val * (1.0 + sat * ((p - 1.0).clamp(0.0, 1.0) - 1.0))
+    };
+
+    (channel(1.0), channel(2.0 / 3.0), channel(1.0 / 3.0))
+}
@@ -57,6 +57,53 @@ use crate::util::{self, invalid_data_error};
 
 thread_local! {
     static RESOURCE_SET: RefCell<ResourceSet> = RefCell::new(ResourceSet::default());
+    static RESOURCE_ERRORS: RefCell<Option<Vec<ResourceError>>> = const { RefCell::new(None) };
+}
+
+/// A problem found with a resource, either while loading or when validating its
+/// references to other resources.
+#[derive(Debug, Clone)]
+pub struct ResourceError {
+    pub kind: String,
+    pub id: String,
+    pub files: Vec<String>,
+    pub message: String,
+}
+
+/// Starts recording resources that fail to load, in addition to the normal log
+/// warnings.  Recording continues until `take_resource_errors` is called.  While
+/// recording, resources that cannot be parsed are skipped rather than aborting the
+/// load, so that all errors may be reported at once.
+pub fn record_resource_errors() {
+    RESOURCE_ERRORS.with(|errors| *errors.borrow_mut() = Some(Vec::new()));
+}
+
+pub fn is_recording_resource_errors() -> bool {
+    RESOURCE_ERRORS.with(|errors| errors.borrow().is_some())
+}
+
+/// The number of errors recorded so far, or zero if not recording
+pub fn resource_error_count() -> usize {
+    RESOURCE_ERRORS.with(|errors| errors.borrow().as_ref().map_or(0, |e| e.len()))
+}
+
+/// Returns all errors recorded since `record_resource_errors` and stops recording
+pub fn take_resource_errors() -> Vec<ResourceError> {
+    RESOURCE_ERRORS.with(|errors| errors.borrow_mut().take().unwrap_or_default())
+}
+
+/// Records an error for the specified resource, if recording is enabled
+pub fn add_resource_error(kind: &str, id: &str, files: &[String], message: String) {
+    RESOURCE_ERRORS.with(|errors| {
+        if let Some(errors) = errors.borrow_mut().as_mut() {
+            errors.push(ResourceError {
+                kind: kind.to_string(),
+                id: id.to_string(),
+                files: files.to_vec(),
+                message,
+            });
+        }
+    });
 }
 
 #[derive(Default)]
@@ -398,6 +445,7 @@ fn insert_if_ok_boxed<K: Eq + Hash + Display, V: ?Sized>(
 fn warn_on_insert<K: Display>(type_str: &str, key: K, error: Error) {
     warn!("Error in {} with id '{}'", type_str, key);
     warn!("{}", error);
+    add_resource_error(type_str, &key.to_string(), &[], error.to_string());
 }
 
 pub fn subdirs<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, Error> {
@@ -130,7 +130,11 @@ fn read_builders_internal<T: serde::de::DeserializeOwned>(
             let builder: T = match read_builder_internal(entry) {
                 Err(e) => {
                     warn!("Error in YAML file merged from {:?}", files);
-                    return Err(e);
+                    if !is_recording_resource_errors() {
+                        return Err(e);
+                    }
+                    add_resource_error(&format!("{kind:?}"), &id, &files, e.to_string());
+                    continue;
                 }
                 Ok(val) => val,
             };
@@ -309,6 +313,7 @@ fn read_file_to_string(path: PathBuf, resources: &mut HashMap<String, String>) {
         Ok(data) => data,
         Err(e) => {
             warn!("Error reading file at '{}': {}", path_str, e);
+            add_resource_error("file", "", std::slice::from_ref(&path_str), e.to_string());
             return;
         }
     };
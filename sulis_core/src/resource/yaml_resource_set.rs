@@ -14,12 +14,14 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::Error;
 use std::path::Path;
 
-use serde_yaml::{self, Value};
+use serde_yaml::{self, Mapping, Value};
+
+use crate::resource::add_resource_error;
 
 /// A set of resources that have been parsed into YAML values.  This is built up
 /// by first reading the bottom level "data" layer, then the module layer, then
@@ -27,6 +29,89 @@ use serde_yaml::{self, Value};
 /// adding new resources or keys to already existing resources.
 pub struct YamlResourceSet {
     pub resources: HashMap<YamlResourceKind, HashMap<String, Value>>,
+
+    /// For each resource that was merged, the files that changed each of its keys
+    pub provenance: HashMap<YamlResourceKind, HashMap<String, ResourceProvenance>>,
+
+    /// Keys that were set by more than one mod, overriding each other
+    pub conflicts: Vec<String>,
+
+    layer: usize,
+}
+
+/// The index of the first mod layer.  The base data is layer 0, and the
+/// campaign is layer 1.
+pub const FIRST_MOD_LAYER: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MergeOp {
+    Set,
+    Append,
+    Patch,
+    Remove,
+    Clear,
+}
+
+/// A single change to a key of a resource, made while merging a file into it
+#[derive(Clone, Debug)]
+pub struct KeySource {
+    pub file: String,
+    pub layer: usize,
+    pub op: MergeOp,
+}
+
+/// Records which files changed each key of a resource after the file first
+/// defining it.  Keys are paths of mapping keys separated by `/`, with the
+/// empty path being the resource itself.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceProvenance {
+    pub keys: BTreeMap<String, Vec<KeySource>>,
+}
+
+impl ResourceProvenance {
+    /// Describes the files that set or appended to the key at `path`, or `None`
+    /// if the key is unchanged from the file first defining the resource
+    pub fn describe(&self, path: &str) -> Option<String> {
+        let sources = self.keys.get(path)?;
+
+        let mut parts = Vec::new();
+        let last_set = sources
+            .iter()
+            .rposition(|s| !matches!(s.op, MergeOp::Append | MergeOp::Patch));
+        if let Some(index) = last_set {
+            let source = &sources[index];
+            let verb = match source.op {
+                MergeOp::Remove => "removed",
+                MergeOp::Clear => "cleared",
+                _ => "set",
+            };
+            let mut part = format!("{} by {}", verb, source.file);
+            if let Some(prev) = sources[..index].last() {
+                part.push_str(&format!(" (overrides {})", prev.file));
+            }
+            parts.push(part);
+        }
+
+        let rest = &sources[last_set.map_or(0, |i| i + 1)..];
+        for (verb, op) in [("patched", MergeOp::Patch), ("appended", MergeOp::Append)] {
+            let mut files: Vec<&str> = Vec::new();
+            for source in rest.iter().filter(|s| s.op == op) {
+                if !files.contains(&source.file.as_str()) {
+                    files.push(&source.file);
+                }
+            }
+
+            if !files.is_empty() {
+                parts.push(format!("{} by {}", verb, files.join(", ")));
+            }
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("; "))
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -81,6 +166,15 @@ impl YamlResourceKind {
         YamlResourceKind::from_str(&path_str)
     }
 
+    /// Returns the kind of resource read from the specified directory, such as
+    /// `abilities` or `images/simple`
+    pub fn from_dir_name(name: &str) -> Option<YamlResourceKind> {
+        match YamlResourceKind::from_str(name) {
+            Some(YamlResourceKind::Skip) => None,
+            kind => kind,
+        }
+    }
+
     fn from_str(s: &str) -> Option<YamlResourceKind> {
         use self::YamlResourceKind::*;
         Some(match s {
@@ -119,8 +213,17 @@ impl YamlResourceKind {
 }
 
 impl YamlResourceSet {
+    fn empty() -> YamlResourceSet {
+        YamlResourceSet {
+            resources: HashMap::new(),
+            provenance: HashMap::new(),
+            conflicts: Vec::new(),
+            layer: 0,
+        }
+    }
+
     pub fn new(data_dir: &Path) -> Result<YamlResourceSet, Error> {
-        let mut resources = HashMap::new();
+        let mut set = YamlResourceSet::empty();
 
         debug!(
             "Parsing YAML in '{}'",
@@ -131,10 +234,36 @@ impl YamlResourceSet {
             data_dir,
             data_dir,
             Some(YamlResourceKind::TopLevel),
-            &mut resources,
+            &mut set,
         );
 
-        Ok(YamlResourceSet { resources })
+        Ok(set)
+    }
+
+    /// Reads the first directory and then appends each of the others in order
+    pub fn from_dirs(dirs: &[String]) -> Result<YamlResourceSet, Error> {
+        let mut set = YamlResourceSet::new(Path::new(&dirs[0]))?;
+        for dir in dirs.iter().skip(1) {
+            set.append(Path::new(dir));
+        }
+        Ok(set)
+    }
+
+    /// Reads only the specified resource subdirectories, such as `abilities`, from
+    /// each of the directories in order
+    pub fn from_subdirs(dirs: &[String], subdirs: &[&str]) -> YamlResourceSet {
+        let mut set = YamlResourceSet::empty();
+        for (layer, dir) in dirs.iter().enumerate() {
+            set.layer = layer;
+            let top_level = Path::new(dir);
+            for subdir in subdirs {
+                let kind = YamlResourceKind::from_dir_name(subdir);
+                if kind.is_some() {
+                    read_recursive(&top_level.join(subdir), top_level, kind, &mut set);
+                }
+            }
+        }
+        set
     }
 
     pub fn append(&mut self, dir: &Path) {
@@ -143,20 +272,147 @@ impl YamlResourceSet {
             dir.to_string_lossy().to_string()
         );
 
-        read_recursive(
-            dir,
-            dir,
-            Some(YamlResourceKind::TopLevel),
-            &mut self.resources,
-        );
+        self.layer += 1;
+        read_recursive(dir, dir, Some(YamlResourceKind::TopLevel), self);
+    }
+
+    /// Returns the merged YAML of the specified resource, with comments noting
+    /// each key that was changed by a file other than the one first defining it.
+    pub fn dump_annotated(&self, kind: YamlResourceKind, id: &str) -> Option<String> {
+        let value = self.resources.get(&kind)?.get(id)?;
+        let empty = ResourceProvenance::default();
+        let provenance = self
+            .provenance
+            .get(&kind)
+            .and_then(|resources| resources.get(id))
+            .unwrap_or(&empty);
+
+        let mut lines = Vec::new();
+        if let Some(Value::Sequence(files)) = value.get(FILE_VAL_STR) {
+            for (index, file) in files.iter().filter_map(|f| f.as_str()).enumerate() {
+                let verb = if index == 0 { "defined in" } else { "merged from" };
+                lines.push(format!("# {verb} {file}"));
+            }
+        }
+
+        if let Some(desc) = provenance.describe("") {
+            lines.push(format!("# all keys {desc}"));
+        }
+
+        for (path, sources) in provenance.keys.iter() {
+            match sources.last() {
+                Some(source) if source.op == MergeOp::Remove => {
+                    lines.push(format!("# '{}' removed by {}", path, source.file));
+                }
+                _ => (),
+            }
+        }
+
+        match value {
+            Value::Mapping(map) => dump_mapping(&mut lines, map, 0, Some(""), provenance),
+            _ => lines.push(scalar_str(value)),
+        }
+
+        lines.push(String::new());
+        Some(lines.join("\n"))
+    }
+}
+
+fn dump_mapping(
+    lines: &mut Vec<String>,
+    map: &Mapping,
+    indent: usize,
+    path: Option<&str>,
+    provenance: &ResourceProvenance,
+) {
+    let pad = " ".repeat(indent);
+    for (key, value) in map {
+        let key_path = path.map(|path| join_path(path, &key_str(key)));
+        if path == Some("") {
+            if let Some(FILE_VAL_STR) | Some(DIRECTORY_VAL_STR) = key.as_str() {
+                continue;
+            }
+        }
+
+        let note = match key_path.as_ref().and_then(|p| provenance.describe(p)) {
+            None => String::new(),
+            Some(desc) => format!("  # {desc}"),
+        };
+        let key = scalar_str(key);
+
+        match value {
+            Value::Mapping(map) if !map.is_empty() => {
+                lines.push(format!("{pad}{key}:{note}"));
+                dump_mapping(lines, map, indent + 2, key_path.as_deref(), provenance);
+            }
+            Value::Sequence(seq) if !seq.is_empty() => {
+                lines.push(format!("{pad}{key}:{note}"));
+                dump_sequence(lines, seq, indent + 2, provenance);
+            }
+            _ => lines.push(format!("{pad}{key}: {}{note}", scalar_str(value))),
+        }
+    }
+}
+
+fn dump_sequence(
+    lines: &mut Vec<String>,
+    seq: &serde_yaml::Sequence,
+    indent: usize,
+    provenance: &ResourceProvenance,
+) {
+    for value in seq {
+        let start = lines.len();
+        match value {
+            Value::Mapping(map) if !map.is_empty() => {
+                dump_mapping(lines, map, indent + 2, None, provenance);
+            }
+            Value::Sequence(seq) if !seq.is_empty() => {
+                dump_sequence(lines, seq, indent + 2, provenance);
+            }
+            _ => lines.push(format!("{}  {}", " ".repeat(indent), scalar_str(value))),
+        }
+        lines[start].replace_range(indent..indent + 2, "- ");
+    }
+}
+
+fn key_str(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.to_string(),
+        _ => scalar_str(key),
+    }
+}
+
+fn scalar_str(value: &Value) -> String {
+    if let Value::String(s) = value {
+        if s.contains('\n') {
+            return serde_json::to_string(s).unwrap_or_default();
+        }
+    }
+
+    let out = serde_yaml::to_string(value).unwrap_or_default();
+    out.trim_start_matches("---").trim().to_string()
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}/{key}")
     }
 }
 
+/// Returns true if `path` is `parent` or is nested somewhere inside it
+fn is_within(path: &str, parent: &str) -> bool {
+    parent.is_empty()
+        || path == parent
+        || (path.starts_with(parent) && path[parent.len()..].starts_with('/'))
+}
+
 fn read_recursive(
     dir: &Path,
     top_level: &Path,
     kind: Option<YamlResourceKind>,
-    resources: &mut HashMap<YamlResourceKind, HashMap<String, Value>>,
+    set: &mut YamlResourceSet,
 ) {
     let dir_str = dir.to_string_lossy().to_string();
     let dir_entries = match fs::read_dir(dir) {
@@ -189,7 +445,7 @@ fn read_recursive(
                 Some(kind) => Some(kind),
             };
 
-            read_recursive(&path, top_level, next_kind, resources);
+            read_recursive(&path, top_level, next_kind, set);
         } else if path.is_file() {
             match kind {
                 None => {
@@ -199,7 +455,7 @@ fn read_recursive(
                     );
                 }
                 Some(kind) => {
-                    read_file(&dir_str, &path, kind, resources);
+                    read_file(&dir_str, &path, kind, set);
                 }
             }
         }
@@ -210,7 +466,7 @@ fn read_file(
     dir_str: &str,
     path: &Path,
     kind: YamlResourceKind,
-    resources: &mut HashMap<YamlResourceKind, HashMap<String, Value>>,
+    set: &mut YamlResourceSet,
 ) {
     let path_str = path.to_string_lossy().to_string();
 
@@ -223,6 +479,7 @@ fn read_file(
         Ok(data) => data,
         Err(e) => {
             warn!("Error reading file at '{}': {}", path_str, e);
+            add_resource_error("file", "", std::slice::from_ref(&path_str), e.to_string());
             return;
         }
     };
@@ -232,6 +489,7 @@ fn read_file(
         Err(e) => {
             warn!("Error parsing '{}' as YAML:", path_str);
             warn!("{}", e);
+            add_resource_error("file", "", std::slice::from_ref(&path_str), e.to_string());
             return;
         }
     };
@@ -241,21 +499,32 @@ fn read_file(
             Value::String(ref s) => s.to_string(),
             _ => {
                 warn!("Top level ID is not a string in '{}'", path_str);
+                add_resource_error("file", "", std::slice::from_ref(&path_str), "Top level ID is not a string".to_string());
                 return;
             }
         },
         None => {
             warn!("Unable to extract top level ID from '{}'", path_str);
+            add_resource_error("file", "", std::slice::from_ref(&path_str), "No top level ID".to_string());
             return;
         }
     };
 
-    let map = resources.entry(kind).or_default();
+    let map = set.resources.entry(kind).or_default();
     // use of entry API here seems to require us to clone our value since
     // we want to either append it or insert it
     //map.entry(id).and_modify(|entry| merge_doc(entry, value)).or_insert(value);
     if let Some(ref mut entry) = map.get_mut(&id) {
-        merge_doc(dir_str, &path_str, entry, value);
+        let provenance = set.provenance.entry(kind).or_default();
+        let mut merge = Merge {
+            name: &path_str,
+            kind,
+            id: &id,
+            layer: set.layer,
+            provenance: provenance.entry(id.clone()).or_default(),
+            conflicts: &mut set.conflicts,
+        };
+        merge_doc(&mut merge, entry, value);
         return;
     }
 
@@ -286,7 +555,68 @@ fn read_file(
 pub const DIRECTORY_VAL_STR: &str = "__directory__";
 pub const FILE_VAL_STR: &str = "__file__";
 
-fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value) {
+/// The file currently being merged into an existing resource
+struct Merge<'a> {
+    name: &'a str,
+    kind: YamlResourceKind,
+    id: &'a str,
+    layer: usize,
+    provenance: &'a mut ResourceProvenance,
+    conflicts: &'a mut Vec<String>,
+}
+
+impl Merge<'_> {
+    fn record(&mut self, path: &str, op: MergeOp) {
+        if !matches!(op, MergeOp::Append | MergeOp::Patch) {
+            self.check_conflict(path);
+            self.provenance
+                .keys
+                .retain(|key, _| key == path || !is_within(key, path));
+        }
+
+        self.provenance
+            .keys
+            .entry(path.to_string())
+            .or_default()
+            .push(KeySource {
+                file: self.name.to_string(),
+                layer: self.layer,
+                op,
+            });
+    }
+
+    /// Checks whether a key set by a different mod is about to be overridden
+    fn check_conflict(&mut self, path: &str) {
+        if self.layer < FIRST_MOD_LAYER {
+            return;
+        }
+
+        let other = self
+            .provenance
+            .keys
+            .iter()
+            .filter(|(key, _)| is_within(key, path) || is_within(path, key))
+            .filter_map(|(_, sources)| sources.last())
+            .find(|source| {
+                source.layer >= FIRST_MOD_LAYER
+                    && source.layer != self.layer
+                    && matches!(source.op, MergeOp::Set | MergeOp::Append | MergeOp::Patch)
+            });
+
+        if let Some(other) = other {
+            let key = if path.is_empty() { "(all keys)" } else { path };
+            let message = format!(
+                "{:?} '{}' key '{}' from '{}' overrides the value from '{}'",
+                self.kind, self.id, key, self.name, other.file
+            );
+            warn!("{}", message);
+            self.conflicts.push(message);
+        }
+    }
+}
+
+fn merge_doc(merge: &mut Merge, base: &mut Value, append: Value) {
+    let name = merge.name;
     let directory_val = Value::String(DIRECTORY_VAL_STR.to_string());
     let file_val = Value::String(FILE_VAL_STR.to_string());
 
@@ -306,7 +636,11 @@ fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value) {
             }
 
             match append {
-                Value::Mapping(append) => merge_map(dir, name, mapping, append),
+                Value::Mapping(mut append) => {
+                    // the ID always matches and is not worth tracking
+                    append.remove(&Value::String("id".to_string()));
+                    merge_map(merge, "", mapping, append)
+                }
                 _ => warn!(
                     "Unable to append '{}' to base YAML as it is not a mapping",
                     name
@@ -320,12 +654,8 @@ fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value) {
     }
 }
 
-fn merge_map(
-    dir: &str,
-    name: &str,
-    map: &mut serde_yaml::Mapping,
-    mut append: serde_yaml::Mapping,
-) {
+fn merge_map(merge: &mut Merge, path: &str, map: &mut Mapping, mut append: Mapping) {
+    let name = merge.name;
     let clear_base_keys: Value = Value::String("clear_base_keys".to_string());
     let remove_base_keys: Value = Value::String("remove_base_keys".to_string());
 
@@ -334,6 +664,7 @@ fn merge_map(
             Value::Bool(val) => {
                 if val {
                     map.clear();
+                    merge.record(path, MergeOp::Clear);
                 }
             }
             _ => warn!("clear_base_keys must be a boolean in '{}'", name),
@@ -344,7 +675,9 @@ fn merge_map(
         match remove {
             Value::Sequence(seq) => {
                 for value in seq {
-                    map.remove(&value);
+                    if map.remove(&value).is_some() {
+                        merge.record(&join_path(path, &key_str(&value)), MergeOp::Remove);
+                    }
                 }
             }
             _ => warn!(
@@ -355,19 +688,21 @@ fn merge_map(
     }
 
     for (key, value) in append {
+        let key_path = join_path(path, &key_str(&key));
         if let Some(ref mut base) = map.get_mut(&key) {
             match base {
                 Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => (),
                 Value::Sequence(ref mut seq) => {
                     match value {
-                        Value::Sequence(append) => merge_sequence(dir, name, seq, append),
+                        Value::Sequence(append) => merge_sequence(merge, &key_path, seq, append),
+                        Value::Mapping(patch) => patch_sequence(merge, &key_path, seq, patch),
                         _ => warn!("Expected sequence for '{:?}' in '{}'", key, name),
                     }
                     continue;
                 }
                 Value::Mapping(ref mut map) => {
                     match value {
-                        Value::Mapping(append) => merge_map(dir, name, map, append),
+                        Value::Mapping(append) => merge_map(merge, &key_path, map, append),
                         _ => warn!("Expected mapping for '{:?}' in '{}'", key, name),
                     }
                     continue;
@@ -376,16 +711,191 @@ fn merge_map(
         }
 
         map.insert(key, value);
+        merge.record(&key_path, MergeOp::Set);
     }
 }
 
 fn merge_sequence(
-    _dir: &str,
-    _name: &str,
+    merge: &mut Merge,
+    path: &str,
     seq: &mut serde_yaml::Sequence,
     append: serde_yaml::Sequence,
 ) {
+    if !append.is_empty() {
+        merge.record(path, MergeOp::Append);
+    }
+
     for value in append {
         seq.push(value);
     }
 }
+
+/// Applies a mapping of patch directives to a base sequence.  The directives are
+/// applied in this order:
+/// - `replace`: a sequence which replaces the base sequence entirely
+/// - `remove`: a sequence of values; each base entry equal to one of them is removed.
+///   A mapping value matches any mapping entry containing all of its keys and values.
+/// - `merge_by_key` and `merge`: each mapping in `merge` is merged into the base
+///   entry with the same value for the `merge_by_key` key, or appended if there is none
+/// - `insert_at` and `insert`: the values in `insert` are inserted at the index
+/// - `append`: the values are appended, the same as specifying a plain sequence
+fn patch_sequence(
+    merge: &mut Merge,
+    path: &str,
+    seq: &mut serde_yaml::Sequence,
+    mut patch: Mapping,
+) {
+    let name = merge.name;
+    let mut take = |directive: &'static str| {
+        let value = patch.remove(&Value::String(directive.to_string()));
+        (directive, value)
+    };
+
+    let replace = directive_sequence(name, take("replace"));
+    let remove = directive_sequence(name, take("remove"));
+    let merge_key = take("merge_by_key").1;
+    let merge_entries = directive_sequence(name, take("merge"));
+    let insert_at = take("insert_at").1;
+    let insert = directive_sequence(name, take("insert"));
+    let append = directive_sequence(name, take("append"));
+
+    for (key, _) in patch {
+        warn!(
+            "Unknown sequence patch directive '{}' for '{}' in '{}'",
+            key_str(&key),
+            path,
+            name
+        );
+    }
+
+    if let Some(replace) = replace {
+        *seq = replace;
+        merge.record(path, MergeOp::Set);
+    }
+
+    let mut patched = false;
+    for pattern in remove.unwrap_or_default() {
+        let len = seq.len();
+        seq.retain(|value| !matches_pattern(value, &pattern));
+        if seq.len() == len {
+            warn!(
+                "No entry matching {:?} to remove from '{}' in '{}'",
+                pattern, path, name
+            );
+        }
+        patched = true;
+    }
+
+    if let Some(entries) = merge_entries {
+        match merge_key {
+            Some(key @ Value::String(_)) => {
+                for entry in entries {
+                    merge_sequence_entry(merge, path, seq, &key, entry);
+                }
+                patched = true;
+            }
+            _ => warn!(
+                "merge_by_key must be specified as a string to merge into '{}' in '{}'",
+                path, name
+            ),
+        }
+    }
+
+    if let Some(values) = insert {
+        match insert_at.as_ref().and_then(|index| index.as_u64()) {
+            None => warn!(
+                "insert_at must be specified as an index to insert into '{}' in '{}'",
+                path, name
+            ),
+            Some(index) => {
+                let mut index = index as usize;
+                if index > seq.len() {
+                    warn!(
+                        "insert_at {} is past the end of '{}' in '{}'",
+                        index, path, name
+                    );
+                    index = seq.len();
+                }
+
+                seq.splice(index..index, values);
+                patched = true;
+            }
+        }
+    }
+
+    if patched {
+        merge.record(path, MergeOp::Patch);
+    }
+
+    if let Some(append) = append {
+        merge_sequence(merge, path, seq, append);
+    }
+}
+
+fn directive_sequence(
+    name: &str,
+    (directive, value): (&str, Option<Value>),
+) -> Option<serde_yaml::Sequence> {
+    match value? {
+        Value::Sequence(seq) => Some(seq),
+        _ => {
+            warn!("'{}' must be a sequence in '{}'", directive, name);
+            None
+        }
+    }
+}
+
+fn merge_sequence_entry(
+    merge: &mut Merge,
+    path: &str,
+    seq: &mut serde_yaml::Sequence,
+    key: &Value,
+    entry: Value,
+) {
+    let mut entry = match entry {
+        Value::Mapping(entry) => entry,
+        _ => {
+            warn!("Entries to merge into '{}' in '{}' must be mappings", path, merge.name);
+            return;
+        }
+    };
+
+    let key_value = match entry.get(key) {
+        None => {
+            warn!(
+                "Entry to merge into '{}' in '{}' is missing the key '{}'",
+                path,
+                merge.name,
+                key_str(key)
+            );
+            return;
+        }
+        Some(value) => value.clone(),
+    };
+
+    let base = seq.iter_mut().find_map(|value| match value {
+        Value::Mapping(map) if map.get(key) == Some(&key_value) => Some(map),
+        _ => None,
+    });
+
+    match base {
+        None => seq.push(Value::Mapping(entry)),
+        Some(base) => {
+            // the matched key is equal and not worth tracking
+            entry.remove(key);
+            let entry_path = format!("{}={}", key_str(key), key_str(&key_value));
+            merge_map(merge, &join_path(path, &entry_path), base, entry);
+        }
+    }
+}
+
+/// Returns true if `value` is equal to `pattern`, or if both are mappings and
+/// every key in `pattern` has an equal value in `value`
+fn matches_pattern(value: &Value, pattern: &Value) -> bool {
+    match (value, pattern) {
+        (Value::Mapping(value), Value::Mapping(pattern)) => pattern
+            .iter()
+            .all(|(key, pattern_value)| value.get(key) == Some(pattern_value)),
+        _ => value == pattern,
+    }
+}
@@ -22,6 +22,7 @@ pub use self::size::Size;
 
 use serde::{Serialize, Deserialize};
 
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::f32;
 use std::fmt;
@@ -68,7 +69,12 @@ pub fn approx_eq(a: f32, b: f32) -> bool {
     i32::abs(a_int - b_int) <= MAX_ULPS
 }
 
-#[derive(Clone)]
+thread_local! {
+    static GAME_RAND: RefCell<ReproducibleRandom> = RefCell::new(ReproducibleRandom::new(None));
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RandomSaveState", into = "RandomSaveState")]
 pub struct ReproducibleRandom {
     seed: u128,
     gen: Pcg64Mcg,
@@ -101,6 +107,34 @@ impl ReproducibleRandom {
     }
 }
 
+// both the seed and generator state are u128, which serde_yaml does not handle correctly,
+// so they are stored as strings
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RandomSaveState {
+    seed: String,
+    state: String,
+}
+
+impl From<ReproducibleRandom> for RandomSaveState {
+    fn from(rand: ReproducibleRandom) -> RandomSaveState {
+        RandomSaveState {
+            seed: rand.seed.to_string(),
+            state: serde_json::to_string(&rand.gen).unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<RandomSaveState> for ReproducibleRandom {
+    type Error = String;
+
+    fn try_from(save: RandomSaveState) -> Result<ReproducibleRandom, String> {
+        let seed = save.seed.parse::<u128>().map_err(|e| e.to_string())?;
+        let gen = serde_json::from_str(&save.state).map_err(|e| e.to_string())?;
+        Ok(ReproducibleRandom { seed, gen })
+    }
+}
+
 impl std::fmt::Debug for ReproducibleRandom {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let state = serde_json::to_string(&self.gen).map_err(|_| std::fmt::Error)?;
@@ -108,11 +142,30 @@ impl std::fmt::Debug for ReproducibleRandom {
     }
 }
 
+/// Sets the random stream used for all game rolls (attacks, damage, initiative, loot, etc)
+/// via `gen_rand` and `shuffle`.  The game state sets this on a new game or a load so that
+/// the same actions produce the same results.
+pub fn set_game_rand(rand: ReproducibleRandom) {
+    GAME_RAND.with(|r| *r.borrow_mut() = rand);
+}
+
+/// Returns a copy of the current state of the game random stream, suitable for saving.
+pub fn game_rand() -> ReproducibleRandom {
+    GAME_RAND.with(|r| r.borrow().clone())
+}
+
 pub fn shuffle<T>(values: &mut [T]) {
-    values.shuffle(&mut rand::thread_rng());
+    GAME_RAND.with(|r| r.borrow_mut().shuffle(values));
 }
 
 pub fn gen_rand<T: SampleUniform + PartialOrd>(min: T, max: T) -> T {
+    GAME_RAND.with(|r| r.borrow_mut().gen(min, max))
+}
+
+/// Generates a random value that does not affect game state, such as for particle
+/// effects or screen shake.  This does not draw from the game random stream, so the
+/// number of frames rendered can't change the results of game rolls.
+pub fn gen_cosmetic_rand<T: SampleUniform + PartialOrd>(min: T, max: T) -> T {
     rand::thread_rng().gen_range(min..max)
 }
 
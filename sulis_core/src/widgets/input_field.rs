@@ -130,6 +130,8 @@ impl WidgetKind for InputField {
             '\u{8}' => {
                 self.text.pop();
             }
+            // tab is used for completion rather than entered as text
+            '\t' => return true,
             _ => {
                 if self.label.borrow().text_draw_end_x > widget.borrow().state.inner_right() as f32
                 {
@@ -32,7 +32,7 @@ pub struct AreaModel {
     pub config: EditorConfig,
 
     tiles: TilesModel,
-    actors: Vec<(Point, Rc<Actor>, Option<String>)>,
+    actors: Vec<(Point, Rc<Actor>, Option<String>, Option<ActorBehavior>)>,
     props: Vec<PropData>,
     encounters: Vec<EncounterData>,
     transitions: Vec<Transition>,
@@ -167,16 +167,22 @@ impl AreaModel {
         });
     }
 
+    /// Places the actor at `x`, `y`.  An actor already placed at exactly that
+    /// location is replaced, keeping its unique ID and behavior.
     pub fn add_actor(&mut self, actor: Rc<Actor>, x: i32, y: i32) {
         if x < 0 || y < 0 {
             return;
         }
 
-        self.actors.push((Point::new(x, y), actor, None));
+        let location = Point::new(x, y);
+        match self.actor_index_at(location) {
+            Some(index) => self.actors[index].1 = actor,
+            None => self.actors.push((location, actor, None, None)),
+        }
     }
 
     pub fn remove_actors_within(&mut self, x: i32, y: i32, width: i32, height: i32) {
-        self.actors.retain(|&(pos, ref actor, _)| {
+        self.actors.retain(|&(pos, ref actor, _, _)| {
             !is_removal(
                 pos,
                 actor.race.size.width,
@@ -189,6 +195,48 @@ impl AreaModel {
         });
     }
 
+    /// Returns the index of the actor covering the square at `x`, `y`, if any
+    pub fn actor_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.actors.iter().position(|(pos, actor, _, _)| {
+            is_removal(
+                *pos,
+                actor.race.size.width,
+                actor.race.size.height,
+                x,
+                y,
+                1,
+                1,
+            )
+        })
+    }
+
+    /// Returns the index of the actor placed exactly at `location`, if any
+    pub fn actor_index_at(&self, location: Point) -> Option<usize> {
+        self.actors.iter().position(|(pos, _, _, _)| *pos == location)
+    }
+
+    pub fn actor_location(&self, index: usize) -> Option<Point> {
+        self.actors.get(index).map(|(pos, _, _, _)| *pos)
+    }
+
+    pub fn actor_behavior(&self, index: usize) -> Option<&ActorBehavior> {
+        self.actors.get(index).and_then(|(_, _, _, behavior)| behavior.as_ref())
+    }
+
+    /// Returns the behavior of the actor at `index`, creating an empty one if
+    /// it has none
+    pub fn actor_behavior_mut(&mut self, index: usize) -> Option<&mut ActorBehavior> {
+        self.actors
+            .get_mut(index)
+            .map(|(_, _, _, behavior)| behavior.get_or_insert_with(ActorBehavior::default))
+    }
+
+    pub fn clear_actor_behavior(&mut self, index: usize) {
+        if let Some((_, _, _, behavior)) = self.actors.get_mut(index) {
+            *behavior = None;
+        }
+    }
+
     pub fn actors_within(
         &self,
         x: i32,
@@ -197,7 +245,7 @@ impl AreaModel {
         height: i32,
     ) -> Vec<(Point, Rc<Actor>)> {
         let mut actors = Vec::new();
-        for &(pos, ref actor, _) in self.actors.iter() {
+        for &(pos, ref actor, _, _) in self.actors.iter() {
             if !is_removal(
                 pos,
                 actor.race.size.width,
@@ -399,7 +447,7 @@ impl AreaModel {
             renderer.draw(draw_list);
         }
 
-        for &(pos, ref actor, _) in self.actors.iter() {
+        for &(pos, ref actor, _, _) in self.actors.iter() {
             let w = actor.race.size.width as f32 / 2.0;
             let h = actor.race.size.height as f32 / 2.0;
             actor.draw(
@@ -676,8 +724,12 @@ impl AreaModel {
                 Some(actor) => actor,
             };
 
-            self.actors
-                .push((actor_data.location, actor, actor_data.unique_id));
+            self.actors.push((
+                actor_data.location,
+                actor,
+                actor_data.unique_id,
+                actor_data.behavior,
+            ));
         }
     }
 
@@ -770,11 +822,17 @@ impl AreaModel {
 
         trace!("Saving actors.");
         let mut actors: Vec<ActorData> = Vec::new();
-        for &(pos, ref actor, ref unique_id) in self.actors.iter() {
+        for &(pos, ref actor, ref unique_id, ref behavior) in self.actors.iter() {
+            // behaviors with nothing set are left out of the area file
+            let behavior = behavior
+                .as_ref()
+                .filter(|behavior| **behavior != ActorBehavior::default())
+                .cloned();
             actors.push(ActorData {
                 id: actor.id.to_string(),
                 unique_id: unique_id.clone(),
                 location: pos,
+                behavior,
             });
         }
 
@@ -0,0 +1,361 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sulis_core::config::Config;
+use sulis_core::io::{DrawList, GraphicsRenderer};
+use sulis_core::resource::{ResourceSet, Sprite};
+use sulis_core::ui::{Callback, Widget, WidgetKind};
+use sulis_core::util::{Offset, Point, Rect, Scale};
+use sulis_core::widgets::{Button, Label, Spinner};
+use sulis_module::area::{ScheduleEntry, Waypoint};
+use sulis_module::Module;
+
+use crate::{AreaEditor, AreaModel, EditorMode};
+
+const NAME: &str = "behavior_picker";
+
+/// Edits the patrol routes, schedules, and wandering of placed actors.  Left
+/// clicking an actor selects it, after which left clicks add patrol waypoints,
+/// or set the post for the current hour while placing posts.  Right clicks
+/// remove waypoints and posts.
+pub struct BehaviorPicker {
+    area_editor: Rc<RefCell<AreaEditor>>,
+    // the location of the selected actor, as indices change when actors are removed
+    selected: Option<Point>,
+    placing_post: bool,
+    cursor_pos: Option<Point>,
+    marker_sprite: Option<Rc<Sprite>>,
+
+    wait_spinner: Rc<RefCell<Spinner>>,
+    wait_widget: Rc<RefCell<Widget>>,
+    wander_spinner: Rc<RefCell<Spinner>>,
+    wander_widget: Rc<RefCell<Widget>>,
+    hour_spinner: Rc<RefCell<Spinner>>,
+    hour_widget: Rc<RefCell<Widget>>,
+    info_widget: Rc<RefCell<Widget>>,
+}
+
+impl BehaviorPicker {
+    pub fn new(area_editor: Rc<RefCell<AreaEditor>>) -> Rc<RefCell<BehaviorPicker>> {
+        let enc_tile = Config::editor_config().area.encounter_tile;
+
+        let sprite = match ResourceSet::sprite(&enc_tile) {
+            Ok(sprite) => Some(sprite),
+            Err(_) => {
+                warn!("Encounter tile '{}' not found", enc_tile);
+                None
+            }
+        };
+
+        let max_hour = Module::rules().hours_per_day as i32 - 1;
+
+        let wait_spinner = Spinner::new(0, 0, 50);
+        let wait_widget = Widget::with_theme(wait_spinner.clone(), "wait");
+        let wander_spinner = Spinner::new(0, 0, 20);
+        let wander_widget = Widget::with_theme(wander_spinner.clone(), "wander");
+        let hour_spinner = Spinner::new(0, 0, max_hour);
+        let hour_widget = Widget::with_theme(hour_spinner.clone(), "hour");
+
+        Rc::new(RefCell::new(BehaviorPicker {
+            area_editor,
+            selected: None,
+            placing_post: false,
+            cursor_pos: None,
+            marker_sprite: sprite,
+            wait_spinner,
+            wait_widget,
+            wander_spinner,
+            wander_widget,
+            hour_spinner,
+            hour_widget,
+            info_widget: Widget::with_theme(Label::empty(), "info"),
+        }))
+    }
+
+    fn hour(&self) -> u32 {
+        self.hour_spinner.borrow().value() as u32
+    }
+
+    /// Returns the current index of the selected actor, if it is still placed
+    fn selected_index(&self, model: &AreaModel) -> Option<usize> {
+        self.selected.and_then(|p| model.actor_index_at(p))
+    }
+
+    /// Updates the widgets to show the selected actor's behavior
+    fn update_info(&mut self, model: &AreaModel) {
+        let hour = self.hour();
+        let hour_name = Module::rules().get_hour_name(hour).to_string();
+
+        let index = self.selected_index(model);
+        if index.is_none() {
+            self.selected = None;
+        }
+
+        let (wander, post) = match index.and_then(|i| model.actor_behavior(i)) {
+            None => (0, "None".to_string()),
+            Some(behavior) => {
+                let entry = behavior.schedule.iter().find(|entry| entry.hour == hour);
+                let post = match entry {
+                    None => "None".to_string(),
+                    Some(ScheduleEntry { location: None, .. }) => "Patrol".to_string(),
+                    Some(ScheduleEntry {
+                        location: Some(p), ..
+                    }) => format!("{},{}", p.x, p.y),
+                };
+                (behavior.wander_radius as i32, post)
+            }
+        };
+
+        let selected = match self.selected {
+            None => "None".to_string(),
+            Some(p) => format!("{},{}", p.x, p.y),
+        };
+
+        self.wander_spinner.borrow_mut().set_value(wander);
+        self.wander_widget.borrow_mut().invalidate_layout();
+
+        let mut info = self.info_widget.borrow_mut();
+        info.state.clear_text_args();
+        info.state.add_text_arg("selected", &selected);
+        info.state.add_text_arg("hour_name", &hour_name);
+        info.state.add_text_arg("post", &post);
+        info.invalidate_layout();
+    }
+
+    fn draw_marker(&self, renderer: &mut dyn GraphicsRenderer, rect: Rect, scale: Scale) {
+        if let Some(ref sprite) = self.marker_sprite {
+            let mut draw_list = DrawList::from_sprite_f32(sprite, rect);
+            draw_list.set_scale(scale);
+            renderer.draw(draw_list);
+        }
+    }
+}
+
+impl EditorMode for BehaviorPicker {
+    fn draw_mode(
+        &mut self,
+        renderer: &mut dyn GraphicsRenderer,
+        model: &AreaModel,
+        offset: Offset,
+        scale: Scale,
+        _millis: u32,
+    ) {
+        let marker = |p: Point| Rect {
+            x: offset.x + p.x as f32,
+            y: offset.y + p.y as f32,
+            w: 1.0,
+            h: 1.0,
+        };
+
+        if let Some(index) = self.selected_index(model) {
+            if let Some(p) = model.actor_location(index) {
+                self.draw_marker(renderer, marker(p), scale);
+            }
+
+            if let Some(behavior) = model.actor_behavior(index) {
+                for waypoint in behavior.patrol.iter() {
+                    self.draw_marker(renderer, marker(waypoint.location), scale);
+                }
+                for entry in behavior.schedule.iter() {
+                    if let Some(p) = entry.location {
+                        self.draw_marker(renderer, marker(p), scale);
+                    }
+                }
+            }
+        }
+
+        if let Some(pos) = self.cursor_pos {
+            self.draw_marker(renderer, marker(pos), scale);
+        }
+    }
+
+    fn cursor_size(&self) -> (i32, i32) {
+        (1, 1)
+    }
+
+    fn mouse_move(&mut self, _model: &mut AreaModel, x: i32, y: i32) {
+        self.cursor_pos = Some(Point::new(x, y));
+    }
+
+    fn left_click(&mut self, model: &mut AreaModel, x: i32, y: i32) {
+        let index = match self.selected_index(model) {
+            Some(index) => index,
+            None => {
+                self.selected = model.actor_at(x, y).and_then(|i| model.actor_location(i));
+                self.update_info(model);
+                return;
+            }
+        };
+
+        let location = Point::new(x, y);
+        let hour = self.hour();
+        let wait_rounds = self.wait_spinner.borrow().value() as u32;
+        let placing_post = self.placing_post;
+        if let Some(behavior) = model.actor_behavior_mut(index) {
+            if placing_post {
+                behavior.schedule.retain(|entry| entry.hour != hour);
+                behavior.schedule.push(ScheduleEntry {
+                    hour,
+                    location: Some(location),
+                });
+                behavior.schedule.sort_by_key(|entry| entry.hour);
+            } else {
+                behavior.patrol.push(Waypoint {
+                    location,
+                    wait_rounds,
+                });
+            }
+        }
+        self.update_info(model);
+    }
+
+    fn right_click(&mut self, model: &mut AreaModel, x: i32, y: i32) {
+        let index = match self.selected_index(model) {
+            None => return,
+            Some(index) => index,
+        };
+
+        let p = Point::new(x, y);
+        let removed = match model.actor_behavior_mut(index) {
+            None => false,
+            Some(behavior) => {
+                let count = behavior.patrol.len() + behavior.schedule.len();
+                behavior.patrol.retain(|waypoint| waypoint.location != p);
+                behavior.schedule.retain(|entry| entry.location != Some(p));
+                count != behavior.patrol.len() + behavior.schedule.len()
+            }
+        };
+
+        // right clicking anywhere else deselects the actor
+        if !removed {
+            self.selected = None;
+        }
+        self.update_info(model);
+    }
+}
+
+impl WidgetKind for BehaviorPicker {
+    fn get_name(&self) -> &str {
+        NAME
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn on_add(&mut self, _widget: &Rc<RefCell<Widget>>) -> Vec<Rc<RefCell<Widget>>> {
+        let editor = Rc::clone(&self.area_editor);
+        self.update_info(&editor.borrow().model);
+
+        self.wander_widget
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, kind| {
+                let (_, picker) = Widget::parent_mut::<BehaviorPicker>(widget);
+                let radius = Widget::downcast::<Spinner>(kind).value() as u32;
+                let mut editor = picker.area_editor.borrow_mut();
+                let index = match picker.selected_index(&editor.model) {
+                    None => return,
+                    Some(index) => index,
+                };
+
+                if let Some(behavior) = editor.model.actor_behavior_mut(index) {
+                    behavior.wander_radius = radius;
+                }
+            })));
+
+        self.hour_widget
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (_, picker) = Widget::parent_mut::<BehaviorPicker>(widget);
+                let editor = Rc::clone(&picker.area_editor);
+                picker.update_info(&editor.borrow().model);
+            })));
+
+        let place_post = Widget::with_theme(Button::empty(), "place_post");
+        place_post.borrow_mut().state.set_active(self.placing_post);
+        place_post
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (_, picker) = Widget::parent_mut::<BehaviorPicker>(widget);
+                picker.placing_post = !picker.placing_post;
+                widget.borrow_mut().state.set_active(picker.placing_post);
+            })));
+
+        let patrol_at_hour = Widget::with_theme(Button::empty(), "patrol_at_hour");
+        patrol_at_hour
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (_, picker) = Widget::parent_mut::<BehaviorPicker>(widget);
+                let editor = Rc::clone(&picker.area_editor);
+                let index = match picker.selected_index(&editor.borrow().model) {
+                    None => return,
+                    Some(index) => index,
+                };
+
+                let hour = picker.hour();
+                if let Some(behavior) = editor.borrow_mut().model.actor_behavior_mut(index) {
+                    behavior.schedule.retain(|entry| entry.hour != hour);
+                    behavior.schedule.push(ScheduleEntry {
+                        hour,
+                        location: None,
+                    });
+                    behavior.schedule.sort_by_key(|entry| entry.hour);
+                }
+                picker.update_info(&editor.borrow().model);
+            })));
+
+        let clear = Widget::with_theme(Button::empty(), "clear");
+        clear
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (_, picker) = Widget::parent_mut::<BehaviorPicker>(widget);
+                let editor = Rc::clone(&picker.area_editor);
+                let index = match picker.selected_index(&editor.borrow().model) {
+                    None => return,
+                    Some(index) => index,
+                };
+
+                editor.borrow_mut().model.clear_actor_behavior(index);
+                picker.update_info(&editor.borrow().model);
+            })));
+
+        vec![
+            Widget::with_theme(Label::empty(), "wait_label"),
+            self.wait_widget.clone(),
+            Widget::with_theme(Label::empty(), "wander_label"),
+            self.wander_widget.clone(),
+            Widget::with_theme(Label::empty(), "hour_label"),
+            self.hour_widget.clone(),
+            self.info_widget.clone(),
+            place_post,
+            patrol_at_hour,
+            clear,
+        ]
+    }
+}
@@ -26,6 +26,9 @@ use crate::area_editor::AreaEditor;
 mod area_model;
 use crate::area_model::AreaModel;
 
+mod behavior_picker;
+use crate::behavior_picker::BehaviorPicker;
+
 mod elev_picker;
 use crate::elev_picker::ElevPicker;
 
@@ -311,6 +314,7 @@ impl WidgetKind for EditorView {
         let trigger_picker_kind = TriggerPicker::new();
         let pass_picker_kind = PassPicker::new();
         let vis_picker_kind = VisPicker::new();
+        let behavior_picker_kind = BehaviorPicker::new(Rc::clone(&area_editor_kind));
 
         let pickers = vec![
             Widget::with_defaults(tile_picker_kind.clone()),
@@ -324,6 +328,7 @@ impl WidgetKind for EditorView {
             Widget::with_defaults(trigger_picker_kind.clone()),
             Widget::with_defaults(pass_picker_kind.clone()),
             Widget::with_defaults(vis_picker_kind.clone()),
+            Widget::with_defaults(behavior_picker_kind.clone()),
         ];
         for picker in pickers.iter() {
             picker.borrow_mut().state.set_visible(false);
@@ -341,6 +346,7 @@ impl WidgetKind for EditorView {
             trigger_picker_kind,
             pass_picker_kind,
             vis_picker_kind,
+            behavior_picker_kind,
         ];
 
         let names = vec![
@@ -355,6 +361,7 @@ impl WidgetKind for EditorView {
             "Triggers",
             "Passability",
             "Visibility",
+            "Behavior",
         ];
 
         // Any new pickers need to be added in all 3 places
@@ -29,6 +29,41 @@ pub enum FuncKind {
     AiAction,
 }
 
+/// The part an AI entity plays within its group, shared with the other group
+/// members through the group's blackboard
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(deny_unknown_fields)]
+pub enum AIRole {
+    Tank,
+    Melee,
+    Ranged,
+    Caster,
+    Healer,
+}
+
+impl AIRole {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AIRole::Tank => "Tank",
+            AIRole::Melee => "Melee",
+            AIRole::Ranged => "Ranged",
+            AIRole::Caster => "Caster",
+            AIRole::Healer => "Healer",
+        }
+    }
+
+    pub fn option_from_str(s: &str) -> Option<AIRole> {
+        match s {
+            "Tank" => Some(AIRole::Tank),
+            "Melee" => Some(AIRole::Melee),
+            "Ranged" => Some(AIRole::Ranged),
+            "Caster" => Some(AIRole::Caster),
+            "Healer" => Some(AIRole::Healer),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct AITemplate {
@@ -40,4 +75,7 @@ pub struct AITemplate {
 
     #[serde(default)]
     pub params: HashMap<String, i32>,
+
+    #[serde(default)]
+    pub role: Option<AIRole>,
 }
@@ -83,6 +83,51 @@ pub struct ActorData {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unique_id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub behavior: Option<ActorBehavior>,
+}
+
+/// What a placed actor does while out of combat.  The actor's post is its
+/// placed location, or the location from its current schedule entry, and it
+/// walks back to its post or patrol route after each combat.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ActorBehavior {
+    /// Points walked in order, looping back to the first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patrol: Vec<Waypoint>,
+
+    /// The entry with the latest `hour` not after the current hour applies,
+    /// with the last entry continuing on past midnight
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleEntry>,
+
+    /// How far from its post the actor idly wanders while not patrolling
+    #[serde(default)]
+    pub wander_radius: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    pub location: Point,
+
+    /// Rounds spent at this point before moving on to the next
+    #[serde(default)]
+    pub wait_rounds: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleEntry {
+    /// The hour of the day this entry starts, indexing the rules' `hour_names`
+    pub hour: u32,
+
+    /// The actor's post for this part of the day.  If not specified, the actor
+    /// walks its patrol route, or returns to its placed location if it has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Point>,
 }
 
 #[derive(Clone)]
@@ -71,6 +71,43 @@ impl PartialEq for CampaignGroup {
 
 impl Eq for CampaignGroup {}
 
+/// A game event that campaign scripts may subscribe to via `event_scripts`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CampaignEventKind {
+    /// Any entity is reduced to zero hit points.  Data: `entity`, `killer`
+    EntityDied,
+
+    /// Combat begins.  No data
+    CombatStarted,
+
+    /// Combat ends.  No data
+    CombatEnded,
+
+    /// The state of a quest or one of its entries changes.
+    /// Data: `quest`, `entry` (nil when the quest itself changed), `state`
+    QuestUpdated,
+
+    /// The party buys an item from a merchant.  Data: `item`, `merchant`, `price`
+    ItemBought,
+
+    /// The party sells an item to a merchant.  Data: `item`, `merchant`, `price`
+    ItemSold,
+}
+
+impl CampaignEventKind {
+    pub fn to_str(self) -> &'static str {
+        use CampaignEventKind::*;
+        match self {
+            EntityDied => "EntityDied",
+            CombatStarted => "CombatStarted",
+            CombatEnded => "CombatEnded",
+            QuestUpdated => "QuestUpdated",
+            ItemBought => "ItemBought",
+            ItemSold => "ItemSold",
+        }
+    }
+}
+
 impl Ord for CampaignGroup {
     fn cmp(&self, other: &CampaignGroup) -> Ordering {
         self.id.cmp(&other.id)
@@ -95,6 +132,7 @@ pub struct Campaign {
     pub on_party_death_script: on_trigger::ScriptData,
     pub on_tick_script: Option<on_trigger::ScriptData>,
     pub on_round_elapsed_script: Option<on_trigger::ScriptData>,
+    pub event_scripts: HashMap<CampaignEventKind, on_trigger::ScriptData>,
     pub world_map: WorldMap,
     pub group: Option<CampaignGroup>,
 }
@@ -148,6 +186,7 @@ impl Campaign {
             on_party_death_script: builder.on_party_death_script,
             on_tick_script: builder.on_tick_script,
             on_round_elapsed_script: builder.on_round_elapsed_script,
+            event_scripts: builder.event_scripts,
             world_map: WorldMap {
                 size: builder.world_map.size,
                 offset: builder.world_map.offset,
@@ -172,6 +211,8 @@ pub struct CampaignBuilder {
     pub on_party_death_script: on_trigger::ScriptData,
     pub on_tick_script: Option<on_trigger::ScriptData>,
     pub on_round_elapsed_script: Option<on_trigger::ScriptData>,
+    #[serde(default)]
+    pub event_scripts: HashMap<CampaignEventKind, on_trigger::ScriptData>,
     pub world_map: WorldMapBuilder,
 }
 
@@ -102,6 +102,10 @@ impl Conversation {
         self.initial_nodes.iter()
     }
 
+    pub fn node_ids(&self) -> impl Iterator<Item = &String> {
+        self.nodes.keys()
+    }
+
     // TODO don't panic when getting a node.
 
     pub fn on_view(&self, node: &str) -> &Vec<OnTrigger> {
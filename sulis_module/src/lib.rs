@@ -30,7 +30,7 @@ pub use self::actor::Faction;
 pub use self::actor::Sex;
 
 pub mod ai;
-pub use self::ai::AITemplate;
+pub use self::ai::{AIRole, AITemplate};
 
 pub mod area;
 pub use self::area::Area;
@@ -57,6 +57,7 @@ pub use self::encounter::Encounter;
 
 pub mod campaign;
 pub use self::campaign::Campaign;
+pub use self::campaign::CampaignEventKind;
 pub use self::campaign::CampaignGroup;
 
 pub mod generator;
@@ -106,14 +107,19 @@ pub use self::rules::bonus;
 pub use self::rules::{
     AccuracyKind, Armor, ArmorKind, Attack, AttackBonuses, AttackKind, Attribute, AttributeList,
     Bonus, BonusKind, BonusList, Damage, DamageKind, DamageList, HitFlags, HitKind, ItemKind,
-    QuickSlot, Resistance, Rules, Slot, StatList, Time, WeaponKind, WeaponStyle, ROUND_TIME_MILLIS,
+    PartyAIRules, QuickSlot, Resistance, Rules, ScriptLimits, ScriptQuota, Slot, StatList,
+    StealthRules, Time, WeaponKind, WeaponStyle, ROUND_TIME_MILLIS,
 };
 
+pub mod validation;
+
 use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::{self, Display};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::Error;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -180,6 +186,9 @@ pub struct Module {
     generators: HashMap<String, Rc<AreaGenerator>>,
 
     root_dir: Option<String>,
+    resource_dirs: Vec<String>,
+    reload_hashes: HashMap<YamlResourceKind, HashMap<String, u64>>,
+    mods: Vec<ModificationInfo>,
     init: bool,
 }
 
@@ -413,6 +422,7 @@ impl Module {
 
         let campaign_builder: CampaignBuilder = read_builder(campaign_yaml)?;
 
+        let reload_hashes = resource_hashes(&yaml);
         let builder_set = ModuleBuilder::from_yaml(&mut yaml)?;
         let area_builders = MODULE.with(|module| {
             let mut module = module.borrow_mut();
@@ -443,9 +453,21 @@ impl Module {
 
             module.rules = Some(Rc::new(rules));
             module.scripts = read_to_string(&dirs, "scripts");
-            expand_include_directives(&mut module.scripts);
+            convert_include_directives(&mut module.scripts);
 
             module.root_dir = Some(dirs[1].to_string());
+            module.resource_dirs = dirs.clone();
+            module.reload_hashes = reload_hashes;
+            module.mods = dirs[2..]
+                .iter()
+                .filter_map(|dir| match ModificationInfo::from_dir(PathBuf::from(dir)) {
+                    Ok(info) => Some(info),
+                    Err(e) => {
+                        warn!("Unable to read mod info from '{}': {}", dir, e);
+                        None
+                    }
+                })
+                .collect();
 
             for (id, builder) in builder_set.item_adjectives {
                 insert_if_ok(
@@ -641,10 +663,122 @@ impl Module {
         }
     }
 
+    /// Re-reads all scripts from the loaded resource directories, converting their
+    /// `--INCLUDE` directives.  Returns the IDs of the scripts that were added,
+    /// changed, or removed.
+    pub fn reload_scripts() -> Vec<String> {
+        MODULE.with(|r| {
+            let mut module = r.borrow_mut();
+            let mut scripts = read_to_string(&module.resource_dirs, "scripts");
+            convert_include_directives(&mut scripts);
+
+            let mut changed: Vec<String> = scripts
+                .iter()
+                .filter(|(id, script)| module.scripts.get(*id) != Some(*script))
+                .map(|(id, _)| id.to_string())
+                .collect();
+            changed.extend(
+                module
+                    .scripts
+                    .keys()
+                    .filter(|id| !scripts.contains_key(*id))
+                    .cloned(),
+            );
+
+            module.scripts = scripts;
+            changed
+        })
+    }
+
+    /// Re-reads abilities and items from the loaded resource directories, and
+    /// rebuilds each one whose YAML has changed.  Resources that fail to build keep
+    /// their previous definition.  Other resources referencing the old definitions,
+    /// such as actors and classes, are not updated.  Returns the rebuilt abilities
+    /// and items.
+    pub fn reload_abilities_and_items() -> Result<(Vec<Rc<Ability>>, Vec<Rc<Item>>), Error> {
+        let dirs = MODULE.with(|r| r.borrow().resource_dirs.clone());
+        let mut yaml = YamlResourceSet::from_subdirs(&dirs, &["abilities", "items"]);
+
+        let hashes = resource_hashes(&yaml);
+        let ability_builders: HashMap<String, AbilityBuilder> =
+            read_builders(&mut yaml, YamlResourceKind::Ability)?;
+        let item_builders: HashMap<String, ItemBuilder> =
+            read_builders(&mut yaml, YamlResourceKind::Item)?;
+
+        MODULE.with(|r| {
+            let mut module = r.borrow_mut();
+            let module = &mut *module;
+
+            let mut abilities = Vec::new();
+            for (id, builder) in ability_builders {
+                if !module.resource_changed(&hashes, YamlResourceKind::Ability, &id) {
+                    continue;
+                }
+
+                match Ability::new(builder, module) {
+                    Err(e) => warn_reload_failed("ability", &id, e),
+                    Ok(ability) => {
+                        let ability = Rc::new(ability);
+                        module.abilities.insert(id, Rc::clone(&ability));
+                        abilities.push(ability);
+                    }
+                }
+            }
+
+            let mut items = Vec::new();
+            for (id, builder) in item_builders {
+                if !module.resource_changed(&hashes, YamlResourceKind::Item, &id) {
+                    continue;
+                }
+
+                match Item::new(builder, module) {
+                    Err(e) => warn_reload_failed("item", &id, e),
+                    Ok(item) => {
+                        // drop any cached copies of the old item with adjectives
+                        let prefix = format!("{id}__ADJ__");
+                        module.items.retain(|key, _| !key.starts_with(&prefix));
+
+                        let item = Rc::new(item);
+                        module.items.insert(id, Rc::clone(&item));
+                        items.push(item);
+                    }
+                }
+            }
+
+            for (kind, hashes) in hashes {
+                module.reload_hashes.entry(kind).or_default().extend(hashes);
+            }
+
+            Ok((abilities, items))
+        })
+    }
+
+    fn resource_changed(
+        &self,
+        hashes: &HashMap<YamlResourceKind, HashMap<String, u64>>,
+        kind: YamlResourceKind,
+        id: &str,
+    ) -> bool {
+        let old = self.reload_hashes.get(&kind).and_then(|h| h.get(id));
+        let new = hashes.get(&kind).and_then(|h| h.get(id));
+        old != new
+    }
+
+    /// The directories resources were loaded from, starting with the base data
+    /// directory, then the campaign and any mods
+    pub fn resource_dirs() -> Vec<String> {
+        MODULE.with(|r| r.borrow().resource_dirs.clone())
+    }
+
     pub fn module_dir() -> Option<String> {
         MODULE.with(|m| m.borrow().root_dir.as_ref().cloned())
     }
 
+    /// Returns the mods loaded on top of the current campaign, in load order
+    pub fn active_mods() -> Vec<ModificationInfo> {
+        MODULE.with(|m| m.borrow().mods.clone())
+    }
+
     pub fn is_initialized() -> bool {
         MODULE.with(|m| m.borrow_mut().init)
     }
@@ -784,6 +918,18 @@ impl Module {
         MODULE.with(|r| all_resources(&r.borrow().actors))
     }
 
+    pub fn all_ai_templates() -> Vec<Rc<AITemplate>> {
+        MODULE.with(|r| all_resources(&r.borrow().ai_templates))
+    }
+
+    pub fn all_areas() -> Vec<Rc<Area>> {
+        MODULE.with(|r| all_resources(&r.borrow().areas))
+    }
+
+    pub fn all_items() -> Vec<Rc<Item>> {
+        MODULE.with(|r| all_resources(&r.borrow().items))
+    }
+
     pub fn all_object_sizes() -> Vec<Rc<ObjectSize>> {
         MODULE.with(|r| {
             r.borrow()
@@ -871,70 +1017,103 @@ impl ModuleBuilder {
     }
 }
 
-struct IncludeExpansion {
-    start_index: usize,
-    end_index: usize,
-    script_id: String,
+/// Hashes the YAML of each ability and item, so changed ones can be found when
+/// reloading
+fn resource_hashes(yaml: &YamlResourceSet) -> HashMap<YamlResourceKind, HashMap<String, u64>> {
+    let mut out = HashMap::new();
+    for kind in [YamlResourceKind::Ability, YamlResourceKind::Item] {
+        let resources = match yaml.resources.get(&kind) {
+            None => continue,
+            Some(resources) => resources,
+        };
+
+        let hashes = resources
+            .iter()
+            .map(|(id, value)| {
+                let mut hasher = DefaultHasher::new();
+                value.hash(&mut hasher);
+                (id.to_string(), hasher.finish())
+            })
+            .collect();
+        out.insert(kind, hashes);
+    }
+    out
 }
 
-fn expand_include_directives(scripts: &mut HashMap<String, String>) {
-    let start_time = time::Instant::now();
+fn warn_reload_failed(kind: &str, id: &str, error: Error) {
+    warn!(
+        "Unable to reload {} '{}', keeping the previous version",
+        kind, id
+    );
+    warn!("{}", error);
+}
 
-    // since we need a mutable reference to one entry and immutable refs to any other
-    // entry this would be very difficult without a clone
-    let scripts_src = scripts.clone();
+/// Converts each `--INCLUDE <script_id>` line into an equivalent
+/// `require("<script_id>")` on the same line, so that line numbers in the
+/// script are unchanged
+fn convert_include_directives(scripts: &mut HashMap<String, String>) {
+    let start_time = time::Instant::now();
 
-    let include_len = "--INCLUDE".len();
+    let ids: Vec<String> = scripts.keys().cloned().collect();
 
-    for (id, script) in scripts {
-        // first find all the expansions for this script
-        let mut expansions = Vec::new();
-        for (index, _) in script.match_indices("--INCLUDE") {
-            expansions.push(IncludeExpansion {
-                start_index: index,
-                end_index: 0,
-                script_id: String::new(),
-            });
+    for (id, script) in scripts.iter_mut() {
+        if !script.contains("--INCLUDE") {
+            continue;
         }
 
-        for expansion in &mut expansions {
-            let substr = &script[(expansion.start_index + include_len)..];
-            let mut iter = substr.lines();
-            let id = match iter.next() {
+        let mut converted = String::with_capacity(script.len());
+        for line in script.split_inclusive('\n') {
+            let indent = line.len() - line.trim_start().len();
+            let include_id = match line.trim().strip_prefix("--INCLUDE") {
                 None => {
-                    error!("Invalid --INCLUDE directive, no script specified.");
-                    return;
+                    converted.push_str(line);
+                    continue;
                 }
-                Some(id) => id,
+                Some(include_id) => include_id.trim(),
             };
-            expansion.end_index = expansion.start_index + include_len + id.len();
-            expansion.script_id = id.trim().to_string();
-        }
 
-        // start from the last and work forward, expanding each entry
-        for exp in expansions.into_iter().rev() {
-            debug!(
-                "Found script expansion in {}: '{}' at {} to {}",
-                id, exp.script_id, exp.start_index, exp.end_index
-            );
+            // invalid directives are left in place; they are just Lua comments
+            if include_id.is_empty() {
+                error!(
+                    "Invalid --INCLUDE directive in '{}', no script specified.",
+                    id
+                );
+                add_resource_error(
+                    "script",
+                    id,
+                    &[],
+                    "--INCLUDE with no script specified".to_string(),
+                );
+                converted.push_str(line);
+                continue;
+            }
 
-            let src = match scripts_src.get(&exp.script_id) {
-                None => {
-                    error!(
-                        "Invalid --INCLUDE direction, script '{}' does not exist",
-                        exp.script_id
-                    );
-                    return;
-                }
-                Some(src) => src,
-            };
+            if !ids.iter().any(|id| id == include_id) {
+                error!(
+                    "Invalid --INCLUDE direction in '{}', script '{}' does not exist",
+                    id, include_id
+                );
+                add_resource_error(
+                    "script",
+                    id,
+                    &[],
+                    format!("--INCLUDE of script '{include_id}' which does not exist"),
+                );
+                converted.push_str(line);
+                continue;
+            }
 
-            script.replace_range(exp.start_index..exp.end_index, src);
+            debug!("Found script include in {}: '{}'", id, include_id);
+            converted.push_str(&line[..indent]);
+            converted.push_str(&format!("require({include_id:?})"));
+            converted.push_str(&line[line.trim_end().len()..]);
         }
+
+        *script = converted;
     }
 
     info!(
-        "Expanded scripts in {}",
+        "Converted script includes in {}",
         util::format_elapsed_secs(start_time.elapsed())
     );
 }
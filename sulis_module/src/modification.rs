@@ -57,6 +57,23 @@ pub struct ModificationInfo {
     pub name: String,
     pub description: String,
     pub dir: String,
+    pub version: String,
+
+    /// IDs of the campaigns this mod may be used with.  If empty, the mod may be
+    /// used with any campaign
+    pub campaigns: Vec<String>,
+
+    /// IDs of mods that must also be active.  This mod is loaded after them.
+    pub requires: Vec<String>,
+
+    /// IDs of mods that may not be active at the same time as this mod
+    pub conflicts: Vec<String>,
+
+    /// IDs of mods that, if active, are loaded before this mod
+    pub load_after: Vec<String>,
+
+    /// IDs of mods that, if active, are loaded after this mod
+    pub load_before: Vec<String>,
 }
 
 impl Display for ModificationInfo {
@@ -75,8 +92,97 @@ impl ModificationInfo {
             description: builder.description,
             id: builder.id,
             dir: path_str,
+            version: builder.version,
+            campaigns: builder.campaigns,
+            requires: builder.requires,
+            conflicts: builder.conflicts,
+            load_after: builder.load_after,
+            load_before: builder.load_before,
         })
     }
+
+    /// Returns true if this mod may be used with the campaign with the specified ID
+    pub fn is_compatible_with(&self, campaign_id: &str) -> bool {
+        self.campaigns.is_empty() || self.campaigns.iter().any(|id| id == campaign_id)
+    }
+}
+
+/// Checks that the specified mods may be used together with the campaign with
+/// `campaign_id`, and sorts them into a load order satisfying the dependencies and
+/// load order hints of each mod.  Mods otherwise keep the order they were passed
+/// in.  Returns a description of each problem if no valid order exists.
+pub fn resolve_load_order(
+    mods: &[ModificationInfo],
+    campaign_id: Option<&str>,
+) -> Result<Vec<ModificationInfo>, Vec<String>> {
+    let mut problems = Vec::new();
+    let index_of = |id: &str| mods.iter().position(|m| m.id == id);
+
+    // the indices of the mods that must be loaded before each mod
+    let mut before: Vec<Vec<usize>> = vec![Vec::new(); mods.len()];
+    for (index, modif) in mods.iter().enumerate() {
+        if let Some(campaign_id) = campaign_id {
+            if !modif.is_compatible_with(campaign_id) {
+                problems.push(format!("{} is not compatible with this campaign", modif.name));
+            }
+        }
+
+        for id in modif.requires.iter() {
+            match index_of(id) {
+                None => problems.push(format!("{} requires the mod '{}'", modif.name, id)),
+                Some(other) => before[index].push(other),
+            }
+        }
+
+        for id in modif.conflicts.iter() {
+            if let Some(other) = index_of(id) {
+                // only report each conflicting pair once
+                if !mods[other].conflicts.contains(&modif.id) || index < other {
+                    problems.push(format!("{} conflicts with {}", modif.name, mods[other].name));
+                }
+            }
+        }
+
+        for id in modif.load_after.iter() {
+            if let Some(other) = index_of(id) {
+                before[index].push(other);
+            }
+        }
+
+        for id in modif.load_before.iter() {
+            if let Some(other) = index_of(id) {
+                before[other].push(index);
+            }
+        }
+    }
+
+    let mut placed = vec![false; mods.len()];
+    let mut order = Vec::new();
+    while order.len() < mods.len() {
+        let next = (0..mods.len())
+            .find(|&i| !placed[i] && before[i].iter().all(|&other| placed[other]));
+
+        match next {
+            Some(index) => {
+                placed[index] = true;
+                order.push(mods[index].clone());
+            }
+            None => {
+                let names: Vec<&str> = (0..mods.len())
+                    .filter(|&i| !placed[i])
+                    .map(|i| mods[i].name.as_str())
+                    .collect();
+                problems.push(format!("Circular load order between {}", names.join(", ")));
+                break;
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(order)
+    } else {
+        Err(problems)
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -85,4 +191,22 @@ pub struct ModificationInfoBuilder {
     pub id: String,
     pub name: String,
     pub description: String,
+
+    #[serde(default)]
+    pub version: String,
+
+    #[serde(default)]
+    pub campaigns: Vec<String>,
+
+    #[serde(default)]
+    pub requires: Vec<String>,
+
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+
+    #[serde(default)]
+    pub load_after: Vec<String>,
+
+    #[serde(default)]
+    pub load_before: Vec<String>,
 }
@@ -55,7 +55,7 @@ pub use self::stat_list::StatList;
 
 use crate::area::LocationKind;
 use sulis_core::ui::{color, Color};
-use sulis_core::util::{gen_rand, invalid_data_error};
+use sulis_core::util::{gen_cosmetic_rand, gen_rand, invalid_data_error};
 
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -120,6 +120,134 @@ pub struct Rules {
     pub hints: Vec<String>,
 
     pub main_menu_music: Option<String>,
+
+    #[serde(default)]
+    pub script_limits: ScriptLimits,
+
+    #[serde(default)]
+    pub party_ai: PartyAIRules,
+
+    #[serde(default)]
+    pub stealth: StealthRules,
+}
+
+/// Settings for party members whose combat turns are taken by the AI
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PartyAIRules {
+    /// The AI template used by party members until the player chooses another
+    pub default_template: String,
+
+    /// AI control is turned off for a party member, returning it to the player,
+    /// when its hit points drop below this fraction of the maximum
+    pub stop_hp_fraction: f32,
+}
+
+impl Default for PartyAIRules {
+    fn default() -> PartyAIRules {
+        PartyAIRules {
+            default_template: "ai_basic".to_string(),
+            stop_hp_fraction: 0.25,
+        }
+    }
+}
+
+/// Settings for hostile actors noticing hidden party members.  Each round, an
+/// unaware actor rolls `1d100 + perception * perception_factor - distance *
+/// distance_penalty - stealth` against each hidden party member it can see,
+/// where stealth is the target's concealment plus `hidden_stealth`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct StealthRules {
+    pub perception_factor: f32,
+    pub distance_penalty: f32,
+    pub hidden_stealth: i32,
+
+    /// A roll of at least this makes the actor suspicious of the target
+    pub suspicious_threshold: i32,
+
+    /// A roll of at least this alerts the actor to the target, starting combat
+    pub alert_threshold: i32,
+
+    /// The number of rounds a suspicious actor investigates before giving up
+    pub suspicious_rounds: u32,
+
+    /// The distance at which moving and attacking can be heard
+    pub move_noise_radius: f32,
+    pub attack_noise_radius: f32,
+
+    /// Multiplies the noise made by hidden entities
+    pub hidden_noise_factor: f32,
+}
+
+impl Default for StealthRules {
+    fn default() -> StealthRules {
+        StealthRules {
+            perception_factor: 5.0,
+            distance_penalty: 10.0,
+            hidden_stealth: 60,
+            suspicious_threshold: 0,
+            alert_threshold: 50,
+            suspicious_rounds: 5,
+            move_noise_radius: 3.0,
+            attack_noise_radius: 8.0,
+            hidden_noise_factor: 0.5,
+        }
+    }
+}
+
+/// The quotas for each kind of Lua script call.  Calls made from within another
+/// call of the same script count against the outer call's quota.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ScriptLimits {
+    pub ai: ScriptQuota,
+    pub ability: ScriptQuota,
+    pub trigger: ScriptQuota,
+    pub console: ScriptQuota,
+
+    /// Whether to end the current AI turn when its AI script exceeds its
+    /// quota, rather than continuing to run the AI
+    pub abort_ai_turn: bool,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> ScriptLimits {
+        ScriptLimits {
+            ai: ScriptQuota::default(),
+            ability: ScriptQuota::default(),
+            trigger: ScriptQuota::default(),
+            console: ScriptQuota {
+                instructions: 1_000_000,
+                ..ScriptQuota::default()
+            },
+            abort_ai_turn: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields, default)]
+pub struct ScriptQuota {
+    /// The maximum number of Lua instructions in one call
+    pub instructions: u32,
+
+    /// The maximum memory used by the script's Lua state, in kilobytes
+    pub memory_kb: usize,
+
+    /// An optional wall clock time limit for one call.  Unlike the other
+    /// quotas, this depends on the speed of the machine.
+    pub millis: Option<f64>,
+}
+
+impl Default for ScriptQuota {
+    fn default() -> ScriptQuota {
+        ScriptQuota {
+            instructions: 50_000,
+            memory_kb: 10_240,
+            millis: Some(50.0),
+        }
+    }
 }
 
 impl Rules {
@@ -134,7 +262,7 @@ impl Rules {
             0 => "",
             1 => &self.hints[0],
             _ => {
-                let index = gen_rand(0, self.hints.len() - 1);
+                let index = gen_cosmetic_rand(0, self.hints.len() - 1);
                 &self.hints[index]
             }
         }
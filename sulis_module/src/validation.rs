@@ -0,0 +1,527 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Cross checks the references between resources in the currently loaded module.
+//! Most of these references are only resolved at runtime, so a broken reference
+//! would otherwise only be found when the content is played.
+
+use std::collections::{HashMap, HashSet};
+
+use sulis_core::resource::{ResourceError, ResourceSet};
+use sulis_core::util::Point;
+
+use crate::ai::FuncKind;
+use crate::area::{OnRest, ToKind};
+use crate::on_trigger::{self, Kind, ScriptData};
+use crate::prop::Interactive;
+use crate::{Module, OnTrigger, MODULE};
+
+/// Checks all references between the resources of the loaded module, returning
+/// an error for each reference that cannot be resolved.  Lua functions are checked
+/// by searching the script source, and any scripts it includes, for a matching top
+/// level definition.
+pub fn check_references() -> Vec<ResourceError> {
+    let mut checker = Checker::default();
+
+    MODULE.with(|module| {
+        let module = module.borrow();
+
+        if let Some(campaign) = module.campaign.as_ref() {
+            checker.campaign(&module, campaign);
+        }
+
+        for area in module.areas.values() {
+            checker.area(&module, area);
+        }
+
+        for conv in module.conversations.values() {
+            for (_, on_view) in conv.initial_nodes() {
+                checker.triggers(&module, "conversation", &conv.id, on_view);
+            }
+
+            for node in conv.node_ids() {
+                checker.triggers(&module, "conversation", &conv.id, conv.on_view(node));
+                for response in conv.responses(node) {
+                    checker.triggers(&module, "conversation", &conv.id, &response.on_select);
+                    checker.triggers(&module, "conversation", &conv.id, &response.to_view);
+                }
+            }
+        }
+
+        for cutscene in module.cutscenes.values() {
+            checker.triggers(&module, "cutscene", &cutscene.id, &cutscene.on_end);
+        }
+
+        for prop in module.props.values() {
+            if let Interactive::Door { on_activate, .. } = &prop.interactive {
+                checker.triggers(&module, "prop", &prop.id, on_activate);
+            }
+        }
+
+        for ability in module.abilities.values() {
+            if let Some(active) = &ability.active {
+                checker.script_func(
+                    &module,
+                    "ability",
+                    &ability.id,
+                    &active.script,
+                    "on_activate",
+                );
+                if let Some(func) = &active.ai.on_activate_fn {
+                    checker.script_func(&module, "ability", &ability.id, &active.script, func);
+                }
+            }
+        }
+
+        for item in module.items.values() {
+            if let Some(usable) = &item.usable {
+                checker.script_func(&module, "item", &item.id, &usable.script, "on_activate");
+                if let Some(func) = &usable.ai.on_activate_fn {
+                    checker.script_func(&module, "item", &item.id, &usable.script, func);
+                }
+            }
+        }
+
+        for ai in module.ai_templates.values() {
+            if !ai.hooks.contains_key(&FuncKind::AiAction) {
+                checker.script_func(&module, "ai_template", &ai.id, &ai.script, "ai_action");
+            }
+
+            for func in ai.hooks.values() {
+                checker.script_func(&module, "ai_template", &ai.id, &ai.script, func);
+            }
+        }
+    });
+
+    checker.errors
+}
+
+/// Returns true if the Lua script source appears to define the specified function,
+/// either as `function name(` or `name = function(`.
+pub fn defines_function(script: &str, func: &str) -> bool {
+    script.lines().any(|line| {
+        let line = line.trim_start();
+        let line = line.strip_prefix("local ").unwrap_or(line).trim_start();
+
+        if let Some(rest) = line.strip_prefix("function ") {
+            if let Some((name, _)) = rest.split_once('(') {
+                return name.trim() == func;
+            }
+        }
+
+        if let Some((name, rest)) = line.split_once('=') {
+            return name.trim() == func && rest.trim_start().starts_with("function");
+        }
+
+        false
+    })
+}
+
+/// Returns the ids of the scripts included by the Lua script source, with either
+/// `require("id")` or `--INCLUDE id`
+pub fn included_scripts(script: &str) -> Vec<&str> {
+    let mut ids = Vec::new();
+    for line in script.lines() {
+        let line = line.trim();
+        if let Some(id) = line.strip_prefix("--INCLUDE") {
+            if !id.trim().is_empty() {
+                ids.push(id.trim());
+            }
+            continue;
+        }
+
+        let mut rest = match line.split_once("--") {
+            None => line,
+            Some((code, _)) => code,
+        };
+        while let Some(index) = rest.find("require") {
+            rest = rest[index + "require".len()..].trim_start();
+            let arg = rest.strip_prefix('(').unwrap_or(rest).trim_start();
+            let quote = match arg.chars().next() {
+                Some(c) if c == '"' || c == '\'' => c,
+                _ => continue,
+            };
+
+            if let Some((id, _)) = arg[1..].split_once(quote) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+/// Returns true if the script with the specified id, or any script that it
+/// includes, appears to define the specified function
+fn script_defines_function(
+    scripts: &HashMap<String, String>,
+    script: &str,
+    func: &str,
+    visited: &mut HashSet<String>,
+) -> bool {
+    if !visited.insert(script.to_string()) {
+        return false;
+    }
+
+    let src = match scripts.get(script) {
+        None => return false,
+        Some(src) => src,
+    };
+
+    defines_function(src, func)
+        || included_scripts(src)
+            .into_iter()
+            .any(|id| script_defines_function(scripts, id, func, visited))
+}
+
+#[derive(Default)]
+struct Checker {
+    errors: Vec<ResourceError>,
+}
+
+impl Checker {
+    fn error(&mut self, kind: &str, id: &str, message: String) {
+        self.errors.push(ResourceError {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            files: Vec::new(),
+            message,
+        });
+    }
+
+    fn campaign(&mut self, module: &Module, campaign: &crate::Campaign) {
+        let id = &campaign.id;
+        match module.areas.get(&campaign.starting_area) {
+            None => self.error(
+                "campaign",
+                id,
+                format!("Starting area '{}' does not exist", campaign.starting_area),
+            ),
+            Some(area) => {
+                let loc = campaign.starting_location;
+                if !area.coords_valid(loc.x, loc.y) {
+                    self.error(
+                        "campaign",
+                        id,
+                        format!("Starting location {loc:?} is outside area '{}'", area.id),
+                    );
+                }
+            }
+        }
+
+        self.script_data(module, "campaign", id, &campaign.on_party_death_script);
+        if let Some(script) = &campaign.on_tick_script {
+            self.script_data(module, "campaign", id, script);
+        }
+        if let Some(script) = &campaign.on_round_elapsed_script {
+            self.script_data(module, "campaign", id, script);
+        }
+        for script in campaign.event_scripts.values() {
+            self.script_data(module, "campaign", id, script);
+        }
+
+        let location_ids: HashSet<&str> = campaign
+            .world_map
+            .locations
+            .iter()
+            .map(|l| l.id.as_str())
+            .collect();
+
+        for location in &campaign.world_map.locations {
+            if let Some(area_id) = &location.linked_area {
+                self.area_point(
+                    module,
+                    "campaign",
+                    id,
+                    area_id,
+                    location.linked_area_pos,
+                    &format!("World map location '{}'", location.id),
+                );
+            }
+
+            for to in location.travel_times.keys() {
+                if !location_ids.contains(to.as_str()) {
+                    self.error(
+                        "campaign",
+                        id,
+                        format!(
+                            "World map location '{}' has a travel time to unknown location '{}'",
+                            location.id, to
+                        ),
+                    );
+                }
+            }
+        }
+
+        for area in module.areas.values() {
+            if let Some(location) = &area.world_map_location {
+                if !location_ids.contains(location.as_str()) {
+                    self.error(
+                        "area",
+                        &area.id,
+                        format!("World map location '{location}' does not exist"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn area(&mut self, module: &Module, area: &crate::Area) {
+        let id = &area.id;
+
+        if let OnRest::FireScript { id: script, func } = &area.on_rest {
+            self.script_func(module, "area", id, script, func);
+        }
+
+        for actor in &area.actors {
+            if !module.actors.contains_key(&actor.id) {
+                self.error("area", id, format!("Actor '{}' does not exist", actor.id));
+            }
+
+            if !area.coords_valid(actor.location.x, actor.location.y) {
+                self.error(
+                    "area",
+                    id,
+                    format!(
+                        "Actor '{}' at {:?} is outside the area",
+                        actor.id, actor.location
+                    ),
+                );
+            }
+        }
+
+        for (index, transition) in area.builder.transitions.iter().enumerate() {
+            let desc = format!("Transition {index}");
+            if ResourceSet::image(&transition.image_display).is_none() {
+                self.error(
+                    "area",
+                    id,
+                    format!("{desc} image '{}' does not exist", transition.image_display),
+                );
+            }
+
+            match module.sizes.get(&transition.size) {
+                None => self.error(
+                    "area",
+                    id,
+                    format!("{desc} size '{}' does not exist", transition.size),
+                ),
+                Some(size) => {
+                    let from = transition.from;
+                    if !area.coords_valid(from.x, from.y)
+                        || !area.coords_valid(from.x + size.width - 1, from.y + size.height - 1)
+                    {
+                        self.error(
+                            "area",
+                            id,
+                            format!("{desc} at {from:?} is outside the area"),
+                        );
+                    }
+                }
+            }
+
+            match &transition.to {
+                ToKind::Area { id: to, x, y } => {
+                    self.area_point(module, "area", id, to, Point::new(*x, *y), &desc)
+                }
+                ToKind::CurArea { x, y } => {
+                    if !area.coords_valid(*x, *y) {
+                        self.error(
+                            "area",
+                            id,
+                            format!("{desc} destination {x},{y} is outside the area"),
+                        );
+                    }
+                }
+                ToKind::FindLink { id: to, .. } => {
+                    if !module.areas.contains_key(to) {
+                        self.error("area", id, format!("{desc} links to unknown area '{to}'"));
+                    }
+                }
+                ToKind::WorldMap => (),
+            }
+        }
+
+        for trigger in &area.triggers {
+            self.triggers(module, "area", id, &trigger.on_activate);
+        }
+    }
+
+    fn area_point(
+        &mut self,
+        module: &Module,
+        kind: &str,
+        id: &str,
+        area_id: &str,
+        point: Point,
+        desc: &str,
+    ) {
+        match module.areas.get(area_id) {
+            None => self.error(
+                kind,
+                id,
+                format!("{desc} links to unknown area '{area_id}'"),
+            ),
+            Some(area) if !area.coords_valid(point.x, point.y) => {
+                self.error(
+                    kind,
+                    id,
+                    format!("{desc} destination {point:?} is outside area '{area_id}'"),
+                );
+            }
+            Some(_) => (),
+        }
+    }
+
+    fn triggers(&mut self, module: &Module, kind: &str, id: &str, triggers: &[OnTrigger]) {
+        for trigger in triggers {
+            self.trigger(module, kind, id, trigger);
+        }
+    }
+
+    fn trigger(&mut self, module: &Module, kind: &str, id: &str, trigger: &OnTrigger) {
+        use OnTrigger::*;
+        match trigger {
+            PartyItem(item) if !module.items.contains_key(item) => {
+                self.error(
+                    kind,
+                    id,
+                    format!("Trigger references unknown item '{item}'"),
+                );
+            }
+            PlayerAbility(ability) if !module.abilities.contains_key(ability) => {
+                self.error(
+                    kind,
+                    id,
+                    format!("Trigger references unknown ability '{ability}'"),
+                );
+            }
+            ShowMerchant(data) if !module.loot_lists.contains_key(&data.loot_list) => {
+                self.error(
+                    kind,
+                    id,
+                    format!(
+                        "Merchant '{}' uses unknown loot list '{}'",
+                        data.id, data.loot_list
+                    ),
+                );
+            }
+            ShowCutscene(cutscene) if !module.cutscenes.contains_key(cutscene) => {
+                self.error(
+                    kind,
+                    id,
+                    format!("Trigger references unknown cutscene '{cutscene}'"),
+                );
+            }
+            StartConversation(conv) if !module.conversations.contains_key(conv) => {
+                self.error(
+                    kind,
+                    id,
+                    format!("Trigger references unknown conversation '{conv}'"),
+                );
+            }
+            FireScript(script) => self.script_data(module, kind, id, script),
+            ShowConfirm(data) => {
+                if let Some(script) = &data.on_accept {
+                    self.script_data(module, kind, id, script);
+                }
+            }
+            ShowMenu(data) => match &data.cb_kind {
+                Kind::Script(script) => self.script_func(module, kind, id, script, &data.cb_func),
+                Kind::Ability(ability) => match module.abilities.get(ability) {
+                    Some(ability) => match &ability.active {
+                        Some(active) => {
+                            self.script_func(module, kind, id, &active.script, &data.cb_func)
+                        }
+                        None => self.error(
+                            kind,
+                            id,
+                            format!("Menu callback ability '{}' is not active", ability.id),
+                        ),
+                    },
+                    None => self.error(
+                        kind,
+                        id,
+                        format!("Menu callback references unknown ability '{ability}'"),
+                    ),
+                },
+                Kind::Item(item) => match module.items.get(item) {
+                    Some(item) => match &item.usable {
+                        Some(usable) => {
+                            self.script_func(module, kind, id, &usable.script, &data.cb_func)
+                        }
+                        None => self.error(
+                            kind,
+                            id,
+                            format!("Menu callback item '{}' is not usable", item.id),
+                        ),
+                    },
+                    None => self.error(
+                        kind,
+                        id,
+                        format!("Menu callback references unknown item '{item}'"),
+                    ),
+                },
+                Kind::Entity => (),
+            },
+            QuestState(data) | NotQuestState(data) => self.quest(module, kind, id, data),
+            _ => (),
+        }
+    }
+
+    fn quest(&mut self, module: &Module, kind: &str, id: &str, data: &on_trigger::QuestStateData) {
+        let quest = match module.quests.get(&data.quest) {
+            None => {
+                self.error(
+                    kind,
+                    id,
+                    format!("Trigger references unknown quest '{}'", data.quest),
+                );
+                return;
+            }
+            Some(quest) => quest,
+        };
+
+        if let Some(entry) = &data.entry {
+            if !quest.entries.contains_key(entry) {
+                self.error(
+                    kind,
+                    id,
+                    format!("Quest '{}' has no entry '{}'", data.quest, entry),
+                );
+            }
+        }
+    }
+
+    fn script_data(&mut self, module: &Module, kind: &str, id: &str, data: &ScriptData) {
+        self.script_func(module, kind, id, &data.id, &data.func);
+    }
+
+    fn script_func(&mut self, module: &Module, kind: &str, id: &str, script: &str, func: &str) {
+        if !module.scripts.contains_key(script) {
+            self.error(kind, id, format!("Script '{script}' does not exist"));
+            return;
+        }
+
+        let mut visited = HashSet::new();
+        if !script_defines_function(&module.scripts, script, func, &mut visited) {
+            self.error(
+                kind,
+                id,
+                format!("Script '{script}' does not define function '{func}'"),
+            );
+        }
+    }
+}
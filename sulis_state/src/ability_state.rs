@@ -100,6 +100,19 @@ impl AbilityState {
         }
     }
 
+    /// Replaces the ability with a reloaded definition, keeping the current
+    /// duration and listeners
+    pub(crate) fn reload(&mut self, ability: &Rc<Ability>) {
+        let new = AbilityState::new(ability);
+        self.ability = new.ability;
+        self.group = new.group;
+        self.combat_only = new.combat_only;
+        self.requires_melee = new.requires_melee;
+        self.requires_ranged = new.requires_ranged;
+        self.requires_shield = new.requires_shield;
+        self.requires_active_mode = new.requires_active_mode;
+    }
+
     pub fn update(&mut self, millis_elapsed: u32) {
         let cur_mod = self.cur_duration / ROUND_TIME_MILLIS;
         self.cur_duration += millis_elapsed;
@@ -17,10 +17,57 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::script::script_callback;
-use crate::{animation::Anim, EntityState, GameState, Script};
-use sulis_module::ai::FuncKind;
+use serde::{Deserialize, Serialize};
+
+use crate::area_feedback_text::ColorKind;
+use crate::script::{script_cache, script_callback};
+use crate::{animation::Anim, AreaFeedbackText, EntityState, GameState, Script};
 use sulis_core::config::Config;
+use sulis_module::{ai::FuncKind, Module};
+
+/// Whether a party member's combat turns are taken by the AI
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum PartyAIMode {
+    /// The player controls the party member
+    #[default]
+    Off,
+
+    /// Acts using the AI template from the actor's own definition, or the
+    /// chosen template if it has none
+    Script,
+
+    /// Acts using the AI template chosen by the player
+    Auto,
+}
+
+impl PartyAIMode {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            PartyAIMode::Off => "Off",
+            PartyAIMode::Script => "Script",
+            PartyAIMode::Auto => "Auto",
+        }
+    }
+
+    pub fn option_from_str(s: &str) -> Option<PartyAIMode> {
+        match s {
+            "Off" => Some(PartyAIMode::Off),
+            "Script" => Some(PartyAIMode::Script),
+            "Auto" => Some(PartyAIMode::Auto),
+            _ => None,
+        }
+    }
+
+    /// Returns the mode after this one, for cycling through the modes
+    pub fn next(self) -> PartyAIMode {
+        match self {
+            PartyAIMode::Off => PartyAIMode::Script,
+            PartyAIMode::Script => PartyAIMode::Auto,
+            PartyAIMode::Auto => PartyAIMode::Off,
+        }
+    }
+}
 
 pub struct AI {
     ai: Option<EntityAI>,
@@ -47,8 +94,11 @@ impl AI {
         }
 
         if entity.borrow().is_party_member() {
-            self.ai = None;
-            return;
+            let mgr = GameState::turn_manager();
+            if !entity.borrow().is_party_ai_active(&mgr.borrow()) || check_party_hp(&entity) {
+                self.ai = None;
+                return;
+            }
         }
 
         let assign = match self.ai {
@@ -79,6 +129,32 @@ impl AI {
     }
 }
 
+/// Turns off AI control for a party member that has dropped below the
+/// configured fraction of its hit points, so the player can take over.
+/// Returns true if AI control was turned off.
+fn check_party_hp(entity: &Rc<RefCell<EntityState>>) -> bool {
+    let frac = Module::rules().party_ai.stop_hp_fraction;
+    {
+        let entity = entity.borrow();
+        let max_hp = entity.actor.stats.max_hp;
+        if max_hp <= 0 || entity.actor.hp() as f32 >= frac * max_hp as f32 {
+            return false;
+        }
+    }
+
+    info!(
+        "Returning control of '{}' to the player due to low hit points",
+        entity.borrow().actor.actor.name
+    );
+    entity.borrow_mut().set_party_ai_mode(PartyAIMode::Off);
+
+    let area = GameState::area_state();
+    let mut text = AreaFeedbackText::with_target(&entity.borrow(), &area.borrow());
+    text.add_entry("AI Off".to_string(), ColorKind::Info);
+    area.borrow_mut().add_feedback_text(text);
+    true
+}
+
 fn end(ai: &mut EntityAI) -> State {
     debug!(
         "AI for '{}' is ending.",
@@ -147,15 +223,37 @@ impl EntityAI {
             return State::End;
         }
 
-        let ai_template = match &self.entity.borrow().actor.actor.ai {
+        let ai_template = match self.entity.borrow().ai_template() {
             None => return State::End,
-            Some(template) => Rc::clone(template),
+            Some(template) => template,
         };
 
         let func = ai_template.hooks.get(&FuncKind::AiAction).map(|f| f.as_str()).unwrap_or("ai_action");
 
         self.actions_taken_this_turn += 1;
 
-        Script::ai(&self.entity, func)
+        let state = Script::ai(&self.entity, func);
+        if self.quota_exceeded() {
+            return State::End;
+        }
+        state
+    }
+
+    /// Returns whether the AI script call that just ran exceeded its quota and
+    /// the turn should be ended
+    fn quota_exceeded(&self) -> bool {
+        if !script_cache::take_quota_exceeded() {
+            return false;
+        }
+
+        if !Module::rules().script_limits.abort_ai_turn {
+            return false;
+        }
+
+        warn!(
+            "Ending the turn of {} after a script exceeded its quota",
+            self.entity.borrow().unique_id()
+        );
+        true
     }
 }
@@ -0,0 +1,151 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! State shared between the members of an AI group, so that an encounter can
+//! coordinate its turns.  Each group has one blackboard, held by the
+//! `TurnManager` and exposed to Lua as `ScriptAIGroup`.
+
+use std::collections::HashMap;
+
+use sulis_core::util::Point;
+
+use crate::save_state::AIBlackboardSaveState;
+
+/// Entities are referred to by their index in the `TurnManager`
+#[derive(Default, Debug, Clone)]
+pub struct AIBlackboard {
+    threat: HashMap<usize, f32>,
+    focus: Option<usize>,
+    claims: HashMap<usize, Point>,
+    flags: HashMap<String, String>,
+}
+
+impl AIBlackboard {
+    /// Creates the blackboard from a save.  `entity` maps the saved entity
+    /// indices to their indices after loading, with entries for entities that
+    /// no longer exist being dropped.
+    pub(crate) fn load<F: Fn(usize) -> Option<usize>>(
+        save: AIBlackboardSaveState,
+        entity: F,
+    ) -> AIBlackboard {
+        AIBlackboard {
+            threat: save
+                .threat
+                .into_iter()
+                .filter_map(|(index, value)| entity(index).map(|index| (index, value)))
+                .collect(),
+            focus: save.focus.and_then(&entity),
+            claims: save
+                .claims
+                .into_iter()
+                .filter_map(|(index, p)| entity(index).map(|index| (index, p)))
+                .collect(),
+            flags: save.flags,
+        }
+    }
+
+    pub(crate) fn save(&self, group: usize) -> AIBlackboardSaveState {
+        AIBlackboardSaveState {
+            group,
+            threat: self.threat.iter().map(|(k, v)| (*k, *v)).collect(),
+            focus: self.focus,
+            claims: self.claims.iter().map(|(k, v)| (*k, *v)).collect(),
+            flags: self.flags.clone(),
+        }
+    }
+
+    /// The total threat generated by the specified entity against this group
+    pub fn threat(&self, entity: usize) -> f32 {
+        self.threat.get(&entity).copied().unwrap_or(0.0)
+    }
+
+    pub fn add_threat(&mut self, entity: usize, amount: f32) {
+        *self.threat.entry(entity).or_insert(0.0) += amount;
+    }
+
+    /// Returns all entities with threat against this group, highest threat first
+    pub fn threat_list(&self) -> Vec<(usize, f32)> {
+        let mut list: Vec<_> = self.threat.iter().map(|(k, v)| (*k, *v)).collect();
+        list.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        list
+    }
+
+    /// The entity the group has chosen to concentrate its attacks on
+    pub fn focus(&self) -> Option<usize> {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, entity: Option<usize>) {
+        self.focus = entity;
+    }
+
+    /// Claims the square at `p` for the `entity` to move to, replacing any
+    /// existing claim by that entity
+    pub fn claim_square(&mut self, entity: usize, p: Point) {
+        self.claims.insert(entity, p);
+    }
+
+    pub fn release_square(&mut self, entity: usize) {
+        self.claims.remove(&entity);
+    }
+
+    pub fn claimed_square(&self, entity: usize) -> Option<Point> {
+        self.claims.get(&entity).copied()
+    }
+
+    /// Returns whether the square at `p` is claimed by any entity other than `except`
+    pub fn is_claimed(&self, p: Point, except: Option<usize>) -> bool {
+        self.claims
+            .iter()
+            .any(|(entity, claim)| *claim == p && Some(*entity) != except)
+    }
+
+    /// Returns all claimed squares other than the one claimed by `entity`
+    pub fn claimed_squares_except(&self, entity: usize) -> Vec<Point> {
+        self.claims
+            .iter()
+            .filter(|(index, _)| **index != entity)
+            .map(|(_, p)| *p)
+            .collect()
+    }
+
+    pub fn flag(&self, flag: &str) -> Option<&str> {
+        self.flags.get(flag).map(|s| s.as_str())
+    }
+
+    pub fn set_flag(&mut self, flag: &str, value: &str) {
+        self.flags.insert(flag.to_string(), value.to_string());
+    }
+
+    pub fn clear_flag(&mut self, flag: &str) {
+        self.flags.remove(flag);
+    }
+
+    /// Claims and focus only apply to a single combat, while threat and flags
+    /// are kept for the next
+    pub(crate) fn end_combat(&mut self) {
+        self.claims.clear();
+        self.focus = None;
+    }
+
+    pub(crate) fn remove_entity(&mut self, entity: usize) {
+        self.threat.remove(&entity);
+        self.claims.remove(&entity);
+        if self.focus == Some(entity) {
+            self.focus = None;
+        }
+    }
+}
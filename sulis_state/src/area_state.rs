@@ -261,7 +261,13 @@ impl AreaState {
             let location = Location::from_point(actor_data.location, &area);
             debug!("Adding actor '{}' at '{:?}'", actor.id, location);
             match self.add_actor(actor, location, Some(unique_id), false, None) {
-                Ok(_) => (),
+                Ok(index) => {
+                    if let Some(behavior) = &actor_data.behavior {
+                        let behavior = IdleBehavior::new(behavior.clone(), actor_data.location);
+                        let entity = GameState::turn_manager().borrow().entity(index);
+                        entity.borrow_mut().set_idle_behavior(Some(behavior));
+                    }
+                }
                 Err(e) => {
                     warn!("Error adding actor to area: {}", e);
                 }
@@ -1064,6 +1070,7 @@ impl AreaState {
         }
 
         mgr.fire_on_moved_next_update(entity_index);
+        detection::add_noise(&entity.borrow(), Module::rules().stealth.move_noise_radius);
         mgr.check_ai_activation(entity, self);
     }
 
@@ -0,0 +1,389 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Hostile actors noticing hidden party members and noises.  Actors that are
+//! not yet in combat are unaware, suspicious, or alerted.  Each round they roll
+//! their perception against the stealth of each hidden party member they can
+//! see, and hearing movement or an attack makes them suspicious.  Suspicious
+//! actors investigate the place they last noticed something, while alerted
+//! actors start combat.  Party members that are not hidden are noticed as soon
+//! as they are seen, as in `TurnManager::check_ai_activation`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use sulis_core::util::{gen_rand, Point};
+use sulis_module::{Attribute, Module};
+
+use crate::save_state::DetectionSaveState;
+use crate::{AreaState, EntityState, GameState};
+
+/// How close an investigating actor must get to the point it is investigating
+const ARRIVED_DIST: f32 = 1.5;
+
+thread_local! {
+    static NOISES: RefCell<Vec<Noise>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Awareness {
+    #[default]
+    Unaware,
+    Suspicious,
+    Alerted,
+}
+
+impl Awareness {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Awareness::Unaware => "Unaware",
+            Awareness::Suspicious => "Suspicious",
+            Awareness::Alerted => "Alerted",
+        }
+    }
+}
+
+/// An actor's awareness of the party
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    awareness: Awareness,
+    last_seen: Option<Point>,
+    rounds_left: u32,
+    risk: u32,
+}
+
+impl Detection {
+    pub(crate) fn load(save: DetectionSaveState) -> Detection {
+        Detection {
+            awareness: save.awareness,
+            last_seen: save.last_seen,
+            rounds_left: save.rounds_left,
+            risk: 0,
+        }
+    }
+
+    /// Returns the save state, or None if there is nothing worth saving
+    pub(crate) fn save(&self) -> Option<DetectionSaveState> {
+        if self.awareness == Awareness::Unaware {
+            return None;
+        }
+
+        Some(DetectionSaveState {
+            awareness: self.awareness,
+            last_seen: self.last_seen,
+            rounds_left: self.rounds_left,
+        })
+    }
+
+    pub fn awareness(&self) -> Awareness {
+        self.awareness
+    }
+
+    /// The point where the actor last saw or heard something
+    pub fn last_seen(&self) -> Option<Point> {
+        self.last_seen
+    }
+
+    /// The percent chance that this actor noticed any single hidden party
+    /// member it could see in the latest round
+    pub fn risk(&self) -> u32 {
+        self.risk
+    }
+
+    /// Raises the awareness to at least `awareness`, having noticed something
+    /// at `p`
+    pub fn notice(&mut self, awareness: Awareness, p: Point) {
+        if awareness == Awareness::Unaware {
+            return;
+        }
+
+        self.awareness = std::cmp::max(self.awareness, awareness);
+        self.last_seen = Some(p);
+        self.rounds_left = Module::rules().stealth.suspicious_rounds;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Detection::default();
+    }
+
+    fn elapse_round(&mut self) {
+        self.risk = 0;
+        if self.awareness != Awareness::Suspicious {
+            return;
+        }
+
+        self.rounds_left = self.rounds_left.saturating_sub(1);
+        if self.rounds_left == 0 {
+            self.reset();
+        }
+    }
+}
+
+struct Noise {
+    source: usize,
+    area_id: String,
+    p: Point,
+    radius: f32,
+}
+
+/// Returns whether the entity is an actor that can notice the party
+pub fn is_observer(entity: &EntityState) -> bool {
+    !entity.actor.is_dead()
+        && !entity.is_party_member()
+        && !entity.is_ai_active()
+        && entity.actor.actor.ai.is_some()
+}
+
+pub fn perception(entity: &EntityState) -> i32 {
+    let perception = entity.actor.stats.attributes.get(Attribute::Perception);
+    (perception as f32 * Module::rules().stealth.perception_factor) as i32
+}
+
+pub fn stealth(entity: &EntityState) -> i32 {
+    let stats = &entity.actor.stats;
+    if stats.hidden {
+        stats.concealment + Module::rules().stealth.hidden_stealth
+    } else {
+        stats.concealment
+    }
+}
+
+fn modifier(observer: &EntityState, target: &EntityState) -> i32 {
+    let rules = Module::rules();
+    let dist = crate::dist(observer, target);
+    perception(observer) - (dist * rules.stealth.distance_penalty) as i32 - stealth(target)
+}
+
+fn threshold(awareness: Awareness) -> i32 {
+    let rules = Module::rules();
+    match awareness {
+        Awareness::Unaware => i32::MIN,
+        Awareness::Suspicious => rules.stealth.suspicious_threshold,
+        Awareness::Alerted => rules.stealth.alert_threshold,
+    }
+}
+
+fn can_see(area: &AreaState, observer: &EntityState, target: &EntityState) -> bool {
+    crate::dist(observer, target) <= area.area.area.vis_dist as f32
+        && area.has_visibility(observer, target)
+}
+
+/// Returns the percent chance that a single roll by the `observer` against
+/// the `target` reaches at least the specified `awareness`.  This is zero if
+/// the observer cannot see the target.
+pub fn chance(
+    area: &AreaState,
+    observer: &EntityState,
+    target: &EntityState,
+    awareness: Awareness,
+) -> u32 {
+    if awareness == Awareness::Unaware {
+        return 100;
+    }
+
+    if !can_see(area, observer, target) {
+        return 0;
+    }
+
+    // the roll is 1 to 100 inclusive
+    let min_roll = threshold(awareness) - modifier(observer, target);
+    (101 - min_roll).clamp(0, 100) as u32
+}
+
+/// Rolls the `observer`'s perception against the `target`'s stealth, returning
+/// the resulting awareness of the target
+pub fn roll(observer: &EntityState, target: &EntityState) -> Awareness {
+    let result = gen_rand(1, 101) + modifier(observer, target);
+
+    if result >= threshold(Awareness::Alerted) {
+        Awareness::Alerted
+    } else if result >= threshold(Awareness::Suspicious) {
+        Awareness::Suspicious
+    } else {
+        Awareness::Unaware
+    }
+}
+
+/// Makes a noise at the `source`'s location which may be heard by hostile
+/// actors within `radius`.  Noises are heard on the next update.
+pub fn add_noise(source: &EntityState, radius: f32) {
+    let radius = if source.actor.stats.hidden {
+        radius * Module::rules().stealth.hidden_noise_factor
+    } else {
+        radius
+    };
+
+    if radius <= 0.0 {
+        return;
+    }
+
+    let noise = Noise {
+        source: source.index(),
+        area_id: source.location.area_id.clone(),
+        p: source.location.to_point(),
+        radius,
+    };
+    NOISES.with(|noises| noises.borrow_mut().push(noise));
+}
+
+/// Makes any pending noises heard.  Called each frame.
+pub(crate) fn update() {
+    let noises: Vec<_> = NOISES.with(|noises| noises.borrow_mut().drain(..).collect());
+    if noises.is_empty() {
+        return;
+    }
+
+    let area = GameState::area_state();
+    let mgr = GameState::turn_manager();
+    for noise in noises {
+        if noise.area_id != area.borrow().area.area.id {
+            continue;
+        }
+
+        let source = match mgr.borrow().entity_checked(noise.source) {
+            None => continue,
+            Some(source) => source,
+        };
+
+        let observers = observers(&area.borrow());
+        for observer in observers {
+            if Rc::ptr_eq(&observer, &source) {
+                continue;
+            }
+
+            let mut observer = observer.borrow_mut();
+            if !observer.is_hostile(&source.borrow()) {
+                continue;
+            }
+
+            if observer.location.to_point().dist(noise.p) > noise.radius {
+                continue;
+            }
+
+            observer
+                .detection_mut()
+                .notice(Awareness::Suspicious, noise.p);
+        }
+    }
+}
+
+fn observers(area: &AreaState) -> Vec<Rc<RefCell<EntityState>>> {
+    let mgr = GameState::turn_manager();
+    let mgr = mgr.borrow();
+    area.entity_iter()
+        .map(|index| mgr.entity(*index))
+        .filter(|entity| is_observer(&entity.borrow()))
+        .collect()
+}
+
+/// Runs one round of detection for each actor in the current area, starting
+/// combat for any that are alerted and moving suspicious actors to
+/// investigate outside of combat
+pub fn update_round() {
+    let area = GameState::area_state();
+    let observers = observers(&area.borrow());
+    if observers.is_empty() {
+        return;
+    }
+
+    let hidden: Vec<_> = GameState::party()
+        .into_iter()
+        .filter(|entity| {
+            let entity = entity.borrow();
+            entity.actor.stats.hidden
+                && !entity.actor.is_dead()
+                && entity.location.is_in(&area.borrow())
+        })
+        .collect();
+
+    let mut alerted: Vec<Rc<RefCell<EntityState>>> = Vec::new();
+    for observer in observers.iter() {
+        observer.borrow_mut().detection_mut().elapse_round();
+
+        for target in hidden.iter() {
+            let (result, risk) = {
+                let observer = observer.borrow();
+                let target = target.borrow();
+                if !observer.is_hostile(&target) || !can_see(&area.borrow(), &observer, &target) {
+                    continue;
+                }
+                let risk = chance(&area.borrow(), &observer, &target, Awareness::Suspicious);
+                (roll(&observer, &target), risk)
+            };
+
+            let p = target.borrow().location.to_point();
+            let mut observer = observer.borrow_mut();
+            let detection = observer.detection_mut();
+            detection.risk = std::cmp::max(detection.risk, risk);
+            detection.notice(result, p);
+
+            if result == Awareness::Alerted && !alerted.iter().any(|e| Rc::ptr_eq(e, target)) {
+                alerted.push(Rc::clone(target));
+            }
+        }
+    }
+
+    for target in alerted {
+        info!("'{}' was detected", target.borrow().actor.actor.name);
+        let mgr = GameState::turn_manager();
+        mgr.borrow_mut()
+            .check_ai_activation(&target, &mut area.borrow_mut());
+    }
+
+    if GameState::is_combat_active() || GameState::is_modal_locked() {
+        return;
+    }
+
+    for observer in observers {
+        investigate(&observer);
+    }
+}
+
+fn investigate(entity: &Rc<RefCell<EntityState>>) {
+    let dest = {
+        let entity = entity.borrow();
+        if !is_observer(&entity) || entity.actor.stats.move_disabled {
+            return;
+        }
+
+        let detection = entity.detection();
+        if detection.awareness() != Awareness::Suspicious {
+            return;
+        }
+
+        match detection.last_seen() {
+            None => return,
+            Some(p) => {
+                if entity.location.to_point().dist(p) <= ARRIVED_DIST {
+                    return;
+                }
+                p
+            }
+        }
+    };
+
+    if GameState::has_blocking_animations(entity) {
+        return;
+    }
+
+    let index = entity.borrow().index();
+    let mut dest = GameState::get_point_dest(&entity.borrow(), dest.x as f32, dest.y as f32);
+    // the point may be occupied by whatever was noticed there
+    dest.dist = ARRIVED_DIST;
+    GameState::move_towards_dest(entity, &[index], dest, None);
+}
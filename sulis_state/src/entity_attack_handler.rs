@@ -18,7 +18,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use sulis_core::io::Audio;
-use crate::{center, is_threat, ActorState, EntityState, GameState};
+use crate::{center, detection, is_threat, ActorState, EntityState, GameState};
 use sulis_module::{AccuracyKind, Attack, AttackKind, DamageKind, HitFlags, HitKind, Module,
     OnTrigger};
 
@@ -163,6 +163,7 @@ fn attack_internal(
     sneak_attack: bool,
 ) -> (HitKind, HitFlags, Vec<(DamageKind, u32)>) {
     let rules = Module::rules();
+    detection::add_noise(&parent.borrow(), rules.stealth.attack_noise_radius);
 
     let concealment = std::cmp::max(
         0,
@@ -25,22 +25,28 @@ use sulis_core::config::Config;
 
 use crate::animation::{self, Anim};
 use crate::save_state::EntitySaveState;
-use crate::script::{self, CallbackData, ScriptEntitySet};
+use crate::script::{self, CallbackData, CampaignEvent, ScriptEntitySet};
 use crate::{
     entity_attack_handler::weapon_attack, entity_texture_cache::Slot, is_within_attack_dist,
-    ActorState, AreaState, ChangeListenerList, EntityTextureCache, EntityTextureSlot, GameState,
-    Location, ScriptCallback, TurnManager,
+    ActorState, AreaState, ChangeListenerList, Detection, EntityTextureCache, EntityTextureSlot,
+    GameState, IdleBehavior, Location, PartyAIMode, ScriptCallback, TurnManager,
 };
 use sulis_core::io::GraphicsRenderer;
 use sulis_core::ui::{color, Color};
 use sulis_core::util::{invalid_data_error, Offset, Scale, Size, Point};
 use sulis_module::area::MAX_AREA_SIZE;
 use sulis_module::{
-    actor::Faction, ai, Actor, DamageKind, HitKind, Module, ObjectSize, ObjectSizeIterator,
+    actor::Faction, ai, AIRole, AITemplate, Actor, CampaignEventKind, DamageKind, HitKind, Module, ObjectSize,
+    ObjectSizeIterator,
 };
 
 enum AIState {
-    Player { vis: Vec<bool>, show_portrait: bool },
+    Player {
+        vis: Vec<bool>,
+        show_portrait: bool,
+        ai_mode: PartyAIMode,
+        ai_template: Option<Rc<AITemplate>>,
+    },
     AI { group: Option<usize>, active: bool },
 }
 
@@ -60,6 +66,8 @@ pub struct EntityState {
     texture_cache_slot: Option<EntityTextureSlot>,
 
     custom_flags: HashMap<String, String>,
+    idle_behavior: Option<IdleBehavior>,
+    detection: Detection,
 
     index: usize,      // index in vec of the owning manager
     unique_id: String, // assigned when setting the index and persisted on save
@@ -85,9 +93,18 @@ impl EntityState {
             },
             Some(_) => {
                 let dim = (MAX_AREA_SIZE * MAX_AREA_SIZE) as usize;
+                let ai_template = save.party_ai_template.as_ref().and_then(|id| {
+                    let template = Module::ai_template(id);
+                    if template.is_none() {
+                        warn!("Invalid party AI template '{}' for entity", id);
+                    }
+                    template
+                });
                 AIState::Player {
                     vis: vec![false; dim],
                     show_portrait: save.show_portrait,
+                    ai_mode: save.party_ai_mode,
+                    ai_template,
                 }
             }
         };
@@ -124,6 +141,8 @@ impl EntityState {
             marked_for_removal: false,
             texture_cache_slot: None,
             custom_flags: save.custom_flags,
+            idle_behavior: save.idle_behavior.map(IdleBehavior::load),
+            detection: save.detection.map(Detection::load).unwrap_or_default(),
             collapsed_groups: save.collapsed_groups,
         })
     }
@@ -140,6 +159,8 @@ impl EntityState {
             AIState::Player {
                 vis: vec![false; dim],
                 show_portrait: true,
+                ai_mode: PartyAIMode::Off,
+                ai_template: None,
             }
         } else {
             AIState::AI {
@@ -172,6 +193,8 @@ impl EntityState {
             ai_state,
             texture_cache_slot: None,
             custom_flags: HashMap::new(),
+            idle_behavior: None,
+            detection: Detection::default(),
             collapsed_groups: Vec::new(),
         }
     }
@@ -324,6 +347,8 @@ impl EntityState {
         self.ai_state = AIState::Player {
             vis: vec![false; dim],
             show_portrait,
+            ai_mode: PartyAIMode::Off,
+            ai_template: None,
         };
     }
 
@@ -341,6 +366,107 @@ impl EntityState {
         }
     }
 
+    pub fn party_ai_mode(&self) -> PartyAIMode {
+        match self.ai_state {
+            AIState::Player { ai_mode, .. } => ai_mode,
+            AIState::AI { .. } => PartyAIMode::Off,
+        }
+    }
+
+    pub fn set_party_ai_mode(&mut self, mode: PartyAIMode) {
+        if let AIState::Player { ref mut ai_mode, .. } = self.ai_state {
+            *ai_mode = mode;
+        }
+    }
+
+    /// Returns the AI template chosen for this party member, or the default
+    /// from the rules if none has been chosen
+    pub fn party_ai_template(&self) -> Option<Rc<AITemplate>> {
+        match self.ai_state {
+            AIState::Player {
+                ai_template: Some(ref template),
+                ..
+            } => Some(Rc::clone(template)),
+            AIState::Player { .. } => {
+                let id = &Module::rules().party_ai.default_template;
+                let template = Module::ai_template(id);
+                if template.is_none() {
+                    warn!("Invalid party AI default template '{}'", id);
+                }
+                template
+            }
+            AIState::AI { .. } => None,
+        }
+    }
+
+    pub(crate) fn chosen_party_ai_template(&self) -> Option<&Rc<AITemplate>> {
+        match self.ai_state {
+            AIState::Player {
+                ref ai_template, ..
+            } => ai_template.as_ref(),
+            AIState::AI { .. } => None,
+        }
+    }
+
+    pub fn set_party_ai_template(&mut self, template: Rc<AITemplate>) {
+        if let AIState::Player {
+            ref mut ai_template,
+            ..
+        } = self.ai_state
+        {
+            *ai_template = Some(template);
+        }
+    }
+
+    /// Returns true if this is a party member whose turns are currently
+    /// being taken by the AI, which only happens in combat.  This takes the
+    /// turn manager as it may be called while the manager is borrowed.
+    pub fn is_party_ai_active(&self, mgr: &TurnManager) -> bool {
+        self.party_ai_mode() != PartyAIMode::Off && mgr.is_combat_active()
+    }
+
+    /// Returns the AI template used to run this entity's turns, if any
+    pub fn ai_template(&self) -> Option<Rc<AITemplate>> {
+        match self.party_ai_mode() {
+            PartyAIMode::Off if self.is_party_member() => None,
+            PartyAIMode::Off => self.actor.actor.ai.clone(),
+            PartyAIMode::Script => self
+                .actor
+                .actor
+                .ai
+                .clone()
+                .or_else(|| self.party_ai_template()),
+            PartyAIMode::Auto => self.party_ai_template(),
+        }
+    }
+
+    /// Returns this entity's patrol, schedule, and wandering behavior outside of combat
+    pub fn idle_behavior(&self) -> Option<&IdleBehavior> {
+        self.idle_behavior.as_ref()
+    }
+
+    pub fn idle_behavior_mut(&mut self) -> Option<&mut IdleBehavior> {
+        self.idle_behavior.as_mut()
+    }
+
+    pub fn set_idle_behavior(&mut self, behavior: Option<IdleBehavior>) {
+        self.idle_behavior = behavior;
+    }
+
+    /// Returns this entity's awareness of hidden party members and noises
+    pub fn detection(&self) -> &Detection {
+        &self.detection
+    }
+
+    pub fn detection_mut(&mut self) -> &mut Detection {
+        &mut self.detection
+    }
+
+    /// Returns the role this entity plays within its AI group, from its AI template
+    pub fn ai_role(&self) -> Option<AIRole> {
+        self.ai_template().and_then(|template| template.role)
+    }
+
     pub fn clear_pc_vis(&mut self) {
         match self.ai_state {
             AIState::Player { ref mut vis, .. } => unsafe {
@@ -491,16 +617,40 @@ impl EntityState {
         damage: Vec<(DamageKind, u32)>,
     ) {
         let hp_amount = damage.iter().map(|(_, amount)| amount).sum();
+        let was_alive = entity.borrow().actor.hp() > 0;
         entity.borrow_mut().actor.remove_hp(hp_amount);
 
         let targets = ScriptEntitySet::from_pair(entity, attacker);
 
         let mgr = GameState::turn_manager();
+        if let Some(group) = entity.borrow().ai_group() {
+            if entity.borrow().is_hostile(&attacker.borrow()) {
+                let attacker = attacker.borrow().index();
+                mgr.borrow_mut()
+                    .ai_blackboard_mut(group)
+                    .add_threat(attacker, hp_amount as f32);
+            }
+        }
+
         let cbs = entity.borrow().callbacks(&mgr.borrow());
         info!("Got {} cbs for {}", cbs.len(), entity.borrow().unique_id());
         cbs.iter()
             .for_each(|cb| cb.on_damaged(&targets, hit_kind, damage.clone()));
 
+        let hp = entity.borrow().actor.hp();
+        if was_alive && hp <= 0 {
+            cbs.iter().for_each(|cb| cb.on_death(&targets));
+
+            let targets = ScriptEntitySet::from_pair(attacker, entity);
+            let cbs = attacker.borrow().callbacks(&mgr.borrow());
+            cbs.iter().for_each(|cb| cb.on_kill(&targets));
+
+            let event = CampaignEvent::new(CampaignEventKind::EntityDied)
+                .with_entity("entity", entity)
+                .with_entity("killer", attacker);
+            GameState::add_campaign_event(event);
+        }
+
         let hp = entity.borrow().actor.hp();
         if hp <= 0 {
             debug!(
@@ -29,11 +29,15 @@ use sulis_module::{
 };
 
 use crate::animation::{particle_generator::Param, Anim, AnimSaveState, AnimState};
-use crate::script::{script_cache, script_callback, Script, ScriptCallback, ScriptEntity};
+use crate::script::script_storage::{self, StorageTable};
+use crate::script::{
+    campaign_event, debugger, script_cache, script_callback, CampaignEvent, Script, ScriptCallback,
+    ScriptEntity,
+};
 use crate::{
-    path_finder, transition_handler, AreaState, ChangeListener, ChangeListenerList, Effect,
-    EntityState, Formation, ItemList, Location, PartyStash, QuestStateSet, SaveState, TurnManager,
-    UICallback, WorldMapState, AI,
+    detection, hot_reload, idle_behavior, path_finder, transition_handler, AIBlackboard, AreaState,
+    ChangeListener, ChangeListenerList, Effect, EntityState, Formation, ItemList, Location,
+    PartyStash, QuestStateSet, SaveState, TurnManager, UICallback, WorldMapState, AI,
 };
 
 thread_local! {
@@ -44,6 +48,7 @@ thread_local! {
     static MODAL_LOCKED: Cell<bool> = const { Cell::new(false) };
     static ANIMATIONS: RefCell<AnimState> = const { RefCell::new(AnimState::new()) };
     static ANIMS_TO_ADD: RefCell<Vec<Anim>> = const { RefCell::new(Vec::new()) };
+    static CAMPAIGN_EVENTS: RefCell<Vec<CampaignEvent>> = const { RefCell::new(Vec::new()) };
     static COMBAT_INACTIVE_TIME: Cell<u32> = const { Cell::new(0) };
 }
 
@@ -81,6 +86,7 @@ impl GameState {
         CLEAR_ANIMS.with(|c| c.set(false));
         MODAL_LOCKED.with(|c| c.set(false));
         ANIMS_TO_ADD.with(|anims| anims.borrow_mut().clear());
+        CAMPAIGN_EVENTS.with(|events| events.borrow_mut().clear());
         AI.with(|ai| *ai.borrow_mut() = AI::new());
         script_cache::setup().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
 
@@ -158,6 +164,14 @@ impl GameState {
                 mgr.borrow_mut().ai_groups.insert(index, value);
             }
 
+            for blackboard in save_state.manager.ai_blackboards {
+                let group = blackboard.group;
+                let blackboard = AIBlackboard::load(blackboard, |index| {
+                    entities.get(&index).map(|entity| entity.borrow().index())
+                });
+                mgr.borrow_mut().ai_blackboards.insert(group, blackboard);
+            }
+
             for effect_save in save_state.manager.effects {
                 let old_index = effect_save.index;
                 let new_index = mgr.borrow().get_next_effect_index();
@@ -239,7 +253,8 @@ impl GameState {
             let mut world_map = save_state.world_map;
             world_map.load();
 
-            mgr.borrow_mut().finish_load();
+            mgr.borrow_mut().finish_load(save_state.rand);
+            script_storage::load(save_state.storage);
             area_state.borrow().update_ambient_audio(&mgr.borrow().current_time());
             area_state.borrow().update_music(false, None);
 
@@ -284,13 +299,16 @@ impl GameState {
         CLEAR_ANIMS.with(|c| c.set(false));
         MODAL_LOCKED.with(|c| c.set(false));
         ANIMS_TO_ADD.with(|anims| anims.borrow_mut().clear());
+        CAMPAIGN_EVENTS.with(|events| events.borrow_mut().clear());
         AI.with(|ai| *ai.borrow_mut() = AI::new());
 
         TURN_MANAGER.with(|mgr| {
             let rules = Module::rules();
             let starting_time = Module::campaign().starting_time;
             mgr.borrow_mut().load(rules.compute_millis(starting_time));
+            mgr.borrow_mut().init_rand();
         });
+        script_storage::load(StorageTable::default());
 
         script_cache::setup().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
         let game_state = GameState::new(pc_actor, party_actors, flags)?;
@@ -933,6 +951,9 @@ impl GameState {
 
     #[must_use]
     pub fn update(millis: u32) -> Option<UICallback> {
+        hot_reload::update(millis);
+        debugger::update();
+
         let ui_cb = STATE.with(|s| {
             let mut state = s.borrow_mut();
             let state = state.as_mut().unwrap();
@@ -951,15 +972,28 @@ impl GameState {
             .for_each(|cb| cb.on_anim_complete());
 
         let mgr = GameState::turn_manager();
+        let prev_round = mgr.borrow().current_round();
         let update_cbs = mgr.borrow_mut().update(millis);
         script_callback::fire_cbs(update_cbs);
 
+        if mgr.borrow().current_round() != prev_round {
+            detection::update_round();
+            idle_behavior::update_round();
+        }
+        detection::update();
+
         let triggered_cbs = mgr.borrow_mut().drain_triggered_cbs();
         script_callback::fire_cbs(triggered_cbs);
 
         let cbs = mgr.borrow_mut().update_entity_move_callbacks();
         script_callback::fire_on_moved(cbs);
 
+        script_callback::fire_on_items_picked_up();
+
+        let events: Vec<CampaignEvent> =
+            CAMPAIGN_EVENTS.with(|e| e.borrow_mut().drain(..).collect());
+        campaign_event::fire(events);
+
         {
             let area_state = GameState::area_state();
             let mut area_state = area_state.borrow_mut();
@@ -1062,6 +1096,12 @@ impl GameState {
         ANIMATIONS.with(|a| a.borrow_mut().clear_all_blocking_anims());
     }
 
+    /// Queues the specified event for the campaign's `event_scripts`.  It is
+    /// fired on the next update
+    pub fn add_campaign_event(event: CampaignEvent) {
+        CAMPAIGN_EVENTS.with(|e| e.borrow_mut().push(event));
+    }
+
     pub fn add_animation(anim: Anim) {
         ANIMS_TO_ADD.with(|a| {
             let mut anims = a.borrow_mut();
@@ -1084,8 +1124,10 @@ impl GameState {
     /// Returns true if the PC has the current turn, false otherwise
     pub fn is_pc_current() -> bool {
         let mgr = GameState::turn_manager();
-        if let Some(entity) = mgr.borrow().current() {
-            return entity.borrow().is_party_member();
+        let mgr = mgr.borrow();
+        if let Some(entity) = mgr.current() {
+            let entity = entity.borrow();
+            return entity.is_party_member() && !entity.is_party_ai_active(&mgr);
         }
 
         false
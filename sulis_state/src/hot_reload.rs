@@ -0,0 +1,198 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Reloads changed scripts, abilities, and items while a game is running, either
+//! on request from the console or automatically when `hot_reload` is set in the
+//! debug config.  Other resources are only reloaded from the main menu.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use sulis_core::config::Config;
+use sulis_module::{Ability, Module};
+
+use crate::script::script_cache;
+use crate::GameState;
+
+const CHECK_INTERVAL_MILLIS: u32 = 1000;
+
+const WATCHED_DIRS: [&str; 3] = ["scripts", "abilities", "items"];
+
+thread_local! {
+    static WATCHER: RefCell<Option<Watcher>> = const { RefCell::new(None) };
+}
+
+struct Watcher {
+    dirs: Vec<String>,
+    elapsed_millis: u32,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    fn new(dirs: Vec<String>) -> Watcher {
+        let modified = scan(&dirs);
+        Watcher {
+            dirs,
+            elapsed_millis: 0,
+            modified,
+        }
+    }
+
+    /// Returns whether any scripts and any YAML files have changed since the
+    /// last check
+    fn check(&mut self) -> (bool, bool) {
+        let modified = scan(&self.dirs);
+
+        let mut changed: Vec<&PathBuf> = modified
+            .iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(*time))
+            .map(|(path, _)| path)
+            .collect();
+        changed.extend(
+            self.modified
+                .keys()
+                .filter(|path| !modified.contains_key(*path)),
+        );
+
+        let scripts = changed.iter().any(|path| has_extension(path, &["lua"]));
+        let yaml = changed
+            .iter()
+            .any(|path| has_extension(path, &["yml", "json"]));
+        self.modified = modified;
+        (scripts, yaml)
+    }
+}
+
+/// Checks the resource directories for changed files once per second and
+/// reloads any that have changed, if hot reloading is enabled
+pub fn update(millis: u32) {
+    if !Config::debug().hot_reload {
+        return;
+    }
+
+    let (scripts, yaml) = WATCHER.with(|watcher| {
+        let mut watcher = watcher.borrow_mut();
+        let dirs = Module::resource_dirs();
+        let watcher = match watcher.as_mut() {
+            Some(watcher) if watcher.dirs == dirs => watcher,
+            _ => {
+                *watcher = Some(Watcher::new(dirs));
+                return (false, false);
+            }
+        };
+
+        watcher.elapsed_millis += millis;
+        if watcher.elapsed_millis < CHECK_INTERVAL_MILLIS {
+            return (false, false);
+        }
+        watcher.elapsed_millis = 0;
+        watcher.check()
+    });
+
+    if scripts || yaml {
+        reload(scripts, yaml);
+    }
+}
+
+/// Reloads all changed scripts if `scripts` is set, and all changed abilities and
+/// items if `yaml` is set.  The abilities of entities in the current game are
+/// updated in place, while existing items keep their old definitions.  Returns a
+/// summary of what was reloaded.
+pub fn reload(scripts: bool, yaml: bool) -> String {
+    let mut summary = Vec::new();
+
+    if scripts {
+        let ids = Module::reload_scripts();
+        let mut failures = script_cache::reload(&ids);
+        summary.push(format!("Reloaded {} script(s)", ids.len() - failures.len()));
+        summary.append(&mut failures);
+    }
+
+    if yaml {
+        match Module::reload_abilities_and_items() {
+            Err(e) => {
+                warn!("Unable to reload abilities and items: {}", e);
+                summary.push(format!("Unable to reload abilities and items: {e}"));
+            }
+            Ok((abilities, items)) => {
+                update_ability_states(&abilities);
+                summary.push(format!(
+                    "Reloaded {} abilities and {} items",
+                    abilities.len(),
+                    items.len()
+                ));
+            }
+        }
+    }
+
+    let summary = summary.join("\n");
+    info!("{}", summary);
+    summary
+}
+
+fn update_ability_states(abilities: &[Rc<Ability>]) {
+    let mgr = GameState::turn_manager();
+    let mgr = mgr.borrow();
+    for entity in mgr.entity_iter() {
+        let mut entity = entity.borrow_mut();
+        for ability in abilities.iter() {
+            if ability.active.is_none() {
+                continue;
+            }
+
+            if let Some(state) = entity.actor.ability_states.get_mut(&ability.id) {
+                state.reload(ability);
+            }
+        }
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    match path.extension() {
+        None => false,
+        Some(ext) => extensions.iter().any(|e| ext == *e),
+    }
+}
+
+fn scan(dirs: &[String]) -> HashMap<PathBuf, SystemTime> {
+    let mut modified = HashMap::new();
+    for dir in dirs {
+        for subdir in WATCHED_DIRS.iter() {
+            scan_recursive(&Path::new(dir).join(subdir), &mut modified);
+        }
+    }
+    modified
+}
+
+fn scan_recursive(dir: &Path, modified: &mut HashMap<PathBuf, SystemTime>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            scan_recursive(&path, modified);
+        } else if let Ok(time) = entry.metadata().and_then(|m| m.modified()) {
+            modified.insert(path, time);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Out of combat behavior for actors placed in an area with an
+//! `ActorBehavior`: walking patrol routes, following time of day schedules,
+//! wandering around their post, and returning to it after combat.  Each
+//! actor in the current area takes one step of its behavior per round.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sulis_core::util::{gen_rand, Point};
+use sulis_module::area::{ActorBehavior, Waypoint};
+use sulis_module::Module;
+
+use crate::save_state::IdleBehaviorSaveState;
+use crate::{Awareness, EntityState, GameState};
+
+/// How close an actor must be to a point to count as having arrived
+const ARRIVED_DIST: f32 = 1.5;
+
+/// The chance out of 100 each round that a wandering actor moves
+const WANDER_CHANCE: u32 = 35;
+
+/// The number of random squares tried when picking a place to wander to
+const WANDER_TRIES: u32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct IdleBehavior {
+    behavior: ActorBehavior,
+    home: Point,
+    next_waypoint: usize,
+    rounds_waited: u32,
+}
+
+enum Goal {
+    Post(Point),
+    Patrol(Waypoint),
+}
+
+impl IdleBehavior {
+    /// Creates the behavior for an actor placed at `home`.  Schedule entries
+    /// with an hour past the end of the day are dropped.
+    pub fn new(mut behavior: ActorBehavior, home: Point) -> IdleBehavior {
+        let hours_per_day = Module::rules().hours_per_day;
+        behavior.schedule.retain(|entry| {
+            if entry.hour >= hours_per_day {
+                warn!(
+                    "Invalid schedule hour {} for actor at {},{}",
+                    entry.hour, home.x, home.y
+                );
+                return false;
+            }
+            true
+        });
+        behavior.schedule.sort_by_key(|entry| entry.hour);
+
+        IdleBehavior {
+            behavior,
+            home,
+            next_waypoint: 0,
+            rounds_waited: 0,
+        }
+    }
+
+    pub(crate) fn load(save: IdleBehaviorSaveState) -> IdleBehavior {
+        let mut behavior = IdleBehavior::new(save.behavior, save.home);
+        if save.next_waypoint < behavior.behavior.patrol.len() {
+            behavior.next_waypoint = save.next_waypoint;
+        }
+        behavior.rounds_waited = save.rounds_waited;
+        behavior
+    }
+
+    pub(crate) fn save(&self) -> IdleBehaviorSaveState {
+        IdleBehaviorSaveState {
+            behavior: self.behavior.clone(),
+            home: self.home,
+            next_waypoint: self.next_waypoint,
+            rounds_waited: self.rounds_waited,
+        }
+    }
+
+    pub fn behavior(&self) -> &ActorBehavior {
+        &self.behavior
+    }
+
+    /// Returns the point the actor currently keeps to, either its post or
+    /// the waypoint it is walking to
+    pub fn post(&self, hour: u32) -> Point {
+        match self.goal(hour) {
+            Goal::Post(p) => p,
+            Goal::Patrol(waypoint) => waypoint.location,
+        }
+    }
+
+    fn goal(&self, hour: u32) -> Goal {
+        let schedule = &self.behavior.schedule;
+        let entry = schedule
+            .iter()
+            .rev()
+            .find(|entry| entry.hour <= hour)
+            .or_else(|| schedule.last());
+
+        if let Some(location) = entry.and_then(|entry| entry.location) {
+            return Goal::Post(location);
+        }
+
+        match self.behavior.patrol.get(self.next_waypoint) {
+            None => Goal::Post(self.home),
+            Some(waypoint) => Goal::Patrol(*waypoint),
+        }
+    }
+
+    /// Decides where the actor at `cur` should move this round, if anywhere
+    fn step(&mut self, cur: Point, hour: u32) -> Option<Point> {
+        let post = match self.goal(hour) {
+            Goal::Post(post) => post,
+            Goal::Patrol(waypoint) => {
+                if cur.dist(waypoint.location) > ARRIVED_DIST {
+                    return Some(waypoint.location);
+                }
+
+                if self.rounds_waited < waypoint.wait_rounds {
+                    self.rounds_waited += 1;
+                    return None;
+                }
+
+                self.rounds_waited = 0;
+                self.next_waypoint = (self.next_waypoint + 1) % self.behavior.patrol.len();
+                return Some(self.behavior.patrol[self.next_waypoint].location);
+            }
+        };
+
+        let radius = self.behavior.wander_radius as f32;
+        if cur.dist(post) > radius + ARRIVED_DIST {
+            return Some(post);
+        }
+
+        None
+    }
+}
+
+/// Runs one round of idle behavior for each actor in the current area that
+/// has one.  Does nothing during combat or while the UI is modal locked.
+pub fn update_round() {
+    if GameState::is_combat_active() || GameState::is_modal_locked() {
+        return;
+    }
+
+    let hour = GameState::turn_manager().borrow().current_time().hour;
+    let area = GameState::area_state();
+    let entities: Vec<_> = {
+        let mgr = GameState::turn_manager();
+        let mgr = mgr.borrow();
+        area.borrow()
+            .entity_iter()
+            .map(|index| mgr.entity(*index))
+            .filter(|entity| entity.borrow().idle_behavior().is_some())
+            .collect()
+    };
+
+    for entity in entities {
+        update_entity(&entity, hour);
+    }
+}
+
+fn update_entity(entity: &Rc<RefCell<EntityState>>, hour: u32) {
+    {
+        let entity = entity.borrow();
+        if entity.actor.is_dead()
+            || entity.is_party_member()
+            || entity.is_ai_active()
+            || entity.actor.stats.move_disabled
+            || entity.detection().awareness() != Awareness::Unaware
+        {
+            return;
+        }
+    }
+
+    // don't interrupt movement or animations started by scripts
+    if GameState::has_blocking_animations(entity) {
+        return;
+    }
+
+    let cur = entity.borrow().location.to_point();
+    let dest = {
+        let mut entity = entity.borrow_mut();
+        let behavior = match entity.idle_behavior_mut() {
+            None => return,
+            Some(behavior) => behavior,
+        };
+        match behavior.step(cur, hour) {
+            Some(dest) => Some(dest),
+            None => {
+                let post = behavior.post(hour);
+                let radius = behavior.behavior.wander_radius;
+                if radius > 0 && gen_rand(1, 101) <= WANDER_CHANCE {
+                    find_wander_point(&entity, post, radius)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(dest) = dest {
+        let index = entity.borrow().index();
+        let dest = GameState::get_point_dest(&entity.borrow(), dest.x as f32, dest.y as f32);
+        GameState::move_towards_dest(entity, &[index], dest, None);
+    }
+}
+
+fn find_wander_point(entity: &EntityState, post: Point, radius: u32) -> Option<Point> {
+    let area = GameState::area_state();
+    let area = area.borrow();
+    let radius = radius as i32;
+    for _ in 0..WANDER_TRIES {
+        let x = post.x + gen_rand(-radius, radius + 1);
+        let y = post.y + gen_rand(-radius, radius + 1);
+        if !area.area.area.coords_valid(x, y) || post.dist(Point::new(x, y)) > radius as f32 {
+            continue;
+        }
+
+        if area.is_passable_for_entity(entity, x, y) {
+            return Some(Point::new(x, y));
+        }
+    }
+
+    None
+}
@@ -18,7 +18,10 @@
 extern crate log;
 
 mod ai;
-pub use self::ai::AI;
+pub use self::ai::{PartyAIMode, AI};
+
+mod ai_blackboard;
+pub use self::ai_blackboard::AIBlackboard;
 
 pub mod ability_state;
 pub use self::ability_state::AbilityState;
@@ -38,6 +41,9 @@ mod change_listener;
 pub use self::change_listener::ChangeListener;
 pub use self::change_listener::ChangeListenerList;
 
+pub mod detection;
+pub use self::detection::{Awareness, Detection};
+
 mod distance_finder;
 pub use self::distance_finder::{
     can_attack, center, center_i32, dist, is_threat, is_within, is_within_attack_dist,
@@ -66,6 +72,11 @@ pub use self::game_state::GameState;
 mod generated_area;
 pub use self::generated_area::{GeneratedArea, PregenOutput};
 
+pub mod idle_behavior;
+pub use self::idle_behavior::IdleBehavior;
+
+pub mod hot_reload;
+
 pub mod inventory;
 pub use self::inventory::Inventory;
 
@@ -103,17 +114,26 @@ mod range_indicator;
 pub use self::range_indicator::{RangeIndicator, RangeIndicatorHandler, RangeIndicatorImageSet};
 
 pub mod save_file;
-pub use self::save_file::SaveFile;
+pub use self::save_file::SaveKind;
 pub use self::save_file::SaveFileMetaData;
 
+pub mod save_migration;
+
 mod save_state;
 pub use self::save_state::SaveState;
 
 pub mod script;
 pub use self::script::{Script, ScriptCallback, ScriptState};
 
+pub mod simulation;
+pub use self::simulation::Simulation;
+
+pub mod tactical_planner;
+
 mod transition_handler;
 
+pub mod trigger_state;
+
 mod turn_manager;
 pub(crate) use self::turn_manager::TurnManager;
 
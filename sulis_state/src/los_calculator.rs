@@ -83,6 +83,18 @@ pub fn has_visibility(
 ) -> bool {
     let start_x = entity.location.x + entity.size.width / 2;
     let start_y = entity.location.y + entity.size.height / 2;
+    has_visibility_from(area, prop_vis_grid, start_x, start_y, target)
+}
+
+/// Returns whether the `target` is visible from the specified point, as if
+/// an entity were standing there
+pub fn has_visibility_from(
+    area: &GeneratedArea,
+    prop_vis_grid: &[bool],
+    start_x: i32,
+    start_y: i32,
+    target: &EntityState,
+) -> bool {
     let src_elev = area.layer_set.elevation(start_x, start_y);
 
     for p in target.location_points() {
@@ -18,8 +18,9 @@ use std::io::Error;
 use std::rc::Rc;
 
 use sulis_core::util::invalid_data_error;
-use sulis_module::{ItemState, LootList, Module, Time};
+use sulis_module::{CampaignEventKind, ItemState, LootList, Module, Time};
 
+use crate::script::CampaignEvent;
 use crate::{save_state::MerchantSaveState, ChangeListenerList, GameState, ItemList};
 
 pub struct MerchantState {
@@ -137,6 +138,36 @@ impl MerchantState {
         ((item_state.item.value as f32) * self.sell_frac).floor() as i32
     }
 
+    /// Buys one copy of the item at the specified index for the party, if the
+    /// party can afford it.  Returns the bought item
+    pub fn buy(&mut self, index: usize) -> Option<ItemState> {
+        let value = self.get_buy_price(&self.items.get(index)?.1);
+        if GameState::party_coins() < value {
+            return None;
+        }
+
+        let item_state = self.remove(index)?;
+        GameState::add_party_coins(-value);
+        self.fire_event(CampaignEventKind::ItemBought, &item_state, value);
+        Some(item_state)
+    }
+
+    /// Sells the specified item from the party to this merchant
+    pub fn sell(&mut self, item_state: ItemState) {
+        let value = self.get_sell_price(&item_state);
+        GameState::add_party_coins(value);
+        self.fire_event(CampaignEventKind::ItemSold, &item_state, value);
+        self.add(item_state);
+    }
+
+    fn fire_event(&self, kind: CampaignEventKind, item_state: &ItemState, price: i32) {
+        let event = CampaignEvent::new(kind)
+            .with_str("item", &item_state.item.id)
+            .with_str("merchant", &self.id)
+            .with_int("price", price);
+        GameState::add_campaign_event(event);
+    }
+
     pub fn add(&mut self, item_state: ItemState) {
         self.items.add(item_state);
 
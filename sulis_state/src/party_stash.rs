@@ -21,6 +21,9 @@ pub struct PartyStash {
     items: ItemList,
     coins_id: String,
     pub listeners: ChangeListenerList<PartyStash>,
+
+    // the IDs and quantities of items picked up since the last update
+    picked_up: Vec<(String, u32)>,
 }
 
 impl PartyStash {
@@ -30,6 +33,7 @@ impl PartyStash {
             items,
             coins_id,
             listeners: ChangeListenerList::default(),
+            picked_up: Vec::new(),
         }
     }
 
@@ -62,6 +66,20 @@ impl PartyStash {
         Some(index)
     }
 
+    /// Adds the item to this stash as with `add_item`, as an item newly gained by
+    /// the party.  This fires the `OnItemPickedUp` callbacks on the next update.
+    pub fn pick_up(&mut self, quantity: u32, item_state: ItemState) -> Option<usize> {
+        if quantity > 0 {
+            self.picked_up.push((item_state.item.id.clone(), quantity));
+        }
+
+        self.add_item(quantity, item_state)
+    }
+
+    pub(crate) fn drain_picked_up(&mut self) -> Vec<(String, u32)> {
+        self.picked_up.drain(..).collect()
+    }
+
     /// Returns whether or not this stash has at least one item
     /// with the specified ID
     pub fn has_item(&self, id: &str) -> bool {
@@ -100,7 +118,7 @@ impl PartyStash {
             let mut i = num_items - 1;
             loop {
                 if let Some((qty, item_state)) = prop_state.remove_all_at(i) {
-                    self.add_item(qty, item_state);
+                    self.pick_up(qty, item_state);
                 }
 
                 if i == 0 {
@@ -120,7 +138,7 @@ impl PartyStash {
         let prop_state = area_state.props_mut().get_mut(prop_index);
 
         if let Some((qty, item_state)) = prop_state.remove_all_at(item_index) {
-            self.add_item(qty, item_state);
+            self.pick_up(qty, item_state);
         }
 
         self.listeners.notify(self);
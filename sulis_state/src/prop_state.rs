@@ -125,7 +125,7 @@ impl PropState {
         let millis_offset = if millis_offset_range == 0 {
             0
         } else {
-            util::gen_rand(0, millis_offset_range)
+            util::gen_cosmetic_rand(0, millis_offset_range)
         };
 
         PropState {
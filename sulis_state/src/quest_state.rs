@@ -18,8 +18,9 @@ use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
-use crate::{save_state::QuestSaveState, ChangeListenerList};
-use sulis_module::{on_trigger::QuestEntryState, Module};
+use crate::script::CampaignEvent;
+use crate::{save_state::QuestSaveState, ChangeListenerList, GameState};
+use sulis_module::{on_trigger::QuestEntryState, CampaignEventKind, Module};
 
 pub struct QuestStateSet {
     quests: HashMap<String, QuestState>,
@@ -114,6 +115,10 @@ impl QuestStateSet {
     }
 
     pub fn set_state(&mut self, quest_id: &str, state: QuestEntryState) {
+        if self.state(quest_id) != state {
+            fire_quest_updated(quest_id, None, state);
+        }
+
         let mut done = false;
         if let Some(ref mut quest) = self.quests.get_mut(quest_id) {
             quest.state = state;
@@ -132,6 +137,10 @@ impl QuestStateSet {
     }
 
     pub fn set_entry_state(&mut self, quest_id: &str, entry: &str, state: QuestEntryState) {
+        if self.entry_state(quest_id, entry) != state {
+            fire_quest_updated(quest_id, Some(entry), state);
+        }
+
         let mut done = false;
         if let Some(ref mut quest) = self.quests.get_mut(quest_id) {
             quest.set_entry_state(entry, state);
@@ -154,6 +163,14 @@ impl QuestStateSet {
     }
 }
 
+fn fire_quest_updated(quest: &str, entry: Option<&str>, state: QuestEntryState) {
+    let mut event = CampaignEvent::new(CampaignEventKind::QuestUpdated).with_str("quest", quest);
+    if let Some(entry) = entry {
+        event = event.with_str("entry", entry);
+    }
+    GameState::add_campaign_event(event.with_str("state", &format!("{state:?}")));
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QuestState {
     id: String,
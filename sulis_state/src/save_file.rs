@@ -15,41 +15,64 @@
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
 use std::fs::{self, File};
-use std::io::{Error, Read};
+use std::io::{BufRead, BufReader, BufWriter, Error, Write};
 use std::path::{Path, PathBuf};
 use std::time;
 
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Serialize, Deserialize};
 
 use chrono::prelude::*;
 
+use crate::save_migration::{self, SAVE_FORMAT_VERSION};
 use crate::{GameState, SaveState};
-use sulis_core::resource::{read_single_resource_path, write_json_to_file};
-use sulis_core::util::invalid_data_error;
-use sulis_core::{config, serde_json, util};
-use sulis_module::Module;
+use sulis_core::config::{self, Config, SaveCompression};
+use sulis_core::extern_image::{self, ImageFormat, RgbaImage};
+use sulis_core::serde_json::{self, Value};
+use sulis_core::util::{self, invalid_data_error};
+use sulis_module::{Module, Time};
 
+const SAVE_EXTENSION: &str = "sav";
+
+// saves written before the header was added are a single uncompressed json object
+const LEGACY_SAVE_EXTENSION: &str = "json";
+
+// thumbnails are stored beside their save so listing saves never reads them
+const THUMBNAIL_EXTENSION: &str = "png";
+
+/// The first line of each save file.  It is kept small so the available saves can
+/// be listed without reading the (possibly compressed) state that follows it.
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct SaveFile {
+struct SaveFileHeader {
     meta: SaveFileMetaData,
-    state: SaveState,
+    compression: SaveCompression,
 }
 
-impl SaveFile {
-    fn from_json(data: &str) -> Result<Self, Error> {
-        let resource: Result<SaveFile, serde_json::Error> = serde_json::from_str(data);
+/// How a save was created, which determines its filename and when it is replaced
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum SaveKind {
+    /// A save with a unique, timestamped filename
+    #[default]
+    Quick,
 
-        match resource {
-            Ok(resource) => Ok(resource),
-            Err(error) => invalid_data_error(&format!("{error}")),
-        }
-    }
+    /// A save to a named slot, overwriting any previous save in that slot
+    Named(String),
+
+    /// An autosave, deleted once newer autosaves exceed the configured count
+    Auto,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct SaveFileMetaData {
+    #[serde(default)]
+    pub format_version: u32,
+
+    #[serde(default)]
+    pub kind: SaveKind,
+
     pub player_name: String,
 
     pub level: Option<u32>,
@@ -58,6 +81,21 @@ pub struct SaveFileMetaData {
     pub datetime: String,
     pub current_area_name: String,
 
+    #[serde(default)]
+    pub party: Vec<SavePartyMember>,
+
+    #[serde(default)]
+    pub play_time_millis: usize,
+
+    #[serde(default)]
+    pub time: Option<Time>,
+
+    #[serde(default)]
+    pub active_quest: Option<String>,
+
+    #[serde(default)]
+    pub mods: Vec<SaveModInfo>,
+
     #[serde(skip)]
     path: PathBuf,
 
@@ -65,6 +103,82 @@ pub struct SaveFileMetaData {
     pub error: Option<String>,
 }
 
+impl SaveFileMetaData {
+    /// Returns true if this save was written by a newer version of the game and
+    /// cannot be loaded
+    pub fn is_from_newer_version(&self) -> bool {
+        self.format_version > SAVE_FORMAT_VERSION
+    }
+
+    /// Describes each difference between the mods active when this save was created
+    /// and the currently active mods
+    pub fn mod_differences(&self) -> Vec<String> {
+        let active = Module::active_mods();
+        let mut differences = Vec::new();
+
+        for saved in self.mods.iter() {
+            match active.iter().find(|info| info.id == saved.id) {
+                None => differences.push(format!("'{}' is no longer active", saved.name)),
+                Some(info) if info.version != saved.version => differences.push(format!(
+                    "'{}' has changed from version '{}' to '{}'",
+                    saved.name, saved.version, info.version
+                )),
+                Some(_) => (),
+            }
+        }
+
+        for info in active.iter() {
+            if !self.mods.iter().any(|saved| saved.id == info.id) {
+                differences.push(format!("'{}' was not active in this save", info.name));
+            }
+        }
+
+        differences
+    }
+
+    /// Reads and decodes the thumbnail stored beside this save, if there is one
+    pub fn thumbnail_image(&self) -> Option<RgbaImage> {
+        let path = self.path.with_extension(THUMBNAIL_EXTENSION);
+        if !path.is_file() {
+            return None;
+        }
+
+        let png = match fs::read(&path) {
+            Ok(png) => png,
+            Err(e) => {
+                warn!("Unable to read save thumbnail at {:?}: {}", path, e);
+                return None;
+            }
+        };
+
+        match extern_image::load_from_memory_with_format(&png, ImageFormat::Png) {
+            Ok(image) => Some(image.to_rgba8()),
+            Err(e) => {
+                warn!("Unable to decode save thumbnail: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SavePartyMember {
+    pub name: String,
+    pub level: u32,
+    pub class: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SaveModInfo {
+    pub id: String,
+    pub name: String,
+
+    #[serde(default)]
+    pub version: String,
+}
+
 fn get_save_dir() -> PathBuf {
     let mut path = config::USER_DIR.clone();
     path.push("save");
@@ -74,22 +188,99 @@ fn get_save_dir() -> PathBuf {
 
 pub fn delete_save(save_file: &SaveFileMetaData) -> Result<(), Error> {
     let path = save_file.path.as_path();
-    fs::remove_file(path)
+    fs::remove_file(path)?;
+
+    let thumbnail = path.with_extension(THUMBNAIL_EXTENSION);
+    if thumbnail.is_file() {
+        fs::remove_file(thumbnail)?;
+    }
+    Ok(())
 }
 
+/// Reads the save state for the specified save, migrating it from older formats if
+/// needed.  Any content that no longer exists in the module is removed from the state,
+/// and may be inspected with `SaveState::removed_content`.
 pub fn load_state(save_file: &SaveFileMetaData) -> Result<SaveState, Error> {
     let path = save_file.path.as_path();
-    let save_file: SaveFile = read_single_resource_path(path)?;
+    let mut save = if is_legacy_save(path) {
+        read_json(path)?
+    } else {
+        read_save(path)?
+    };
+    save_migration::migrate(&mut save)?;
+
+    let mut state = take_field(&mut save, "state")?;
+
+    let removed = save_migration::remove_missing_content(&mut state);
+    for entry in removed.iter() {
+        warn!("{}", entry);
+    }
+
+    let mut state: SaveState = from_json_value(state)?;
+    state.removed_content = removed;
+    Ok(state)
+}
+
+/// A save of the game state at the time it was created, which has not yet been
+/// written to disk.  This allows a thumbnail to be captured before writing it.
+pub struct PendingSave {
+    kind: SaveKind,
+    path: PathBuf,
+    header: SaveFileHeader,
+    state: SaveState,
+}
+
+impl PendingSave {
+    pub fn kind(&self) -> &SaveKind {
+        &self.kind
+    }
+
+    /// Writes this save, along with the specified PNG `thumbnail` if present.
+    /// Named saves overwrite any existing save with the same name, while autosaves
+    /// cause the oldest autosaves to be removed.
+    pub fn write(self, thumbnail: Option<Vec<u8>>) -> Result<(), Error> {
+        let start_time = time::Instant::now();
+
+        write_save(self.path.as_path(), &self.header, &self.state)?;
+
+        info!(
+            "  Save to disk complete in {} secs",
+            util::format_elapsed_secs(start_time.elapsed())
+        );
+
+        // a thumbnail is not essential, so failing to write it does not fail the save
+        let thumbnail_path = self.path.with_extension(THUMBNAIL_EXTENSION);
+        let result = match thumbnail {
+            Some(png) => fs::write(&thumbnail_path, png),
+            None if thumbnail_path.is_file() => fs::remove_file(&thumbnail_path),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            warn!("Unable to write save thumbnail at {:?}", thumbnail_path);
+            warn!("{}", e);
+        }
+
+        if self.kind == SaveKind::Auto {
+            remove_old_autosaves();
+        }
 
-    Ok(save_file.state)
+        Ok(())
+    }
 }
 
-pub fn create_save() -> Result<(), Error> {
+/// Captures the current game state for saving.  The save is not written until
+/// `PendingSave::write` is called.
+pub fn create_save(kind: SaveKind) -> Result<PendingSave, Error> {
     let start_time = time::Instant::now();
     info!("Start save");
 
     let utc = Utc::now();
-    let filename = format!("save_{}.json", utc.format("%Y%m%d-%H%M%S%.3f"));
+    let timestamp = utc.format("%Y%m%d-%H%M%S%.3f");
+    let filename = match &kind {
+        SaveKind::Quick => format!("save_{timestamp}.{SAVE_EXTENSION}"),
+        SaveKind::Named(name) => format!("slot_{}.{SAVE_EXTENSION}", slot_filename(name)?),
+        SaveKind::Auto => format!("autosave_{timestamp}.{SAVE_EXTENSION}"),
+    };
 
     let mut path = get_save_dir();
     if !path.is_dir() {
@@ -99,7 +290,7 @@ pub fn create_save() -> Result<(), Error> {
 
     path.push(filename);
 
-    let meta = create_meta_data(utc.format("%c").to_string());
+    let meta = create_meta_data(utc.format("%c").to_string(), kind.clone());
 
     info!(
         "  Filename and meta data creation complete in {} secs",
@@ -108,35 +299,139 @@ pub fn create_save() -> Result<(), Error> {
 
     let state = SaveState::create();
 
-    let save = SaveFile { meta, state };
+    let header = SaveFileHeader {
+        meta,
+        compression: Config::saves_config().compression,
+    };
 
     info!(
         "  Save data created in {} secs",
         util::format_elapsed_secs(start_time.elapsed())
     );
 
-    let result = write_json_to_file(path.as_path(), &save);
+    Ok(PendingSave {
+        kind,
+        path,
+        header,
+        state,
+    })
+}
 
-    info!(
-        "  Save to disk complete in {} secs",
-        util::format_elapsed_secs(start_time.elapsed())
-    );
+/// Converts a save slot name into a string usable as part of a filename
+fn slot_filename(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return invalid_data_error("Save slot name must not be empty");
+    }
 
-    result
+    Ok(name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '_' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect())
 }
 
-fn create_meta_data(datetime: String) -> SaveFileMetaData {
+/// Writes the header line followed by the state.  The save is written to a
+/// temporary file first so a failed save never replaces an existing one.
+fn write_save(path: &Path, header: &SaveFileHeader, state: &SaveState) -> Result<(), Error> {
+    let temp_path = path.with_extension("tmp");
+
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    serde_json::to_writer(&mut writer, header)?;
+    writer.write_all(b"\n")?;
+
+    match header.compression {
+        SaveCompression::None => serde_json::to_writer_pretty(&mut writer, state)?,
+        SaveCompression::Gzip => {
+            let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+            serde_json::to_writer(&mut encoder, state)?;
+            encoder.finish()?;
+        }
+        SaveCompression::Zstd => {
+            let mut encoder = zstd::Encoder::new(&mut writer, 0)?;
+            serde_json::to_writer(&mut encoder, state)?;
+            encoder.finish()?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(&temp_path, path)
+}
+
+fn remove_old_autosaves() {
+    let count = Config::saves_config().autosave_count;
+    let saves = match get_available_save_files() {
+        Ok(saves) => saves,
+        Err(e) => {
+            warn!("Unable to read saves to remove old autosaves");
+            warn!("{}", e);
+            return;
+        }
+    };
+
+    // saves are sorted with the most recent first
+    let autosaves = saves.iter().filter(|save| save.kind == SaveKind::Auto);
+    for save in autosaves.skip(count) {
+        info!("Removing old autosave '{}'", save.path.to_string_lossy());
+        if let Err(e) = delete_save(save) {
+            warn!("Unable to remove old autosave");
+            warn!("{}", e);
+        }
+    }
+}
+
+fn create_meta_data(datetime: String, kind: SaveKind) -> SaveFileMetaData {
     let cur_area = GameState::area_state();
     let cur_area = cur_area.borrow();
     let player = GameState::player();
     let player = player.borrow();
 
+    let party = GameState::party()
+        .iter()
+        .map(|member| {
+            let actor = &member.borrow().actor.actor;
+            SavePartyMember {
+                name: actor.name.to_string(),
+                level: actor.total_level,
+                class: actor.base_class().name.to_string(),
+            }
+        })
+        .collect();
+
+    let mgr = GameState::turn_manager();
+    let mgr = mgr.borrow();
+
+    let active_quest = GameState::quest_state()
+        .current_quest()
+        .and_then(|id| Module::quest(id))
+        .map(|quest| quest.name.to_string());
+
+    let mods = Module::active_mods()
+        .into_iter()
+        .map(|info| SaveModInfo {
+            id: info.id,
+            name: info.name,
+            version: info.version,
+        })
+        .collect();
+
     SaveFileMetaData {
+        format_version: SAVE_FORMAT_VERSION,
+        kind,
         player_name: player.actor.actor.name.to_string(),
         level: Some(player.actor.actor.total_level),
         class: Some(player.actor.actor.base_class().name.to_string()),
         datetime,
         current_area_name: cur_area.area.area.name.to_string(),
+        party,
+        play_time_millis: mgr.total_elapsed_millis(),
+        time: Some(mgr.current_time()),
+        active_quest,
+        mods,
         path: Default::default(),
         error: None,
     }
@@ -159,33 +454,97 @@ pub fn has_available_save_files() -> bool {
             Ok(entry) => entry,
         };
 
-        let path = entry.path();
-        if !path.is_file() {
-            continue;
+        if is_save_file(&entry.path()) {
+            return true;
         }
+    }
 
-        let extension = match path.extension() {
-            None => continue,
-            Some(ext) => ext.to_string_lossy(),
-        };
+    false
+}
 
-        if extension != "json" {
-            continue;
-        }
+fn is_save_file(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
 
-        return true;
+    match path.extension() {
+        None => false,
+        Some(ext) => ext == SAVE_EXTENSION || ext == LEGACY_SAVE_EXTENSION,
     }
+}
 
-    false
+fn is_legacy_save(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == LEGACY_SAVE_EXTENSION)
 }
 
-fn read_save_file(path: &Path) -> Result<SaveFile, Error> {
-    let mut file = File::open(path)?;
+/// Opens the specified save and parses the header line, leaving the reader
+/// positioned at the start of the state
+fn read_header(path: &Path) -> Result<(Value, BufReader<File>), Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
 
-    let mut file_data = String::new();
-    file.read_to_string(&mut file_data)?;
+    match serde_json::from_str(&line) {
+        Ok(header) => Ok((header, reader)),
+        Err(error) => invalid_data_error(&format!("{error}")),
+    }
+}
 
-    SaveFile::from_json(&file_data)
+/// Reads the full save at the specified path, in the same form as a legacy save
+fn read_save(path: &Path) -> Result<Value, Error> {
+    let (mut header, reader) = read_header(path)?;
+    let compression: SaveCompression = from_json_value(take_field(&mut header, "compression")?)?;
+
+    let state: Value = match compression {
+        SaveCompression::None => serde_json::from_reader(reader)?,
+        SaveCompression::Gzip => serde_json::from_reader(GzDecoder::new(reader))?,
+        SaveCompression::Zstd => serde_json::from_reader(zstd::Decoder::with_buffer(reader)?)?,
+    };
+
+    let meta = take_field(&mut header, "meta")?;
+    Ok(serde_json::json!({ "meta": meta, "state": state }))
+}
+
+fn take_field(value: &mut Value, key: &str) -> Result<Value, Error> {
+    match value.get_mut(key) {
+        None => invalid_data_error(&format!("Save file has no '{key}'")),
+        Some(field) => Ok(field.take()),
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, Error> {
+    let data = fs::read_to_string(path)?;
+    match serde_json::from_str(&data) {
+        Ok(value) => Ok(value),
+        Err(error) => invalid_data_error(&format!("{error}")),
+    }
+}
+
+fn from_json_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, Error> {
+    match serde_json::from_value(value) {
+        Ok(value) => Ok(value),
+        Err(error) => invalid_data_error(&format!("{error}")),
+    }
+}
+
+/// Reads only the meta data from the specified save, migrating it if needed
+fn read_meta_data(path: &Path) -> Result<SaveFileMetaData, Error> {
+    let mut save = if is_legacy_save(path) {
+        read_json(path)?
+    } else {
+        read_header(path)?.0
+    };
+    let meta = take_field(&mut save, "meta")?;
+
+    let version = save_migration::format_version(&meta);
+    let mut header = serde_json::json!({ "meta": meta });
+    if let Err(e) = save_migration::migrate(&mut header) {
+        let mut meta = create_error_meta(path.to_path_buf(), e);
+        meta.format_version = version;
+        return Ok(meta);
+    }
+
+    from_json_value(take_field(&mut header, "meta")?)
 }
 
 fn create_error_meta(path: PathBuf, error: Error) -> SaveFileMetaData {
@@ -211,11 +570,18 @@ fn create_error_meta(path: PathBuf, error: Error) -> SaveFileMetaData {
     let datetime = time.format("%c").to_string();
 
     SaveFileMetaData {
+        format_version: 0,
+        kind: SaveKind::default(),
         player_name: "Unknown Player".to_string(),
         level: None,
         class: None,
         datetime,
         current_area_name: "Unknown Area".to_string(),
+        party: Vec::new(),
+        play_time_millis: 0,
+        time: None,
+        active_quest: None,
+        mods: Vec::new(),
         path,
         error: Some(error.to_string()),
     }
@@ -238,23 +604,14 @@ pub fn get_available_save_files() -> Result<Vec<SaveFileMetaData>, Error> {
         let entry = entry?;
 
         let path = entry.path();
-        if !path.is_file() {
-            continue;
-        }
-
-        let extension = match path.extension() {
-            None => continue,
-            Some(ext) => ext.to_string_lossy(),
-        };
-
-        if extension != "json" {
+        if !is_save_file(&path) {
             continue;
         }
 
         let path_buf = path.to_path_buf();
 
-        let save_file: SaveFile = match read_save_file(&path_buf) {
-            Ok(save_file) => save_file,
+        let mut meta = match read_meta_data(&path_buf) {
+            Ok(meta) => meta,
             Err(e) => {
                 warn!("Unable to read save file: {}", path_buf.to_string_lossy());
                 warn!("{}", e);
@@ -262,8 +619,6 @@ pub fn get_available_save_files() -> Result<Vec<SaveFileMetaData>, Error> {
                 continue;
             }
         };
-
-        let mut meta = save_file.meta;
         meta.path = path_buf;
 
         results.push(meta);
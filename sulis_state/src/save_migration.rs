@@ -0,0 +1,398 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Upgrades saved games written by older versions of the game.  Saves are migrated
+//! as raw JSON before being deserialized, so the save state types only ever need to
+//! describe the current format.
+//!
+//! To change the save format, increment `SAVE_FORMAT_VERSION` and add a `Migration`
+//! from the previous version to the end of `MIGRATIONS`.
+
+use std::collections::HashSet;
+use std::io::Error;
+
+use sulis_core::serde_json::{Map, Value};
+use sulis_core::util::invalid_data_error;
+use sulis_module::Module;
+
+/// The save format written by this build.  Saves from before versioning was added
+/// have no version and are treated as version 0.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+struct Migration {
+    from_version: u32,
+    description: &'static str,
+    apply: fn(&mut Value),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from_version: 0,
+    description: "Rename OnSwapWeapons callbacks to OnHeldChanged",
+    apply: rename_swap_weapons_callbacks,
+}];
+
+/// Reads the format version from the meta data of a save file
+pub fn format_version(meta: &Value) -> u32 {
+    meta.get("format_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+
+/// Upgrades the specified save file, including both meta data and state, to the
+/// current format version.  Returns an error if the save is from a newer version.
+pub fn migrate(save: &mut Value) -> Result<(), Error> {
+    let mut version = match save.get("meta") {
+        None => return invalid_data_error("Save file has no meta data"),
+        Some(meta) => format_version(meta),
+    };
+
+    if version > SAVE_FORMAT_VERSION {
+        return invalid_data_error(&format!(
+            "Save was created by a newer version of Sulis (save format {version}, \
+             this version supports up to {SAVE_FORMAT_VERSION})"
+        ));
+    }
+
+    for migration in MIGRATIONS.iter() {
+        if migration.from_version < version {
+            continue;
+        }
+
+        info!(
+            "Migrating save from format {}: {}",
+            migration.from_version, migration.description
+        );
+        (migration.apply)(save);
+        version = migration.from_version + 1;
+    }
+
+    if let Some(Value::Object(meta)) = save.get_mut("meta") {
+        meta.insert("format_version".to_string(), Value::from(version));
+    }
+
+    Ok(())
+}
+
+fn rename_swap_weapons_callbacks(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Object(funcs)) = map.get_mut("funcs") {
+                if let Some(func) = funcs.remove("OnSwapWeapons") {
+                    funcs.entry("OnHeldChanged").or_insert(func);
+                }
+            }
+
+            for child in map.values_mut() {
+                rename_swap_weapons_callbacks(child);
+            }
+        }
+        Value::Array(vec) => {
+            for child in vec.iter_mut() {
+                rename_swap_weapons_callbacks(child);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Removes any items, props, abilities, loot lists, and non party characters from the
+/// save state that no longer exist in the currently loaded module, along with any
+/// references to the removed characters.  Returns a description of each removed entry.
+pub fn remove_missing_content(state: &mut Value) -> Vec<String> {
+    let mut removed = Vec::new();
+
+    if let Some(Value::Array(stash)) = state.get_mut("stash") {
+        retain_item_entries(stash, "the party stash", &mut removed);
+    }
+
+    if let Some(Value::Object(areas)) = state.get_mut("areas") {
+        for (area_id, area) in areas.iter_mut() {
+            remove_missing_area_content(area_id, area, &mut removed);
+        }
+    }
+
+    let mut removed_entities = HashSet::new();
+    let mut removed_groups = HashSet::new();
+    if let Some(Value::Array(entities)) = state.pointer_mut("/manager/entities") {
+        entities.retain_mut(|entity| {
+            let unique_id = str_field(entity, "unique_id").to_string();
+            let is_party = entity.get("actor_base").is_some_and(|base| !base.is_null());
+            let actor_id = entity
+                .pointer("/actor/id")
+                .and_then(Value::as_str)
+                .unwrap_or("");
+            if !is_party && Module::actor(actor_id).is_none() {
+                removed.push(format!(
+                    "Removed '{unique_id}', actor '{actor_id}' no longer exists"
+                ));
+                if let Some(index) = entity.get("index").and_then(Value::as_u64) {
+                    removed_entities.insert(index);
+                }
+                if let Some(group) = entity.get("ai_group").and_then(Value::as_u64) {
+                    removed_groups.insert(group);
+                }
+                return false;
+            }
+
+            remove_missing_entity_content(&unique_id, entity, &mut removed);
+            true
+        });
+    }
+
+    if !removed_entities.is_empty() {
+        remove_entity_refs(state, &removed_entities, removed_groups);
+    }
+
+    removed
+}
+
+/// Removes all references to the specified entity indices.  The remaining indices
+/// are mapped to the new entity indices when the save is loaded.
+fn remove_entity_refs(state: &mut Value, removed: &HashSet<u64>, mut groups: HashSet<u64>) {
+    let is_removed = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_u64)
+            .is_some_and(|i| removed.contains(&i))
+    };
+
+    if let Some(Value::Array(selected)) = state.get_mut("selected") {
+        selected.retain(|index| !is_removed(Some(index)));
+    }
+
+    let mut removed_effects = HashSet::new();
+    if let Some(Value::Array(effects)) = state.pointer_mut("/manager/effects") {
+        effects.retain_mut(|effect| {
+            if is_removed(effect.get("entity")) || is_removed(effect.pointer("/surface/aura")) {
+                if let Some(index) = effect.get("index").and_then(Value::as_u64) {
+                    removed_effects.insert(index);
+                }
+                return false;
+            }
+
+            if let Some(Value::Array(callbacks)) = effect.get_mut("callbacks") {
+                callbacks.retain_mut(|cb| {
+                    if is_removed(cb.get("parent")) {
+                        return false;
+                    }
+
+                    remove_target_refs(cb, &is_removed);
+                    true
+                });
+            }
+            true
+        });
+    }
+
+    if let Some(Value::Array(anims)) = state.get_mut("anims") {
+        anims.retain(|anim| {
+            let effect = anim.get("removal_effect").and_then(Value::as_u64);
+            !is_removed(anim.get("owner")) && !effect.is_some_and(|i| removed_effects.contains(&i))
+        });
+    }
+
+    // keep any group that still has a member
+    if let Some(Value::Array(entities)) = state.pointer("/manager/entities") {
+        for entity in entities {
+            if let Some(group) = entity.get("ai_group").and_then(Value::as_u64) {
+                groups.remove(&group);
+            }
+        }
+    }
+
+    if let Some(Value::Object(ai_groups)) = state.pointer_mut("/manager/ai_groups") {
+        ai_groups.retain(|group, _| !group.parse().is_ok_and(|g: u64| groups.contains(&g)));
+    }
+
+    if let Some(Value::Array(blackboards)) = state.pointer_mut("/manager/ai_blackboards") {
+        blackboards.retain_mut(|blackboard| {
+            let group = blackboard.get("group").and_then(Value::as_u64);
+            if group.is_some_and(|g| groups.contains(&g)) {
+                return false;
+            }
+
+            for key in ["threat", "claims"] {
+                if let Some(Value::Array(entries)) = blackboard.get_mut(key) {
+                    entries.retain(|entry| !is_removed(entry.get(0)));
+                }
+            }
+
+            if is_removed(blackboard.get("focus")) {
+                blackboard["focus"] = Value::Null;
+            }
+            true
+        });
+    }
+}
+
+fn remove_target_refs(cb: &mut Value, is_removed: &impl Fn(Option<&Value>) -> bool) {
+    let targets = match cb.get_mut("targets") {
+        Some(targets) if !targets.is_null() => targets,
+        _ => return,
+    };
+
+    if is_removed(targets.get("parent")) {
+        *targets = Value::Null;
+        return;
+    }
+
+    if let Some(Value::Array(indices)) = targets.get_mut("indices") {
+        indices.retain(|index| !is_removed(Some(index)));
+    }
+}
+
+fn remove_missing_area_content(area_id: &str, area: &mut Value, removed: &mut Vec<String>) {
+    if let Some(Value::Array(props)) = area.get_mut("props") {
+        props.retain_mut(|prop| {
+            let id = str_field(prop, "id");
+            if Module::prop(id).is_none() {
+                removed.push(format!("Removed prop '{id}' from area '{area_id}'"));
+                return false;
+            }
+
+            if let Some(Value::Object(container)) = prop.pointer_mut("/interactive/Container") {
+                let desc = format!("a container in area '{area_id}'");
+                if let Some(Value::Array(items)) = container.get_mut("items") {
+                    retain_item_entries(items, &desc, removed);
+                }
+                clear_missing_loot_list(container, "loot_to_generate", &desc, removed);
+            }
+            true
+        });
+    }
+
+    if let Some(Value::Array(merchants)) = area.get_mut("merchants") {
+        for merchant in merchants.iter_mut() {
+            let desc = format!(
+                "merchant '{}' in area '{area_id}'",
+                str_field(merchant, "id")
+            );
+            if let Some(Value::Array(items)) = merchant.get_mut("items") {
+                retain_item_entries(items, &desc, removed);
+            }
+            if let Value::Object(map) = merchant {
+                clear_missing_loot_list(map, "loot_list_id", &desc, removed);
+            }
+        }
+    }
+}
+
+fn remove_missing_entity_content(unique_id: &str, entity: &mut Value, removed: &mut Vec<String>) {
+    if let Some(actor) = entity.get_mut("actor") {
+        for slots in ["equipped", "quick"] {
+            if let Some(Value::Array(items)) = actor.get_mut(slots) {
+                for item in items.iter_mut() {
+                    if !item.is_null() && !item_exists(item) {
+                        removed.push(format!(
+                            "Removed item '{}' from '{unique_id}'",
+                            str_field(item, "id")
+                        ));
+                        *item = Value::Null;
+                    }
+                }
+            }
+        }
+
+        if let Some(Value::Object(states)) = actor.get_mut("ability_states") {
+            states.retain(|id, _| {
+                let exists = Module::ability(id).is_some();
+                if !exists {
+                    removed.push(format!("Removed ability '{id}' from '{unique_id}'"));
+                }
+                exists
+            });
+        }
+    }
+
+    let base = match entity.get_mut("actor_base") {
+        Some(base) if !base.is_null() => base,
+        _ => return,
+    };
+
+    if let Some(Value::Array(abilities)) = base.get_mut("abilities") {
+        abilities.retain(|id| {
+            let id = id.as_str().unwrap_or("");
+            let exists = Module::ability(id).is_some();
+            if !exists {
+                removed.push(format!("Removed ability '{id}' from '{unique_id}'"));
+            }
+            exists
+        });
+    }
+
+    if let Some(Value::Object(inventory)) = base.get_mut("inventory") {
+        for slots in ["equipped", "quick"] {
+            if let Some(Value::Object(items)) = inventory.get_mut(slots) {
+                items.retain(|_, item| {
+                    let exists = item_exists(item);
+                    if !exists {
+                        removed.push(format!(
+                            "Removed item '{}' from '{unique_id}'",
+                            str_field(item, "id")
+                        ));
+                    }
+                    exists
+                });
+            }
+        }
+    }
+}
+
+fn retain_item_entries(entries: &mut Vec<Value>, desc: &str, removed: &mut Vec<String>) {
+    entries.retain(|entry| {
+        let item = entry.get("item").unwrap_or(&Value::Null);
+        let exists = item_exists(item);
+        if !exists {
+            removed.push(format!(
+                "Removed item '{}' from {desc}",
+                str_field(item, "id")
+            ));
+        }
+        exists
+    });
+}
+
+fn clear_missing_loot_list(
+    map: &mut Map<String, Value>,
+    key: &str,
+    desc: &str,
+    removed: &mut Vec<String>,
+) {
+    let id = match map.get(key).and_then(Value::as_str) {
+        None => return,
+        Some(id) => id.to_string(),
+    };
+
+    if Module::loot_list(&id).is_none() {
+        removed.push(format!("Removed loot list '{id}' from {desc}"));
+        map.insert(key.to_string(), Value::Null);
+    }
+}
+
+fn item_exists(item: &Value) -> bool {
+    let adjectives: Vec<String> = match item.get("adjectives") {
+        Some(Value::Array(adjectives)) => adjectives
+            .iter()
+            .filter_map(|a| a.as_str().map(|a| a.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Module::create_get_item(str_field(item, "id"), &adjectives).is_some()
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or("")
+}
@@ -22,18 +22,21 @@ use std::u64;
 
 use serde::{Serialize, Deserialize};
 
-use sulis_core::util::{ExtInt, Point};
+use sulis_core::util::{self, ExtInt, Point, ReproducibleRandom};
 use sulis_module::{
     actor::{ActorBuilder, RewardBuilder},
+    area::ActorBehavior,
     BonusList, ItemListEntrySaveState, ItemSaveState, QuickSlot, Slot,
 };
 
 use crate::animation::AnimSaveState;
 use crate::area_state::TriggerState;
+use crate::script::script_storage::{self, StorageTable};
 use crate::script::CallbackData;
 use crate::{
-    effect, prop_state::Interactive, turn_manager::EncounterRef, ActorState, Effect, EntityState,
-    Formation, GameState, Location, MerchantState, PStats, PropState, QuestState, WorldMapState,
+    effect, prop_state::Interactive, turn_manager::EncounterRef, ActorState, Awareness, Effect,
+    EntityState, Formation, GameState, Location, MerchantState, PStats, PartyAIMode, PropState,
+    QuestState, WorldMapState,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -56,6 +59,15 @@ pub struct SaveState {
 
     #[serde(default)]
     pub(crate) total_elapsed_millis: usize,
+
+    #[serde(default)]
+    pub(crate) rand: Option<ReproducibleRandom>,
+
+    #[serde(default)]
+    pub(crate) storage: StorageTable,
+
+    #[serde(skip)]
+    pub(crate) removed_content: Vec<String>,
 }
 
 fn default_zoom() -> f32 {
@@ -118,9 +130,18 @@ impl SaveState {
             world_map: GameState::world_map(),
             quests: quest_state,
             total_elapsed_millis,
+            rand: Some(util::game_rand()),
+            storage: script_storage::save(),
+            removed_content: Vec::new(),
         }
     }
 
+    /// Descriptions of any content that was removed from this save when it was
+    /// loaded, because it no longer exists in the module
+    pub fn removed_content(&self) -> &[String] {
+        &self.removed_content
+    }
+
     pub fn load(self) -> Result<(), Error> {
         GameState::load(self)
     }
@@ -140,6 +161,9 @@ pub struct ManagerSaveState {
     pub(crate) effects: Vec<EffectSaveState>,
     pub(crate) cur_ai_group_index: usize,
     pub(crate) ai_groups: HashMap<String, EncounterRef>,
+
+    #[serde(default)]
+    pub(crate) ai_blackboards: Vec<AIBlackboardSaveState>,
 }
 
 impl ManagerSaveState {
@@ -167,15 +191,49 @@ impl ManagerSaveState {
             ai_groups.insert(key.to_string(), value.clone());
         }
 
+        let ai_blackboards = mgr
+            .ai_blackboards
+            .iter()
+            .map(|(group, blackboard)| blackboard.save(*group))
+            .collect();
+
         ManagerSaveState {
             entities,
             effects,
             cur_ai_group_index,
             ai_groups,
+            ai_blackboards,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DetectionSaveState {
+    pub(crate) awareness: Awareness,
+    pub(crate) last_seen: Option<Point>,
+    pub(crate) rounds_left: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct IdleBehaviorSaveState {
+    pub(crate) behavior: ActorBehavior,
+    pub(crate) home: Point,
+    pub(crate) next_waypoint: usize,
+    pub(crate) rounds_waited: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AIBlackboardSaveState {
+    pub(crate) group: usize,
+    pub(crate) threat: Vec<(usize, f32)>,
+    pub(crate) focus: Option<usize>,
+    pub(crate) claims: Vec<(usize, Point)>,
+    pub(crate) flags: HashMap<String, String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct EffectSaveState {
@@ -435,6 +493,18 @@ pub struct EntitySaveState {
 
     #[serde(default)]
     pub(crate) collapsed_groups: Vec<String>,
+
+    #[serde(default)]
+    pub(crate) idle_behavior: Option<IdleBehaviorSaveState>,
+
+    #[serde(default)]
+    pub(crate) detection: Option<DetectionSaveState>,
+
+    #[serde(default)]
+    pub(crate) party_ai_mode: PartyAIMode,
+
+    #[serde(default)]
+    pub(crate) party_ai_template: Option<String>,
 }
 
 impl EntitySaveState {
@@ -508,6 +578,12 @@ impl EntitySaveState {
             show_portrait: entity.show_portrait(),
             actor_base,
             collapsed_groups: entity.collapsed_groups(),
+            idle_behavior: entity.idle_behavior().map(|behavior| behavior.save()),
+            detection: entity.detection().save(),
+            party_ai_mode: entity.party_ai_mode(),
+            party_ai_template: entity
+                .chosen_party_ai_template()
+                .map(|template| template.id.to_string()),
         }
     }
 }
@@ -32,17 +32,30 @@
 //! 4. Item Scripts: Similar to ability scripts, but called when using an item.  The entry point is
 //!    `on_activate(parent, item)`.
 //!
-//! Since standard Lua methods for referencing other script files will not work, Sulis includes
-//! a simple facility to include the contents of a script into another script.  This is done
-//! with a line in the target script:
+//! Since standard Lua methods for referencing other script files will not work, Sulis provides
+//! a `require(script_id)` function.  The first time a script is required by a given script
+//! state, it is run in that state, so any global functions it defines become available, and
+//! its return value is cached and returned.  Later calls return the cached value without running
+//! the script again.  Required scripts may require further scripts; requiring a script that is
+//! still being loaded is an error.  Errors and tracebacks refer to the required script's own ID
+//! and line numbers.
+//!
+//! For compatibility, a line in the form
 //! --INCLUDE <other_script_id>
-//! The entire contents of the referenced script will be placed into the script file in place
-//! of the --INCLUDE directive at evaluation time.
-//! --INCLUDE directives are only evaluated one level deep at this time.
+//! is equivalent to `require("<other_script_id>")`.  Unlike the text substitution previously
+//! used for `--INCLUDE`, this runs the other script as a separate chunk, so any top level
+//! `local` variables and functions it declares are not visible to the including script.  Only
+//! globals, and the value returned by the other script, are shared.
 
 mod area_targeter;
 pub use self::area_targeter::AreaTargeter;
 
+mod script_ai_group;
+pub use self::script_ai_group::ScriptAIGroup;
+
+pub mod campaign_event;
+pub use self::campaign_event::CampaignEvent;
+
 mod module_export;
 pub use self::module_export::ModuleExport;
 
@@ -51,6 +64,10 @@ pub use self::script_ability::{ScriptAbility, ScriptAbilitySet};
 
 pub mod script_cache;
 
+pub mod console;
+
+pub mod debugger;
+
 pub mod script_callback;
 pub use self::script_callback::{
     CallbackData, FuncKind, ScriptCallback, ScriptHitKind, TriggeredCallback,
@@ -88,6 +105,9 @@ pub use self::script_image_layer_animation::ScriptImageLayerAnimation;
 mod script_particle_generator;
 pub use self::script_particle_generator::ScriptParticleGenerator;
 
+pub mod script_storage;
+pub use self::script_storage::ScriptStorage;
+
 mod script_scale_animation;
 pub use self::script_scale_animation::ScriptScaleAnimation;
 
@@ -97,16 +117,22 @@ pub use self::script_subpos_animation::ScriptSubposAnimation;
 pub mod targeter;
 pub use self::targeter::TargeterData;
 
+pub mod test_runner;
+
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time;
 
-use rlua::{self, FromLuaMulti, Function, Lua, ToLuaMulti};
+use rlua::{self, FromLuaMulti, Function, Lua, Table, ToLuaMulti, Value};
 
 use crate::{ai, EntityState, GameState};
-use sulis_core::{config::Config, util::Point};
-use sulis_module::{Ability, DamageKind, HitKind, Module, QuickSlot};
+use sulis_core::{
+    benchmark,
+    config::Config,
+    util::{gen_rand, Point},
+};
+use sulis_module::{Ability, DamageKind, HitKind, Module, QuickSlot, ScriptQuota};
 
 pub type Result<T> = std::result::Result<T, rlua::Error>;
 
@@ -150,7 +176,7 @@ impl Script {
         arg: T,
         func: &str,
     ) where
-        T: rlua::UserData + Send + 'static,
+        T: for<'a> rlua::ToLua<'a> + Send,
     {
         if let Err(e) = script_cache::entity_script(parent, targets, Some(arg), func) {
             warn!("Error in entity with arg script '{}': {}", func, e);
@@ -196,7 +222,7 @@ impl Script {
         arg: T,
         func: &str,
     ) where
-        T: rlua::UserData + Send + 'static,
+        T: for<'a> rlua::ToLua<'a> + Send,
     {
         if let Err(e) = script_cache::item_script(parent, i_kind, targets, Some(arg), func) {
             warn!("Error in item with arg script '{}': {}", func, e);
@@ -280,7 +306,7 @@ impl Script {
         arg: T,
         func: &str,
     ) where
-        T: rlua::UserData + Send + 'static,
+        T: for<'a> rlua::ToLua<'a> + Send,
     {
         if let Err(e) = script_cache::ability_script(parent, ability, targets, Some(arg), func) {
             warn!("Error in ability script with arg '{}': {}", func, e);
@@ -307,16 +333,106 @@ impl Script {
             warn!("Error in trigger script '{}/{}': {}", script_id, func, e);
         }
     }
+
+    /// Compiles the specified script source without running it, returning any
+    /// syntax error found.
+    pub fn check_syntax(script_id: &str, script: &str) -> Result<()> {
+        let lua = Lua::new_with(get_rlua_std_lib());
+        lua.context(|lua| {
+            lua.load(script).set_name(script_id)?.into_function()?;
+            Ok(())
+        })
+    }
 }
 
-const MEM_LIMIT: usize = 10_485_760;
-const INSTRUCTION_LIMIT: u32 = 50_000;
+/// Registry keys for the results of required scripts, and the IDs of the
+/// scripts currently being loaded
+const LOADED_MODULES: &str = "loaded_modules";
+const LOADING_MODULES: &str = "loading_modules";
+
 const INSTRUCTIONS_PER_CHECK: u32 = 50;
-const MILLIS_LIMIT: f64 = 50.0;
+
+/// The kinds of script calls, each with their own `ScriptQuota` in the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    Ai,
+    Ability,
+    Trigger,
+    Console,
+}
+
+impl ScriptKind {
+    fn quota(self) -> ScriptQuota {
+        let rules = Module::rules();
+        let limits = &rules.script_limits;
+        match self {
+            ScriptKind::Ai => limits.ai,
+            ScriptKind::Ability => limits.ability,
+            ScriptKind::Trigger => limits.trigger,
+            ScriptKind::Console => limits.console,
+        }
+    }
+}
+
+/// The quota that was exceeded by a script call
+#[derive(Debug, Clone, Copy)]
+pub enum QuotaKind {
+    Instructions(u32),
+    MemoryKb(usize),
+    Millis(f64),
+}
+
+/// The error returned when a script call exceeds its quota
+#[derive(Debug, Clone)]
+pub struct QuotaError {
+    pub script: String,
+    pub func: String,
+    pub kind: ScriptKind,
+    pub quota: QuotaKind,
+}
+
+impl std::fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?} script '{}", self.kind, self.script)?;
+        if !self.func.is_empty() {
+            write!(f, ":{}", self.func)?;
+        }
+        match self.quota {
+            QuotaKind::Instructions(limit) => {
+                write!(f, "' exceeded its limit of {limit} instructions")
+            }
+            QuotaKind::MemoryKb(limit) => write!(f, "' exceeded its memory limit of {limit} KB"),
+            QuotaKind::Millis(limit) => write!(f, "' exceeded its time limit of {limit} millis"),
+        }
+    }
+}
+
+impl std::error::Error for QuotaError {}
+
+/// Returns the `QuotaError` that caused the specified error, if there is one
+pub fn quota_error(error: &rlua::Error) -> Option<&QuotaError> {
+    match error {
+        rlua::Error::CallbackError { cause, .. } => quota_error(cause),
+        rlua::Error::ExternalError(e) => e.downcast_ref::<QuotaError>(),
+        _ => None,
+    }
+}
+
+fn is_memory_error(error: &rlua::Error) -> bool {
+    match error {
+        rlua::Error::CallbackError { cause, .. } => is_memory_error(cause),
+        rlua::Error::MemoryError(_) => true,
+        _ => false,
+    }
+}
 
 pub struct InstructionState {
     count: u32,
     start_time: time::Instant,
+    script: String,
+    func: String,
+    kind: ScriptKind,
+    quota: ScriptQuota,
 }
 
 /// A script state, containing a complete lua state.
@@ -329,8 +445,14 @@ pub struct ScriptState {
 
 impl Default for ScriptState {
     fn default() -> ScriptState {
-        let lua = Lua::new_with(get_rlua_std_lib());
-        lua.set_memory_limit(Some(MEM_LIMIT));
+        let debugging = debugger::enabled();
+        let lua = if debugging {
+            debugger::new_lua(get_rlua_std_lib())
+        } else {
+            Lua::new_with(get_rlua_std_lib())
+        };
+        let quota = ScriptQuota::default();
+        lua.set_memory_limit(Some(quota.memory_kb * 1024));
 
         lua.context(|lua| {
             let globals = lua.globals();
@@ -341,11 +463,25 @@ impl Default for ScriptState {
                     warn!("{}", e);
                 }
             }
+
+            if let Err(e) = replace_math_random(lua) {
+                warn!("Error setting up Lua math.random");
+                warn!("{}", e);
+            }
+
+            if let Err(e) = add_require(lua) {
+                warn!("Error setting up Lua require");
+                warn!("{}", e);
+            }
         });
 
         let instructions = Arc::new(Mutex::new(InstructionState {
             count: 0,
             start_time: time::Instant::now(),
+            script: String::new(),
+            func: String::new(),
+            kind: ScriptKind::Trigger,
+            quota,
         }));
         let state = ScriptState {
             lua,
@@ -355,34 +491,60 @@ impl Default for ScriptState {
         };
 
         let instructions = Arc::clone(&state.instructions);
-        state.lua.set_hook(
-            rlua::HookTriggers {
-                every_nth_instruction: Some(INSTRUCTIONS_PER_CHECK),
-                ..Default::default()
-            },
-            move |_, _| {
+        if debugging {
+            // the debugger needs a hook on each line, so lines are counted
+            // against the instruction limit instead
+            let result = debugger::set_line_hook(&state.lua, move |paused| {
                 let state = &mut *instructions.lock().unwrap();
-                state.count += INSTRUCTIONS_PER_CHECK;
+                state.start_time += paused;
+                state.count += 1;
+                state.check_limits()
+            });
+
+            if let Err(e) = result {
+                warn!("Error setting up the Lua debugger hook");
+                warn!("{}", e);
+            }
+        } else {
+            state.lua.set_hook(
+                rlua::HookTriggers {
+                    every_nth_instruction: Some(INSTRUCTIONS_PER_CHECK),
+                    ..Default::default()
+                },
+                move |_, _| {
+                    let state = &mut *instructions.lock().unwrap();
+                    state.count += INSTRUCTIONS_PER_CHECK;
+                    state.check_limits()
+                },
+            );
+        }
 
-                if state.count > INSTRUCTION_LIMIT {
-                    return Err(rlua::Error::RuntimeError(format!(
-                        "Instruction limit of \
-                         {INSTRUCTION_LIMIT} reached"
-                    )));
-                }
+        state
+    }
+}
 
-                if get_elapsed_millis(state.start_time.elapsed()) > MILLIS_LIMIT {
-                    return Err(rlua::Error::RuntimeError(format!(
-                        "Script time limit of \
-                         {MILLIS_LIMIT} millis reached"
-                    )));
-                }
+impl InstructionState {
+    fn check_limits(&self) -> Result<()> {
+        if self.count > self.quota.instructions {
+            return Err(self.error(QuotaKind::Instructions(self.quota.instructions)));
+        }
 
-                Ok(())
-            },
-        );
+        if let Some(millis) = self.quota.millis {
+            if get_elapsed_millis(self.start_time.elapsed()) > millis {
+                return Err(self.error(QuotaKind::Millis(millis)));
+            }
+        }
 
-        state
+        Ok(())
+    }
+
+    fn error(&self, quota: QuotaKind) -> rlua::Error {
+        rlua::Error::external(QuotaError {
+            script: self.script.clone(),
+            func: self.func.clone(),
+            kind: self.kind,
+            quota,
+        })
     }
 }
 
@@ -400,22 +562,60 @@ impl ScriptState {
         );
     }
 
-    fn reset_instruction_state(&self) {
+    fn instructions(&self) -> u32 {
+        self.instructions.lock().unwrap().count
+    }
+
+    fn reset_instruction_state(&self, kind: ScriptKind, func: &str) {
+        let quota = kind.quota();
+        self.lua.set_memory_limit(Some(quota.memory_kb * 1024));
+
         let instructions = &mut *self.instructions.lock().unwrap();
         instructions.count = 0;
         instructions.start_time = time::Instant::now();
+        instructions.script = self.id.clone();
+        instructions.func = func.to_string();
+        instructions.kind = kind;
+        instructions.quota = quota;
+    }
+
+    /// Converts a Lua memory error into a `QuotaError` for the current call
+    fn check_memory_error(&self, error: rlua::Error) -> rlua::Error {
+        if !is_memory_error(&error) {
+            return error;
+        }
+
+        let instructions = self.instructions.lock().unwrap();
+        instructions.error(QuotaKind::MemoryKb(instructions.quota.memory_kb))
     }
 
     pub(in crate::script) fn load(&mut self, id: &str, script: &str) -> Result<()> {
         self.id = id.to_string();
+        self.reset_instruction_state(ScriptKind::Trigger, "");
         self.lua
-            .context(|lua| lua.load(&script).set_name(&id)?.exec())
+            .context(|lua| exec_module(lua, id, script).map(|_| ()))
+            .map_err(|e| self.check_memory_error(e))
+    }
+
+    /// Returns the IDs of all scripts that have been loaded into this state,
+    /// including this script itself
+    pub(in crate::script) fn modules(&self) -> Vec<String> {
+        let modules = self.lua.context(|lua| {
+            let loaded: Table = lua.named_registry_value(LOADED_MODULES)?;
+            loaded
+                .pairs::<String, Value>()
+                .map(|pair| pair.map(|(id, _)| id))
+                .collect::<Result<Vec<_>>>()
+        });
+
+        modules.unwrap_or_default()
     }
 
     pub(in crate::script) fn exec_func<Args, Ret>(
         &self,
         function: &str,
         args: Args,
+        kind: ScriptKind,
         report: bool,
     ) -> Result<Ret>
     where
@@ -424,7 +624,7 @@ impl ScriptState {
     {
         let cur_depth = self.current_depth.get();
         if cur_depth == 0 {
-            self.reset_instruction_state();
+            self.reset_instruction_state(kind, function);
         }
         self.current_depth.set(cur_depth + 1);
         if report {
@@ -435,21 +635,32 @@ impl ScriptState {
                 cur_depth + 1
             );
         }
+        let profile_start =
+            benchmark::profiling().then(|| (time::Instant::now(), self.instructions()));
         let result = self.lua.context(|lua| {
             let func: Function = lua.globals().get(function)?;
             func.call(args)
         });
+        if let Some((start, instructions)) = profile_start {
+            benchmark::profile_script(
+                &self.id,
+                function,
+                get_elapsed_millis(start.elapsed()),
+                self.instructions().saturating_sub(instructions) as u64,
+                self.lua.used_memory() as f64 / 1024.0,
+            );
+        }
         self.lua.gc_collect()?;
         if report {
             self.print_report(function);
         }
         self.current_depth.set(cur_depth);
-        result
+        result.map_err(|e| self.check_memory_error(e))
     }
 
     pub fn console(&self, script: String, party: &[Rc<RefCell<EntityState>>]) -> Result<String> {
         assert!(!party.is_empty());
-        self.reset_instruction_state();
+        self.reset_instruction_state(ScriptKind::Console, "console");
         let result = self.lua.context(|lua| {
             lua.globals().set("player", ScriptEntity::from(&party[0]))?;
 
@@ -463,7 +674,7 @@ impl ScriptState {
             lua.load(&script).eval::<String>()
         });
         self.print_report("console");
-        result
+        result.map_err(|e| self.check_memory_error(e))
     }
 }
 
@@ -483,6 +694,101 @@ fn get_targeter() -> Result<Rc<RefCell<AreaTargeter>>> {
     }
 }
 
+/// Replaces the standard lua `math.random` with one drawing from the game random
+/// stream, so that script rolls are reproducible along with the rest of the game.
+fn replace_math_random(lua: rlua::Context) -> Result<()> {
+    let math: rlua::Table = lua.globals().get("math")?;
+
+    let random = lua.create_function(|_, (m, n): (Option<i64>, Option<i64>)| {
+        let value = match (m, n) {
+            (None, _) => rlua::Value::Number(gen_rand(0.0, 1.0)),
+            (Some(max), None) => {
+                if max < 1 {
+                    return Err(rlua::Error::RuntimeError(
+                        "bad argument #1 to 'random' (interval is empty)".to_string(),
+                    ));
+                }
+                rlua::Value::Integer(gen_rand(1, random_end(max)?))
+            }
+            (Some(min), Some(max)) => {
+                if max < min {
+                    return Err(rlua::Error::RuntimeError(
+                        "bad argument #2 to 'random' (interval is empty)".to_string(),
+                    ));
+                }
+                rlua::Value::Integer(gen_rand(min, random_end(max)?))
+            }
+        };
+        Ok(value)
+    })?;
+    math.set("random", random)?;
+
+    // seeding is controlled by the game state
+    let randomseed = lua.create_function(|_, _: rlua::MultiValue| Ok(()))?;
+    math.set("randomseed", randomseed)?;
+
+    Ok(())
+}
+
+/// The exclusive upper bound for `math.random` with the inclusive `max`
+fn random_end(max: i64) -> rlua::Result<i64> {
+    max.checked_add(1).ok_or_else(|| {
+        rlua::Error::RuntimeError("bad argument to 'random' (interval is too large)".to_string())
+    })
+}
+
+/// Adds the `require` function, which loads a script into the calling state
+/// once and returns its cached result
+fn add_require(lua: rlua::Context) -> Result<()> {
+    lua.set_named_registry_value(LOADED_MODULES, lua.create_table()?)?;
+    lua.set_named_registry_value(LOADING_MODULES, lua.create_table()?)?;
+
+    let require = lua.create_function(|lua, id: String| {
+        let script = Module::script(&id)
+            .ok_or_else(|| rlua::Error::RuntimeError(format!("No script found with id '{id}'")))?;
+        exec_module(lua, &id, &script)
+    })?;
+    lua.globals().set("require", require)
+}
+
+/// Runs the script with the specified ID if it has not already been run in this
+/// state, and returns its result
+fn exec_module<'lua>(lua: rlua::Context<'lua>, id: &str, script: &str) -> Result<Value<'lua>> {
+    let loaded: Table = lua.named_registry_value(LOADED_MODULES)?;
+    let value: Value = loaded.get(id)?;
+    if !matches!(value, Value::Nil) {
+        return Ok(value);
+    }
+
+    let loading: Table = lua.named_registry_value(LOADING_MODULES)?;
+    let stack = loading
+        .clone()
+        .sequence_values::<String>()
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(index) = stack.iter().position(|loading_id| loading_id == id) {
+        return Err(rlua::Error::RuntimeError(format!(
+            "Cyclic require of '{}': {} -> {}",
+            id,
+            stack[index..].join(" -> "),
+            id
+        )));
+    }
+
+    loading.set(stack.len() + 1, id)?;
+    let result = lua
+        .load(script)
+        .set_name(id)
+        .and_then(|chunk| chunk.call::<_, Value>(()));
+    loading.set(stack.len() + 1, Value::Nil)?;
+
+    let value = match result? {
+        Value::Nil => Value::Boolean(true),
+        value => value,
+    };
+    loaded.set(id, value.clone())?;
+    Ok(value)
+}
+
 fn get_rlua_std_lib() -> rlua::StdLib {
     use rlua::StdLib;
 
@@ -1082,6 +1082,149 @@ impl AreaTargeter {
         &self.selectable
     }
 
+    /// Returns the cursor positions worth checking when searching for the best
+    /// placement of this targeter.  These are the selectable entities, or for
+    /// free select, the points within range that place the shape over each
+    /// effectable entity.  Lines and cones are aimed from their origin through
+    /// each entity, with cones also tried with the entity near either edge.
+    pub fn candidate_positions(&self) -> Vec<Point> {
+        let mut points = Vec::new();
+
+        let range = match self.free_select {
+            None => {
+                for target in self.selectable.iter() {
+                    let target = target.borrow();
+                    points.push(Point::new(target.location.x, target.location.y));
+                }
+                return points;
+            }
+            Some(range) => range,
+        };
+
+        match self.shape {
+            Shape::Line {
+                origin_x,
+                origin_y,
+                length,
+                ..
+            } => {
+                let origin = (origin_x as f32, origin_y as f32);
+                self.add_ray_candidates(&mut points, origin, length as f32, &[0.0], range);
+            }
+            Shape::LineSegment {
+                origin_x,
+                origin_y,
+                ..
+            } => {
+                // the segment ends at the cursor, so it may stop at an entity or
+                // continue through it
+                let origin = (origin_x as f32, origin_y as f32);
+                self.add_ray_candidates(&mut points, origin, range, &[0.0], range);
+            }
+            Shape::Cone {
+                origin_x,
+                origin_y,
+                radius,
+                angle,
+                ..
+            } => {
+                // keep the entity just inside either edge, as rounding the cursor
+                // to a square shifts the aim slightly
+                let edge = angle * 0.4;
+                let offsets = [0.0, -edge, edge];
+                self.add_ray_candidates(&mut points, (origin_x, origin_y), radius, &offsets, range);
+            }
+            Shape::Single | Shape::Circle { .. } | Shape::ObjectSize { .. } => {
+                self.add_area_candidates(&mut points, range);
+            }
+        }
+
+        points.sort_by_key(|p| (p.y, p.x));
+        points.dedup();
+        points
+    }
+
+    /// Adds the points within `range` from which the shape, centered on the
+    /// cursor, reaches each effectable entity
+    fn add_area_candidates(&self, points: &mut Vec<Point>, range: f32) {
+        let reach = match self.shape {
+            Shape::Circle { radius, .. } => radius,
+            Shape::ObjectSize { ref size } => match Module::object_size(size) {
+                None => 0.0,
+                Some(size) => size.width.max(size.height) as f32 / 2.0,
+            },
+            _ => 0.0,
+        };
+        let extent = reach.ceil() as i32;
+
+        let parent = self.parent.borrow();
+        for target in self.effectable.iter() {
+            let target = target.borrow();
+            let loc = &target.location;
+            for y in (loc.y - extent)..(loc.y + target.size.height + extent) {
+                for x in (loc.x - extent)..(loc.x + target.size.width + extent) {
+                    let p = Point::new(x, y);
+                    if dist(&p, &*target) > reach || !is_within(&*parent, &p, range) {
+                        continue;
+                    }
+                    points.push(p);
+                }
+            }
+        }
+    }
+
+    /// Adds cursor points within `range` that aim the shape from `origin`
+    /// through each square of each effectable entity, rotated by each of the
+    /// `angles`.  Points are placed both on the entity and at `reach`, as far
+    /// as the shape extends.
+    fn add_ray_candidates(
+        &self,
+        points: &mut Vec<Point>,
+        origin: (f32, f32),
+        reach: f32,
+        angles: &[f32],
+        range: f32,
+    ) {
+        let offset = self.shape.get_cursor_offset();
+        let parent = self.parent.borrow();
+        for target in self.effectable.iter() {
+            let target = target.borrow();
+            let loc = &target.location;
+            for y in loc.y..(loc.y + target.size.height) {
+                for x in loc.x..(loc.x + target.size.width) {
+                    let (dx, dy) = (x as f32 - origin.0, y as f32 - origin.1);
+                    let target_dist = (dx * dx + dy * dy).sqrt();
+                    if target_dist > reach {
+                        continue;
+                    }
+
+                    let direction = dy.atan2(dx);
+                    for angle in angles.iter() {
+                        let (sin, cos) = (direction + angle).sin_cos();
+                        for dist in [target_dist, reach.min(range)] {
+                            let p = Point::new(
+                                (origin.0 + cos * dist).round() as i32,
+                                (origin.1 + sin * dist).round() as i32,
+                            );
+                            if !is_within(&*parent, &p, range) {
+                                continue;
+                            }
+                            points.push(p + offset);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns whether this targeter was created by the ability with the specified ID
+    pub fn is_for_ability(&self, id: &str) -> bool {
+        match &self.script_source {
+            ScriptSource::Ability(ability) => ability.id == id,
+            ScriptSource::Item { .. } => false,
+        }
+    }
+
     pub fn cur_affected(&self) -> &Vec<Rc<RefCell<EntityState>>> {
         &self.cur_effected
     }
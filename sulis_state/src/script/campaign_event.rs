@@ -0,0 +1,111 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Campaign wide events.  A campaign subscribes to events in its
+//! `campaign.yml`, mapping each event kind to a script function:
+//!
+//! ```yaml
+//! event_scripts:
+//!   EntityDied:
+//!     id: campaign
+//!     func: on_entity_died
+//! ```
+//!
+//! Events are queued as they happen and the script functions are called on the
+//! next update, each with a table of the event data.  The table always holds
+//! the event `kind`, along with the fields listed in `CampaignEventKind`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rlua::{Context, ToLua, Value};
+
+use crate::script::ScriptEntity;
+use crate::{EntityState, Script};
+use sulis_module::{CampaignEventKind, Module};
+
+enum EventValue {
+    Str(String),
+    Int(i32),
+    Entity(usize),
+}
+
+/// A single occurrence of a `CampaignEventKind`, with its data
+pub struct CampaignEvent {
+    kind: CampaignEventKind,
+    data: Vec<(&'static str, EventValue)>,
+}
+
+impl CampaignEvent {
+    pub fn new(kind: CampaignEventKind) -> CampaignEvent {
+        CampaignEvent {
+            kind,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> CampaignEventKind {
+        self.kind
+    }
+
+    pub fn with_str(mut self, key: &'static str, value: &str) -> CampaignEvent {
+        self.data.push((key, EventValue::Str(value.to_string())));
+        self
+    }
+
+    pub fn with_int(mut self, key: &'static str, value: i32) -> CampaignEvent {
+        self.data.push((key, EventValue::Int(value)));
+        self
+    }
+
+    pub fn with_entity(
+        mut self,
+        key: &'static str,
+        entity: &Rc<RefCell<EntityState>>,
+    ) -> CampaignEvent {
+        self.data
+            .push((key, EventValue::Entity(entity.borrow().index())));
+        self
+    }
+}
+
+impl<'lua> ToLua<'lua> for CampaignEvent {
+    fn to_lua(self, lua: Context<'lua>) -> rlua::Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("kind", self.kind.to_str())?;
+        for (key, value) in self.data {
+            match value {
+                EventValue::Str(value) => table.set(key, value)?,
+                EventValue::Int(value) => table.set(key, value)?,
+                EventValue::Entity(index) => table.set(key, ScriptEntity::new(index))?,
+            }
+        }
+        Ok(Value::Table(table))
+    }
+}
+
+/// Calls the campaign script subscribed to each of the specified events, if any
+pub fn fire(events: Vec<CampaignEvent>) {
+    let campaign = Module::campaign();
+    for event in events {
+        let script_data = match campaign.event_scripts.get(&event.kind) {
+            None => continue,
+            Some(data) => data,
+        };
+
+        Script::trigger(&script_data.id, &script_data.func, event);
+    }
+}
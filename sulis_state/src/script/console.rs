@@ -0,0 +1,301 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Support for the developer console: capturing the output of the script
+//! logging functions, completing method names and IDs, and expanding the
+//! built-in console commands into Lua.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::marker::PhantomData;
+
+use log::Level;
+use rlua::{Context, FromLuaMulti, MetaMethod, ToLuaMulti, UserData, UserDataMethods};
+
+use crate::script::{Result, ScriptEntity, ScriptInterface};
+use crate::GameState;
+use sulis_module::Module;
+
+/// The maximum number of log lines kept until the console takes them
+const MAX_LOG_LINES: usize = 200;
+
+/// Global names completed at the start of a line, in addition to commands
+const GLOBALS: [&str; 3] = ["game", "player", "party"];
+
+/// The built-in commands, with their usage
+pub const COMMANDS: [(&str, &str); 9] = [
+    ("spawn", "spawn <actor> <x> <y> [faction]"),
+    ("teleport", "teleport <x> <y> [area]"),
+    ("give", "give <item> [count]"),
+    ("setflag", "setflag <flag> [value]"),
+    ("quest", "quest <quest> [entry] <state>"),
+    ("kill", "kill <entity>"),
+    ("script", "script [id]"),
+    ("profile", "profile [clear|save]"),
+    ("help", "help"),
+];
+
+thread_local! {
+    static LOG: RefCell<VecDeque<(Level, String)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Records a message logged by a script, for display in the console
+pub(crate) fn log(level: Level, text: &str) {
+    LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        if log.len() == MAX_LOG_LINES {
+            log.pop_front();
+        }
+        log.push_back((level, text.to_string()));
+    });
+}
+
+/// Returns and clears all messages logged by scripts since the last call
+pub fn take_log() -> Vec<(Level, String)> {
+    LOG.with(|log| log.borrow_mut().drain(..).collect())
+}
+
+/// Returns whether `word` is the name of a built-in command
+pub fn is_command(word: &str) -> bool {
+    COMMANDS.iter().any(|(name, _)| *name == word)
+}
+
+/// Expands a built-in command, other than `script`, `profile`, and `help`, into the
+/// equivalent Lua.  Returns `None` if the line is not a command, or an error
+/// with the command's usage if it has the wrong arguments.
+pub fn expand_command(line: &str) -> Option<std::result::Result<String, String>> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = words.split_first()?;
+    let usage = COMMANDS.iter().find(|(n, _)| n == name)?.1;
+
+    Some(expand(name, args).map_err(|e| {
+        if e.is_empty() {
+            format!("Usage: {usage}")
+        } else {
+            e
+        }
+    }))
+}
+
+/// Expands the command, returning an empty error if the arguments are wrong
+fn expand(name: &str, args: &[&str]) -> std::result::Result<String, String> {
+    let lua = match (name, args) {
+        ("spawn", [actor, x, y]) => {
+            format!("game:spawn_actor_at({actor:?}, {}, {})", int(x)?, int(y)?)
+        }
+        ("spawn", [actor, x, y, faction]) => format!(
+            "game:spawn_actor_at({actor:?}, {}, {}, {faction:?})",
+            int(x)?,
+            int(y)?
+        ),
+        ("teleport", [x, y]) => format!("game:transition_party_to({}, {})", int(x)?, int(y)?),
+        ("teleport", [x, y, area]) => format!(
+            "game:transition_party_to({}, {}, {area:?})",
+            int(x)?,
+            int(y)?
+        ),
+        ("give", [item]) => format!("game:add_party_item({item:?})"),
+        ("give", [item, count]) => format!(
+            "for i = 1, {} do game:add_party_item({item:?}) end",
+            int(count)?
+        ),
+        ("setflag", [flag]) => format!("game:player():set_flag({flag:?})"),
+        ("setflag", [flag, value]) => format!("game:player():set_flag({flag:?}, {value:?})"),
+        ("quest", [quest, state]) => format!("game:set_quest_state({quest:?}, {state:?})"),
+        ("quest", [quest, entry, state]) => {
+            format!("game:set_quest_entry_state({quest:?}, {entry:?}, {state:?})")
+        }
+        ("kill", [entity]) => format!(
+            "local target = game:entity_with_id({entity:?}); \
+             local hp = target:stats().max_hp; \
+             target:take_damage(target, hp, hp, \"Raw\")"
+        ),
+        _ => return Err(String::new()),
+    };
+
+    Ok(lua)
+}
+
+fn int(value: &str) -> std::result::Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected a number but found '{value}'"))
+}
+
+/// Returns the byte index in `line` of the word being completed, along with
+/// the sorted possible completions of that word
+pub fn complete(line: &str) -> (usize, Vec<String>) {
+    let start = line
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '-')
+        .last()
+        .map_or(line.len(), |(index, _)| index);
+    let (before, prefix) = line.split_at(start);
+
+    let candidates: Vec<String> = if before.ends_with(':') || before.ends_with('.') {
+        let receiver = before[..before.len() - 1]
+            .rsplit(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or_default();
+        if receiver == "game" {
+            method_names::<ScriptInterface>()
+        } else {
+            method_names::<ScriptEntity>()
+        }
+    } else if before.ends_with('"') || before.ends_with('\'') {
+        entity_ids()
+    } else {
+        let words: Vec<&str> = before.split_whitespace().collect();
+        match words.as_slice() {
+            [] => COMMANDS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .chain(GLOBALS.iter().map(|name| name.to_string()))
+                .collect(),
+            [command] => argument_ids(command),
+            ["teleport", _, _] => Module::all_areas().iter().map(|a| a.id.clone()).collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    let completions: BTreeSet<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+    (start, completions.into_iter().collect())
+}
+
+fn argument_ids(command: &str) -> Vec<String> {
+    match command {
+        "spawn" => Module::all_actors().iter().map(|a| a.id.clone()).collect(),
+        "give" => Module::all_items()
+            .iter()
+            .map(|i| i.id.clone())
+            .filter(|id| !id.contains("__ADJ__"))
+            .collect(),
+        "quest" => Module::all_quests().iter().map(|q| q.id.clone()).collect(),
+        "kill" => entity_ids(),
+        "script" => Module::all_scripts(),
+        "profile" => vec!["clear".to_string(), "save".to_string()],
+        _ => Vec::new(),
+    }
+}
+
+fn entity_ids() -> Vec<String> {
+    let mgr = GameState::turn_manager();
+    let mgr = mgr.borrow();
+    mgr.entity_iter()
+        .map(|entity| entity.borrow().unique_id().to_string())
+        .collect()
+}
+
+/// Returns the names of all methods callable on `T` from Lua
+fn method_names<T: UserData>() -> Vec<String> {
+    let mut names = MethodNames::<T> {
+        names: Vec::new(),
+        _phantom: PhantomData,
+    };
+    T::add_methods(&mut names);
+    names.names
+}
+
+/// Records the names of the methods added to a user data type, without
+/// creating them
+struct MethodNames<T> {
+    names: Vec<String>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> MethodNames<T> {
+    fn add(&mut self, name: &[u8]) {
+        self.names.push(String::from_utf8_lossy(name).to_string());
+    }
+}
+
+impl<'lua, T: UserData> UserDataMethods<'lua, T> for MethodNames<T> {
+    fn add_method<S, A, R, M>(&mut self, name: &S, _: M)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + Fn(Context<'lua>, &T, A) -> Result<R>,
+    {
+        self.add(name.as_ref());
+    }
+
+    fn add_method_mut<S, A, R, M>(&mut self, name: &S, _: M)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> Result<R>,
+    {
+        self.add(name.as_ref());
+    }
+
+    fn add_function<S, A, R, F>(&mut self, name: &S, _: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> Result<R>,
+    {
+        self.add(name.as_ref());
+    }
+
+    fn add_function_mut<S, A, R, F>(&mut self, name: &S, _: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> Result<R>,
+    {
+        self.add(name.as_ref());
+    }
+
+    fn add_meta_method<A, R, M>(&mut self, _: MetaMethod, _: M)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + Fn(Context<'lua>, &T, A) -> Result<R>,
+    {
+    }
+
+    fn add_meta_method_mut<A, R, M>(&mut self, _: MetaMethod, _: M)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> Result<R>,
+    {
+    }
+
+    fn add_meta_function<A, R, F>(&mut self, _: MetaMethod, _: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> Result<R>,
+    {
+    }
+
+    fn add_meta_function_mut<A, R, F>(&mut self, _: MetaMethod, _: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> Result<R>,
+    {
+    }
+}
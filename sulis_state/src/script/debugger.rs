@@ -0,0 +1,591 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! A debugger for Lua scripts, enabled by setting `lua_debugger_port` in the
+//! debug config.  While enabled, every script line is passed through a hook
+//! which stops at breakpoints and when stepping.  Scripts run on the main
+//! thread, so the whole game is paused while a script is stopped, and the
+//! debugger is driven from an editor or other client over a local socket.
+//!
+//! The protocol is modelled on the Debug Adapter Protocol, with each message
+//! sent as a single line of JSON.  Requests have the form
+//! `{"seq": 1, "command": "stepIn", "arguments": {...}}` and are answered with
+//! `{"type": "response", "request_seq": 1, "command": "stepIn", "success": true,
+//! "body": {...}}`, with a `message` in place of the `body` on failure.  When a
+//! script stops, the event
+//! `{"type": "event", "event": "stopped", "body": {"reason": "breakpoint",
+//! "script": "<id>", "line": 12}}` is sent.
+//!
+//! The supported commands are:
+//!
+//! - `setBreakpoints` with arguments `{"script": "<id>", "lines": [12, 20]}`,
+//!   replacing all breakpoints in that script
+//! - `pause`, stopping at the next script line that runs
+//! - `continue`, `next`, `stepIn`, and `stepOut`, when stopped
+//! - `stackTrace`, when stopped, giving the `frames` of the Lua stack from the
+//!   innermost outwards
+//! - `variables` with arguments `{"frame": 0}`, when stopped, giving the
+//!   `locals` and `upvalues` of a stack frame
+//!
+//! Line numbers refer to the lines of the script with the given ID, including
+//! scripts loaded with `require` or `--INCLUDE`.  While the debugger is enabled, the instruction limit counts
+//! script lines rather than instructions, and time spent stopped does not
+//! count towards the time limit.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use rlua::{self, Context, Function, Lua, StdLib, Table, Value};
+use sulis_core::config::Config;
+use sulis_core::serde_json::{self, json, Value as Json};
+
+use crate::script::Result;
+
+/// The registry key of the Lua debug library, which is removed from the
+/// globals so scripts cannot use it
+const DEBUG_LIB: &str = "sulis_debugger_lib";
+
+/// The maximum number of table entries shown when describing a variable
+const MAX_TABLE_ENTRIES: usize = 10;
+
+/// The maximum depth of nested tables shown when describing a variable
+const MAX_TABLE_DEPTH: usize = 2;
+
+thread_local! {
+    static DEBUGGER: RefCell<Option<Debugger>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Run,
+    Pause,
+    Into,
+    Over(usize),
+    Out(usize),
+}
+
+struct Debugger {
+    port: u16,
+    listener: Option<TcpListener>,
+    client: Option<Client>,
+    breakpoints: HashMap<String, HashSet<i64>>,
+    step: Step,
+}
+
+impl Debugger {
+    fn bind(port: u16) -> Debugger {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener));
+
+        let listener = match listener {
+            Ok(listener) => {
+                info!("Lua debugger listening on port {}", port);
+                Some(listener)
+            }
+            Err(e) => {
+                warn!("Unable to start Lua debugger on port {}: {}", port, e);
+                None
+            }
+        };
+
+        Debugger {
+            port,
+            listener,
+            client: None,
+            breakpoints: HashMap::new(),
+            step: Step::Run,
+        }
+    }
+
+    fn accept(&mut self) {
+        let listener = match self.listener.as_ref() {
+            None => return,
+            Some(listener) => listener,
+        };
+
+        let stream = match listener.accept() {
+            Ok((stream, addr)) => {
+                info!("Lua debugger client connected from {}", addr);
+                stream
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("Error accepting Lua debugger client: {}", e);
+                return;
+            }
+        };
+
+        // a new client starts from a clean state
+        self.disconnect();
+        self.client = Some(Client {
+            stream,
+            buffer: Vec::new(),
+        });
+    }
+
+    fn disconnect(&mut self) {
+        if self.client.take().is_some() {
+            info!("Lua debugger client disconnected");
+        }
+        self.breakpoints.clear();
+        self.step = Step::Run;
+    }
+
+    /// Handles all requests received while scripts are running
+    fn poll(&mut self) {
+        loop {
+            let line = match self.client.as_mut().map(|c| c.next_line(false)) {
+                None => return,
+                Some(Ok(Some(line))) => line,
+                Some(Ok(None)) => return,
+                Some(Err(e)) => {
+                    warn!("Lua debugger connection error: {}", e);
+                    self.disconnect();
+                    return;
+                }
+            };
+
+            self.handle(&line, None);
+        }
+    }
+
+    /// Handles a single request, returning whether it resumed the stopped script
+    fn handle(&mut self, line: &str, stopped: Option<&Stopped>) -> bool {
+        let request: Json = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Invalid Lua debugger request '{}': {}", line, e);
+                return false;
+            }
+        };
+
+        let command = request["command"].as_str().unwrap_or_default().to_string();
+        let args = &request["arguments"];
+
+        let mut resumed = false;
+        let result = match (command.as_str(), stopped) {
+            ("setBreakpoints", _) => self.set_breakpoints(args),
+            ("pause", None) => {
+                self.step = Step::Pause;
+                Ok(Json::Null)
+            }
+            ("pause", Some(_)) => Ok(Json::Null),
+            ("continue" | "next" | "stepIn" | "stepOut", Some(stopped)) => {
+                self.step = match command.as_str() {
+                    "continue" => Step::Run,
+                    "next" => Step::Over(stopped.frames.len()),
+                    "stepIn" => Step::Into,
+                    _ => Step::Out(stopped.frames.len()),
+                };
+                resumed = true;
+                Ok(Json::Null)
+            }
+            ("stackTrace", Some(stopped)) => Ok(stopped.stack_trace()),
+            ("variables", Some(stopped)) => stopped.variables(args),
+            ("continue" | "next" | "stepIn" | "stepOut" | "stackTrace" | "variables", None) => {
+                Err("No script is stopped".to_string())
+            }
+            _ => Err(format!("Unknown command '{command}'")),
+        };
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = Json::String(message),
+        }
+        self.send(&response);
+        resumed
+    }
+
+    fn set_breakpoints(&mut self, args: &Json) -> std::result::Result<Json, String> {
+        let script = match args["script"].as_str() {
+            None => return Err("No script specified".to_string()),
+            Some(script) => script.to_string(),
+        };
+
+        let lines: HashSet<i64> = args["lines"]
+            .as_array()
+            .map(|lines| lines.iter().filter_map(|line| line.as_i64()).collect())
+            .unwrap_or_default();
+
+        let mut sorted: Vec<i64> = lines.iter().copied().collect();
+        sorted.sort_unstable();
+        if lines.is_empty() {
+            self.breakpoints.remove(&script);
+        } else {
+            self.breakpoints.insert(script.clone(), lines);
+        }
+
+        Ok(json!({ "script": script, "lines": sorted }))
+    }
+
+    fn send(&mut self, message: &Json) {
+        let client = match self.client.as_mut() {
+            None => return,
+            Some(client) => client,
+        };
+
+        if let Err(e) = client.send(message) {
+            warn!("Lua debugger connection error: {}", e);
+            self.disconnect();
+        }
+    }
+
+    fn is_breakpoint(&self, script: &str, line: i64) -> bool {
+        self.breakpoints
+            .get(script)
+            .is_some_and(|lines| lines.contains(&line))
+    }
+}
+
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl Client {
+    /// Returns the next complete line received from the client, waiting for
+    /// one if `blocking` is set.  Returns an error if the client has
+    /// disconnected.
+    fn next_line(&mut self, blocking: bool) -> std::io::Result<Option<String>> {
+        self.stream.set_nonblocking(!blocking)?;
+        loop {
+            if let Some(index) = self.buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=index).collect();
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if line.is_empty() {
+                    continue;
+                }
+                return Ok(Some(line));
+            }
+
+            let mut data = [0; 4096];
+            match self.stream.read(&mut data) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(len) => self.buffer.extend_from_slice(&data[..len]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn send(&mut self, message: &Json) -> std::io::Result<()> {
+        self.stream.set_nonblocking(false)?;
+        let mut data = serde_json::to_vec(message)?;
+        data.push(b'\n');
+        self.stream.write_all(&data)
+    }
+}
+
+struct Frame {
+    level: i64,
+    name: String,
+    script: String,
+    line: i64,
+}
+
+/// The state of a script stopped in the debugger
+struct Stopped<'lua> {
+    lua: Context<'lua>,
+    debug: Table<'lua>,
+    frames: Vec<Frame>,
+}
+
+impl<'lua> Stopped<'lua> {
+    fn stack_trace(&self) -> Json {
+        let frames: Vec<Json> = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.name,
+                    "script": frame.script,
+                    "line": frame.line,
+                })
+            })
+            .collect();
+        json!({ "frames": frames })
+    }
+
+    fn variables(&self, args: &Json) -> std::result::Result<Json, String> {
+        let index = args["frame"].as_u64().unwrap_or(0) as usize;
+        let frame = match self.frames.get(index) {
+            None => return Err(format!("No stack frame {index}")),
+            Some(frame) => frame,
+        };
+
+        self.frame_variables(frame).map_err(|e| e.to_string())
+    }
+
+    fn frame_variables(&self, frame: &Frame) -> Result<Json> {
+        let getlocal: Function = self.debug.get("getlocal")?;
+        let getupvalue: Function = self.debug.get("getupvalue")?;
+        let getinfo: Function = self.debug.get("getinfo")?;
+
+        let mut locals = Vec::new();
+        for index in 1.. {
+            let (name, value): (Option<String>, Value) = getlocal.call((frame.level, index))?;
+            let name = match name {
+                None => break,
+                Some(name) => name,
+            };
+
+            // skip internal values such as loop state
+            if name.starts_with('(') {
+                continue;
+            }
+            locals.push(json!({ "name": name, "value": describe(self.lua, value, 0) }));
+        }
+
+        let mut upvalues = Vec::new();
+        let info: Option<Table> = getinfo.call((frame.level, "f"))?;
+        if let Some(func) = info.and_then(|info| info.get::<_, Function>("func").ok()) {
+            for index in 1.. {
+                let (name, value): (Option<String>, Value) =
+                    getupvalue.call((func.clone(), index))?;
+                let name = match name {
+                    None => break,
+                    Some(name) => name,
+                };
+
+                // the globals table, which is the same for every function
+                if name == "_ENV" {
+                    continue;
+                }
+                upvalues.push(json!({ "name": name, "value": describe(self.lua, value, 0) }));
+            }
+        }
+
+        Ok(json!({ "locals": locals, "upvalues": upvalues }))
+    }
+}
+
+/// Returns whether scripts should be run under the debugger
+pub fn enabled() -> bool {
+    Config::debug().lua_debugger_port.is_some()
+}
+
+/// Creates a new Lua state with the specified libraries, along with the debug
+/// library for use by the debugger only
+pub(crate) fn new_lua(libs: StdLib) -> Lua {
+    // the debug library is only reachable from the registry, so scripts
+    // cannot use it to get around the sandbox
+    let lua = unsafe { Lua::unsafe_new_with(libs | StdLib::DEBUG) };
+    let result = lua.context(|lua| {
+        let globals = lua.globals();
+        let debug: Table = globals.get("debug")?;
+        lua.set_named_registry_value(DEBUG_LIB, debug)?;
+        globals.set("debug", Value::Nil)
+    });
+
+    if let Err(e) = result {
+        warn!("Error setting up the Lua debugger");
+        warn!("{}", e);
+    }
+    lua
+}
+
+/// Listens for and handles requests from debugger clients.  Called once per
+/// frame.
+pub fn update() {
+    let port = match Config::debug().lua_debugger_port {
+        None => return,
+        Some(port) => port,
+    };
+
+    DEBUGGER.with(|debugger| {
+        let mut debugger = debugger.borrow_mut();
+        if debugger.as_ref().map(|d| d.port) != Some(port) {
+            *debugger = Some(Debugger::bind(port));
+        }
+
+        if let Some(debugger) = debugger.as_mut() {
+            debugger.accept();
+            debugger.poll();
+        }
+    });
+}
+
+/// Sets a hook on `lua`, created with `new_lua`, which is called on each new
+/// script line and stops in the debugger when needed.  `on_line` is then called
+/// with the time spent stopped.
+pub(crate) fn set_line_hook<F>(lua: &Lua, on_line: F) -> Result<()>
+where
+    F: 'static + Send + Fn(Duration) -> Result<()>,
+{
+    // the hook is set through the debug library rather than `Lua::set_hook`, so
+    // that the hooked function's stack is left intact for reading locals
+    lua.context(|lua| {
+        let debug: Table = lua.named_registry_value(DEBUG_LIB)?;
+        let sethook: Function = debug.get("sethook")?;
+        let hook = lua.create_function(move |lua, (_, line): (String, i64)| {
+            let paused = check_stop(lua, line)?;
+            on_line(paused)
+        })?;
+        sethook.call((hook, "l"))
+    })
+}
+
+/// If the script should stop at `line`, waits for the client to resume it.
+/// Returns the time spent stopped.
+fn check_stop(lua: Context, line: i64) -> Result<Duration> {
+    DEBUGGER.with(|debugger| {
+        let mut debugger = debugger.borrow_mut();
+        let debugger = match debugger.as_mut() {
+            Some(debugger) if debugger.client.is_some() => debugger,
+            _ => return Ok(Duration::ZERO),
+        };
+
+        if debugger.step == Step::Run && debugger.breakpoints.is_empty() {
+            return Ok(Duration::ZERO);
+        }
+
+        let debug: Table = lua.named_registry_value(DEBUG_LIB)?;
+        let frames = get_frames(&debug)?;
+        let script = match frames.first() {
+            None => return Ok(Duration::ZERO),
+            Some(frame) => frame.script.clone(),
+        };
+
+        let reason = if debugger.is_breakpoint(&script, line) {
+            "breakpoint"
+        } else {
+            let stop = match debugger.step {
+                Step::Run => false,
+                Step::Pause | Step::Into => true,
+                Step::Over(depth) => frames.len() <= depth,
+                Step::Out(depth) => frames.len() < depth,
+            };
+            if !stop {
+                return Ok(Duration::ZERO);
+            }
+
+            if debugger.step == Step::Pause {
+                "pause"
+            } else {
+                "step"
+            }
+        };
+
+        let start = Instant::now();
+        let stopped = Stopped { lua, debug, frames };
+        debugger.send(&json!({
+            "type": "event",
+            "event": "stopped",
+            "body": { "reason": reason, "script": script, "line": line },
+        }));
+
+        loop {
+            let line = match debugger.client.as_mut().map(|c| c.next_line(true)) {
+                None => break,
+                Some(Ok(Some(line))) => line,
+                Some(Ok(None)) => continue,
+                Some(Err(e)) => {
+                    warn!("Lua debugger connection error: {}", e);
+                    debugger.disconnect();
+                    break;
+                }
+            };
+
+            if debugger.handle(&line, Some(&stopped)) {
+                break;
+            }
+        }
+
+        Ok(start.elapsed())
+    })
+}
+
+/// Returns the Lua stack frames from the hooked function outwards.  The levels
+/// are relative to the hook function.
+fn get_frames(debug: &Table) -> Result<Vec<Frame>> {
+    let getinfo: Function = debug.get("getinfo")?;
+
+    let mut frames = Vec::new();
+    for level in 1.. {
+        let info: Option<Table> = getinfo.call((level, "Sln"))?;
+        let info = match info {
+            None => break,
+            Some(info) => info,
+        };
+
+        // skip the hook function and any Rust functions
+        let what: String = info.get("what")?;
+        if what == "C" {
+            continue;
+        }
+
+        let name = info
+            .get::<_, Option<String>>("name")?
+            .unwrap_or_else(|| if what == "main" { "main chunk" } else { "?" }.to_string());
+        frames.push(Frame {
+            level,
+            name,
+            script: info.get::<_, Option<String>>("source")?.unwrap_or_default(),
+            line: info.get("currentline")?,
+        });
+    }
+    Ok(frames)
+}
+
+/// Returns a short readable description of a Lua value
+fn describe<'lua>(lua: Context<'lua>, value: Value<'lua>, depth: usize) -> String {
+    match value {
+        Value::Nil => "nil".to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("{:?}", String::from_utf8_lossy(s.as_bytes())),
+        Value::Table(table) if depth < MAX_TABLE_DEPTH => {
+            let mut entries = Vec::new();
+            for pair in table.pairs::<Value, Value>() {
+                let (key, value) = match pair {
+                    Err(_) => break,
+                    Ok(pair) => pair,
+                };
+
+                if entries.len() == MAX_TABLE_ENTRIES {
+                    entries.push("...".to_string());
+                    break;
+                }
+
+                let key = match key {
+                    Value::String(s) => String::from_utf8_lossy(s.as_bytes()).to_string(),
+                    key => format!("[{}]", describe(lua, key, depth + 1)),
+                };
+                entries.push(format!("{} = {}", key, describe(lua, value, depth + 1)));
+            }
+            format!("{{{}}}", entries.join(", "))
+        }
+        value => {
+            let tostring: rlua::Result<Function> = lua.globals().get("tostring");
+            tostring
+                .and_then(|f| f.call::<_, String>(value))
+                .unwrap_or_else(|_| "?".to_string())
+        }
+    }
+}
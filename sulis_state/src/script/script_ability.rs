@@ -21,9 +21,10 @@ use std::rc::Rc;
 use rlua::{self, Context, UserData, UserDataMethods};
 
 use crate::script::{CallbackData, ScriptEntity};
+use crate::tactical_planner::{self, AoeOptions, AoeWeights};
 use crate::{area_feedback_text::ColorKind, AreaFeedbackText, EntityState, GameState};
 use sulis_module::{
-    ability::{self, AIData, Range},
+    ability::{self, AIData, AIKind, Range},
     Ability, Module,
 };
 
@@ -292,6 +293,25 @@ impl UserData for ScriptAbilitySet {
 /// the `priority`, an integer, the `kind`, `group, `range`, and `target`, all Strings.  See
 /// `ScriptAbilitySet::only_group`, `ScriptAbilitySet::only_range`,
 /// `ScriptAbilitySet::only_kind`.
+///
+/// # `best_aoe_target(opts: Table (Optional)) -> Table`
+/// Searches for the placement of the currently active targeter, which must have
+/// been created by this ability, affecting the best set of entities.  Each
+/// affected hostile and friendly of the targeter's parent adds the `hostile` or
+/// `friendly` weight from `opts` to the score.  These default to `1.0` and
+/// `-2.0`, or `-1.0` and `1.0` for `Heal` and `Buff` abilities.  Returns a table
+/// of `x`, `y`, `score`, `hostiles`, and `friendlies` for the best placement,
+/// with the targeter moved there, or nil if no placement scored more than
+/// `opts.min_score`, which defaults to zero.
+/// ## Examples
+/// ```lua
+///   parent:use_ability(ability)
+///   if ability:best_aoe_target() then
+///     game:activate_targeter()
+///   else
+///     game:cancel_targeter()
+///   end
+/// ```
 #[derive(Clone)]
 pub struct ScriptAbility {
     pub id: String,
@@ -432,7 +452,52 @@ impl UserData for ScriptAbility {
 
             Ok(ai_data)
         });
+
+        methods.add_method("best_aoe_target", best_aoe_target);
+    }
+}
+
+fn best_aoe_target<'a>(
+    lua: Context<'a>,
+    ability: &ScriptAbility,
+    opts: Option<rlua::Table<'a>>,
+) -> Result<Option<rlua::Table<'a>>> {
+    let targeter = GameState::area_state().borrow().targeter();
+    let targeter = match targeter {
+        Some(targeter) if targeter.borrow().is_for_ability(&ability.id) => targeter,
+        _ => {
+            return Err(rlua::Error::FromLuaConversionError {
+                from: "ScriptAbility",
+                to: "AreaTargeter",
+                message: Some(format!("No active targeter for the ability '{}'", ability.id)),
+            });
+        }
+    };
+
+    let mut weights = match ability.ai_data.kind {
+        AIKind::Heal | AIKind::Buff => AoeWeights::beneficial(),
+        _ => AoeWeights::harmful(),
+    };
+    let mut min_score = 0.0;
+    if let Some(opts) = opts {
+        weights.hostile = opts.get::<_, Option<f32>>("hostile")?.unwrap_or(weights.hostile);
+        weights.friendly = opts.get::<_, Option<f32>>("friendly")?.unwrap_or(weights.friendly);
+        min_score = opts.get::<_, Option<f32>>("min_score")?.unwrap_or(min_score);
     }
+
+    let options = AoeOptions { weights, min_score };
+    let best = match tactical_planner::best_aoe_target(&mut targeter.borrow_mut(), &options) {
+        None => return Ok(None),
+        Some(best) => best,
+    };
+
+    let table = lua.create_table()?;
+    table.set("x", best.x)?;
+    table.set("y", best.y)?;
+    table.set("score", best.score)?;
+    table.set("hostiles", best.hostiles)?;
+    table.set("friendlies", best.friendlies)?;
+    Ok(Some(table))
 }
 
 fn deactivate(_lua: Context, ability: &ScriptAbility, target: ScriptEntity) -> Result<()> {
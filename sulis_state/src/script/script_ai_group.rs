@@ -0,0 +1,275 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rlua::{UserData, UserDataMethods};
+
+use crate::script::ScriptEntity;
+use crate::{AIBlackboard, EntityState, GameState};
+use sulis_core::util::Point;
+use sulis_module::AIRole;
+
+/// The blackboard shared by the members of an AI group, normally the creatures
+/// spawned by a single encounter.  Obtained with `ScriptEntity::ai_group`.
+/// Threat is added automatically whenever a group member is damaged by a
+/// hostile, and kept until the attacker is removed.  The focus and claimed
+/// squares are cleared when combat ends, and each member's claim is also
+/// released at the start of its turn.  Everything is saved with the game.
+///
+/// # `id() -> Int`
+/// Returns the unique ID of this group.
+///
+/// # `members(role: String (Optional)) -> Table`
+/// Returns a table of all living members of this group, as `ScriptEntity`
+/// objects.  If `role` is specified, only members whose AI template has that
+/// role are included.  Valid roles are `Tank`, `Melee`, `Ranged`, `Caster`,
+/// and `Healer`.
+///
+/// # `threat(target: ScriptEntity) -> Float`
+/// Returns the total threat the `target` has generated against this group.
+///
+/// # `add_threat(target: ScriptEntity, amount: Float)`
+/// Adds the specified `amount` of threat for the `target`, which may be
+/// negative.
+///
+/// # `threat_table() -> Table`
+/// Returns a list of tables with the `entity` and `threat` of each living
+/// entity that has generated threat against this group, highest threat first.
+///
+/// # `highest_threat() -> ScriptEntity`
+/// Returns the living entity with the highest threat, or nil if there is none.
+///
+/// # `focus() -> ScriptEntity`
+/// Returns the target the group is focusing its attacks on, or nil if there is
+/// no focus or the target is dead.
+///
+/// # `set_focus(target: ScriptEntity)`
+/// Sets the target the group should focus its attacks on.
+///
+/// # `clear_focus()`
+/// Clears the focused target.
+///
+/// # `claim_square(entity: ScriptEntity, x: Int, y: Int)`
+/// Claims the square at `x`, `y` for the `entity` to move to, so that other
+/// members pick a different one.  Each entity holds at most one claim.
+/// `ScriptEntity::best_position` skips squares claimed by other members.
+///
+/// # `release_square(entity: ScriptEntity)`
+/// Releases the square claimed by the `entity`, if any.
+///
+/// # `claimed_square(entity: ScriptEntity) -> Table`
+/// Returns a table with the `x` and `y` of the square claimed by the `entity`,
+/// or nil if it has no claim.
+///
+/// # `is_square_claimed(x: Int, y: Int, except: ScriptEntity (Optional)) -> Bool`
+/// Returns whether the square at `x`, `y` is claimed by any entity other than
+/// `except`.
+///
+/// # `set_flag(flag: String, value: String (Optional))`
+/// Sets a `flag` shared by the group.  If the value is not specified, it is
+/// set to `true`.
+///
+/// # `get_flag(flag: String) -> String`
+/// Returns the value of the `flag`, or nil if it is not set.
+///
+/// # `clear_flag(flag: String)`
+/// Clears the `flag`.
+/// ## Examples
+/// ```lua
+///   local group = parent:ai_group()
+///   if group ~= nil and group:focus() == nil then
+///     group:set_focus(group:highest_threat() or target)
+///   end
+/// ```
+#[derive(Clone)]
+pub struct ScriptAIGroup {
+    group: usize,
+}
+
+impl ScriptAIGroup {
+    pub fn new(group: usize) -> ScriptAIGroup {
+        ScriptAIGroup { group }
+    }
+
+    fn with<T, F: FnOnce(&AIBlackboard) -> T>(&self, f: F) -> T {
+        let mgr = GameState::turn_manager();
+        let mgr = mgr.borrow();
+        match mgr.ai_blackboard(self.group) {
+            None => f(&AIBlackboard::default()),
+            Some(blackboard) => f(blackboard),
+        }
+    }
+
+    fn with_mut<T, F: FnOnce(&mut AIBlackboard) -> T>(&self, f: F) -> T {
+        let mgr = GameState::turn_manager();
+        let mut mgr = mgr.borrow_mut();
+        f(mgr.ai_blackboard_mut(self.group))
+    }
+}
+
+/// Returns the entity with the specified index, if it is still alive
+fn living(index: usize) -> Option<Rc<RefCell<EntityState>>> {
+    let entity = GameState::turn_manager().borrow().entity_checked(index)?;
+    if entity.borrow().actor.is_dead() {
+        return None;
+    }
+    Some(entity)
+}
+
+impl UserData for ScriptAIGroup {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("id", |_, group, ()| Ok(group.group));
+
+        methods.add_method("members", |lua, group, role: Option<String>| {
+            let role = match role {
+                None => None,
+                Some(role) => match AIRole::option_from_str(&role) {
+                    None => {
+                        warn!("Invalid AI role '{}' in script", role);
+                        return Err(rlua::Error::FromLuaConversionError {
+                            from: "String",
+                            to: "AIRole",
+                            message: Some(format!("Invalid AI role '{role}'")),
+                        });
+                    }
+                    Some(role) => Some(role),
+                },
+            };
+
+            let table = lua.create_table()?;
+            let mgr = GameState::turn_manager();
+            for entity in mgr.borrow().entity_iter() {
+                let entity = entity.borrow();
+                if entity.ai_group() != Some(group.group) || entity.actor.is_dead() {
+                    continue;
+                }
+                if role.is_some() && entity.ai_role() != role {
+                    continue;
+                }
+                table.set(table.raw_len() + 1, ScriptEntity::new(entity.index()))?;
+            }
+            Ok(table)
+        });
+
+        methods.add_method("threat", |_, group, target: ScriptEntity| {
+            let index = target.try_unwrap_index()?;
+            Ok(group.with(|bb| bb.threat(index)))
+        });
+
+        methods.add_method(
+            "add_threat",
+            |_, group, (target, amount): (ScriptEntity, f32)| {
+                let index = target.try_unwrap_index()?;
+                group.with_mut(|bb| bb.add_threat(index, amount));
+                Ok(())
+            },
+        );
+
+        methods.add_method("threat_table", |lua, group, ()| {
+            let table = lua.create_table()?;
+            for (index, threat) in group.with(|bb| bb.threat_list()) {
+                if living(index).is_none() {
+                    continue;
+                }
+                let entry = lua.create_table()?;
+                entry.set("entity", ScriptEntity::new(index))?;
+                entry.set("threat", threat)?;
+                table.set(table.raw_len() + 1, entry)?;
+            }
+            Ok(table)
+        });
+
+        methods.add_method("highest_threat", |_, group, ()| {
+            let list = group.with(|bb| bb.threat_list());
+            let entity = list.into_iter().find_map(|(index, _)| living(index));
+            Ok(entity.map(|entity| ScriptEntity::from(&entity)))
+        });
+
+        methods.add_method("focus", |_, group, ()| {
+            let entity = group.with(|bb| bb.focus()).and_then(living);
+            Ok(entity.map(|entity| ScriptEntity::from(&entity)))
+        });
+
+        methods.add_method("set_focus", |_, group, target: ScriptEntity| {
+            let index = target.try_unwrap_index()?;
+            group.with_mut(|bb| bb.set_focus(Some(index)));
+            Ok(())
+        });
+
+        methods.add_method("clear_focus", |_, group, ()| {
+            group.with_mut(|bb| bb.set_focus(None));
+            Ok(())
+        });
+
+        methods.add_method(
+            "claim_square",
+            |_, group, (entity, x, y): (ScriptEntity, i32, i32)| {
+                let index = entity.try_unwrap_index()?;
+                group.with_mut(|bb| bb.claim_square(index, Point::new(x, y)));
+                Ok(())
+            },
+        );
+
+        methods.add_method("release_square", |_, group, entity: ScriptEntity| {
+            let index = entity.try_unwrap_index()?;
+            group.with_mut(|bb| bb.release_square(index));
+            Ok(())
+        });
+
+        methods.add_method("claimed_square", |lua, group, entity: ScriptEntity| {
+            let index = entity.try_unwrap_index()?;
+            let p = match group.with(|bb| bb.claimed_square(index)) {
+                None => return Ok(None),
+                Some(p) => p,
+            };
+            let table = lua.create_table()?;
+            table.set("x", p.x)?;
+            table.set("y", p.y)?;
+            Ok(Some(table))
+        });
+
+        methods.add_method(
+            "is_square_claimed",
+            |_, group, (x, y, except): (i32, i32, Option<ScriptEntity>)| {
+                let except = match except {
+                    None => None,
+                    Some(entity) => Some(entity.try_unwrap_index()?),
+                };
+                Ok(group.with(|bb| bb.is_claimed(Point::new(x, y), except)))
+            },
+        );
+
+        methods.add_method(
+            "set_flag",
+            |_, group, (flag, val): (String, Option<String>)| {
+                let val = val.as_deref().unwrap_or("true");
+                group.with_mut(|bb| bb.set_flag(&flag, val));
+                Ok(())
+            },
+        );
+
+        methods.add_method("get_flag", |_, group, flag: String| {
+            Ok(group.with(|bb| bb.flag(&flag).map(|s| s.to_string())))
+        });
+
+        methods.add_method("clear_flag", |_, group, flag: String| {
+            group.with_mut(|bb| bb.clear_flag(&flag));
+            Ok(())
+        });
+    }
+}
@@ -22,7 +22,8 @@ use std::time::{Duration, Instant};
 use rlua::{self, FromLuaMulti, ToLua, ToLuaMulti};
 
 use crate::script::{
-    Result, ScriptAbility, ScriptEntity, ScriptEntitySet, ScriptItem, ScriptItemKind, ScriptState,
+    quota_error, test_runner, Result, ScriptAbility, ScriptEntity, ScriptEntitySet, ScriptItem,
+    ScriptItemKind, ScriptKind, ScriptState,
 };
 use crate::{ai, EntityState};
 use sulis_core::util::Point;
@@ -31,6 +32,7 @@ use sulis_module::{ai::AITemplate, Ability, Item, Module};
 thread_local! {
     static SCRIPT_CACHE: RefCell<HashMap<String, Rc<ScriptState>>> = RefCell::new(HashMap::new());
     static REPORTING: Cell<bool> = const { Cell::new(true) };
+    static QUOTA_EXCEEDED: Cell<bool> = const { Cell::new(false) };
 }
 
 pub fn setup() -> Result<()> {
@@ -40,6 +42,10 @@ pub fn setup() -> Result<()> {
 
         cache.clear();
         for id in Module::all_scripts() {
+            if test_runner::is_test_script(&id) {
+                continue;
+            }
+
             let script = get_script_from_id(&id)?;
             let mut state = ScriptState::default();
             state.load(&id, &script)?;
@@ -55,11 +61,62 @@ pub fn setup() -> Result<()> {
     Ok(())
 }
 
+/// Rebuilds the script states for the specified scripts, and for any scripts that
+/// require them, from the module's current scripts, removing any that no longer
+/// exist.  A script that fails to load keeps its previous state.  Returns a
+/// description of each failure.
+pub fn reload(ids: &[String]) -> Vec<String> {
+    let mut failures = Vec::new();
+    SCRIPT_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+
+        let mut ids = ids.to_vec();
+        for (id, state) in cache.iter() {
+            if !ids.contains(id) && state.modules().iter().any(|module| ids.contains(module)) {
+                ids.push(id.to_string());
+            }
+        }
+
+        for id in ids.iter() {
+            if test_runner::is_test_script(id) {
+                continue;
+            }
+
+            let script = match Module::script(id) {
+                None => {
+                    cache.remove(id);
+                    continue;
+                }
+                Some(script) => script,
+            };
+
+            let mut state = ScriptState::default();
+            match state.load(id, &script) {
+                Err(e) => {
+                    warn!("Unable to reload script '{}': {}", id, e);
+                    failures.push(format!("Unable to reload script '{id}': {e}"));
+                }
+                Ok(()) => {
+                    cache.insert(id.to_string(), Rc::new(state));
+                }
+            }
+        }
+    });
+    failures
+}
+
 pub fn set_report_enabled(enabled: bool) {
     REPORTING.with(|r| r.set(enabled));
 }
 
-fn parse_traceback_line_num(traceback: &str) -> Option<i32> {
+/// Returns whether the most recent script call exceeded its quota
+pub fn take_quota_exceeded() -> bool {
+    QUOTA_EXCEEDED.with(|q| q.replace(false))
+}
+
+/// Returns the script ID and line number of the first line of the traceback
+/// with a line number.  The ID is only present if the line names the script.
+fn parse_traceback_location(traceback: &str) -> Option<(Option<String>, i32)> {
     // find first line with a line number, this should be the right
     // place on the call stack
 
@@ -69,34 +126,37 @@ fn parse_traceback_line_num(traceback: &str) -> Option<i32> {
             Some(num_str) => num_str,
         };
 
-        match num_str.parse() {
+        let num = match num_str.parse() {
             Err(_) => continue,
-            Ok(num) => return Some(num),
+            Ok(num) => num,
         };
+
+        let id = line
+            .split_once("[string \"")
+            .and_then(|(_, rest)| rest.split_once("\"]"))
+            .map(|(id, _)| id.to_string());
+        return Some((id, num));
     }
 
     None
 }
 
 #[allow(clippy::format_push_string)] // performance characteristics of this function are irrelevant
-fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, i32) {
+fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, String, i32) {
     let mut out = String::new();
 
-    let num = match parse_traceback_line_num(traceback) {
+    let (id, num) = match parse_traceback_location(traceback) {
         None => {
             out.push_str("No traceback available.\n");
-            return (out, 0);
+            return (out, state.id.clone(), 0);
         }
-        Some(num) => num,
+        Some((id, num)) => (id.unwrap_or_else(|| state.id.clone()), num),
     };
 
-    let script = match get_script_from_id(&state.id) {
+    let script = match get_script_from_id(&id) {
         Err(_) => {
-            out.push_str(&format!(
-                "Unable to find script: {} for traceback.\n",
-                state.id
-            ));
-            return (out, 0);
+            out.push_str(&format!("Unable to find script: {id} for traceback.\n"));
+            return (out, id, 0);
         }
         Ok(script) => script,
     };
@@ -117,15 +177,11 @@ fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, i32) {
         out.push_str(&format!(" | {line}\n"));
     }
 
-    (out, num)
+    (out, id, num)
 }
 
-pub fn exec_func<Args, Ret>(id: &str, func: &str, args: Args) -> Result<Ret>
-where
-    Args: for<'a> ToLuaMulti<'a>,
-    Ret: for<'a> FromLuaMulti<'a>,
-{
-    let state = SCRIPT_CACHE.with(|cache| {
+fn get_state(id: &str) -> Result<Rc<ScriptState>> {
+    SCRIPT_CACHE.with(|cache| {
         let cache = cache.borrow();
 
         //setup the script if it does not already exist
@@ -138,18 +194,38 @@ where
         }
 
         Ok(Rc::clone(cache.get(id).unwrap()))
-    })?;
+    })
+}
+
+/// Evaluates a line from the console in the state of the specified script, so
+/// that the functions it defines may be called
+pub fn console(id: &str, script: String, party: &[Rc<RefCell<EntityState>>]) -> Result<String> {
+    get_state(id)?.console(script, party)
+}
+
+pub fn exec_func<Args, Ret>(id: &str, func: &str, args: Args, kind: ScriptKind) -> Result<Ret>
+where
+    Args: for<'a> ToLuaMulti<'a>,
+    Ret: for<'a> FromLuaMulti<'a>,
+{
+    let state = get_state(id)?;
 
     let reporting = REPORTING.with(|r| r.get());
+    QUOTA_EXCEEDED.with(|q| q.set(false));
 
     use rlua::Error::*;
-    match state.exec_func(func, args, reporting) {
+    match state.exec_func(func, args, kind, reporting) {
         Ok(ret) => Ok(ret),
+        Err(e) if quota_error(&e).is_some() => {
+            warn!("Script quota exceeded: {}", quota_error(&e).unwrap());
+            QUOTA_EXCEEDED.with(|q| q.set(true));
+            Err(e)
+        }
         Err(CallbackError { traceback, cause }) => {
-            let (output, line_num) = print_nearby_lines(&state, &traceback);
+            let (output, script_id, line_num) = print_nearby_lines(&state, &traceback);
             warn!(
                 "Script Error:\n{}\n{}.lua:{} Called '{}'\n{}",
-                cause, state.id, line_num, func, output
+                cause, script_id, line_num, func, output
             );
             Err(CallbackError { traceback, cause })
         }
@@ -158,12 +234,13 @@ where
 }
 
 pub fn ai_script(parent: &Rc<RefCell<EntityState>>, func: &str) -> Result<ai::State> {
-    let script_data = get_script_data_from_entity(parent)?;
+    let script_data = get_ai_template(parent)?;
     let parent = ScriptEntity::from(parent);
     exec_func(
         &script_data.script,
         func,
         (parent, script_data.params.clone()),
+        ScriptKind::Ai,
     )
 }
 
@@ -178,7 +255,12 @@ where
 {
     let script_data = get_script_data_from_entity(parent)?;
     let parent = ScriptEntity::from(parent);
-    exec_func(&script_data.script, func, (parent, targets, arg))
+    exec_func(
+        &script_data.script,
+        func,
+        (parent, targets, arg),
+        ScriptKind::Ability,
+    )
 }
 
 pub fn item_on_activate(
@@ -231,7 +313,12 @@ where
     let script = get_item_script_id(&item_src)?;
     let parent = ScriptEntity::from(parent);
 
-    exec_func(&script, func, (parent, item, targets, arg))
+    exec_func(
+        &script,
+        func,
+        (parent, item, targets, arg),
+        ScriptKind::Ability,
+    )
 }
 
 pub fn ability_on_activate(parent: usize, func: String, ability: &Rc<Ability>) -> Result<()> {
@@ -239,7 +326,7 @@ pub fn ability_on_activate(parent: usize, func: String, ability: &Rc<Ability>) -
     let parent = ScriptEntity::new(parent);
     let ability = ScriptAbility::from(ability);
 
-    exec_func(&script, &func, (parent, ability))
+    exec_func(&script, &func, (parent, ability), ScriptKind::Ability)
 }
 
 pub fn ability_on_deactivate(parent: usize, ability: &Rc<Ability>) -> Result<()> {
@@ -256,7 +343,12 @@ pub fn ability_on_deactivate(parent: usize, ability: &Rc<Ability>) -> Result<()>
     let script = get_ability_script_id(ability)?;
     let parent = ScriptEntity::new(parent);
     let ability = ScriptAbility::from(ability);
-    exec_func(&script, "on_deactivate", (parent, ability))
+    exec_func(
+        &script,
+        "on_deactivate",
+        (parent, ability),
+        ScriptKind::Ability,
+    )
 }
 
 pub fn ability_on_target_select(
@@ -288,14 +380,19 @@ where
     let script = get_ability_script_id(ability)?;
     let parent = ScriptEntity::from(parent);
     let ability = ScriptAbility::from(ability);
-    exec_func(&script, func, (parent, ability, targets, arg))
+    exec_func(
+        &script,
+        func,
+        (parent, ability, targets, arg),
+        ScriptKind::Ability,
+    )
 }
 
 pub fn trigger_script<Args>(script_id: &str, func: &str, args: Args) -> Result<()>
 where
     Args: for<'a> ToLuaMulti<'a>,
 {
-    exec_func(script_id, func, args)
+    exec_func(script_id, func, args, ScriptKind::Trigger)
 }
 
 fn get_script_data_from_entity(entity: &Rc<RefCell<EntityState>>) -> Result<Rc<AITemplate>> {
@@ -311,6 +408,23 @@ fn get_script_data_from_entity(entity: &Rc<RefCell<EntityState>>) -> Result<Rc<A
     }
 }
 
+/// Returns the template running the entity's AI turns, which for party
+/// members may differ from the actor's own
+fn get_ai_template(entity: &Rc<RefCell<EntityState>>) -> Result<Rc<AITemplate>> {
+    let entity = entity.borrow();
+    match entity.ai_template() {
+        None => Err(rlua::Error::ToLuaConversionError {
+            from: "Entity",
+            to: "Script",
+            message: Some(format!(
+                "AI called for entity '{}' with no AI",
+                entity.unique_id()
+            )),
+        }),
+        Some(ai) => Ok(ai),
+    }
+}
+
 fn get_item_script_id(item: &Rc<Item>) -> Result<String> {
     match &item.usable {
         None => Err(rlua::Error::ToLuaConversionError {
@@ -21,7 +21,7 @@ use std::rc::Rc;
 use std::result;
 
 use serde::{Serialize, Deserialize};
-use rlua::{UserData, UserDataMethods};
+use rlua::{Context, ToLua, UserData, UserDataMethods, Value};
 
 use crate::script::{
     script_entity, ScriptActiveSurface, ScriptAppliedEffect, ScriptEntity, ScriptEntitySet,
@@ -71,6 +71,8 @@ pub fn fire_cbs(cbs: Vec<TriggeredCallback>) {
             FuncKind::OnMoved => cb.on_moved(),
             FuncKind::OnRoundElapsed => cb.on_round_elapsed(),
             FuncKind::OnSurfaceRoundElapsed => cb.on_surface_round_elapsed(),
+            FuncKind::OnCombatStarted => cb.on_combat_started(),
+            FuncKind::OnTurnStarted => cb.on_turn_started(),
             FuncKind::OnActivated => match &cb.kind {
                 Kind::Ability(id) => {
                     let ability = Module::ability(id).unwrap();
@@ -111,7 +113,8 @@ fn get_on_activate_fn(cb: &CallbackData, ability: &Ability) -> String {
     match mgr.entity_checked(cb.parent) {
         None => ON_ACTIVATE_DEFAULT.to_string(),
         Some(entity) => {
-            if entity.borrow().is_party_member() {
+            let entity = entity.borrow();
+            if entity.is_party_member() && !entity.is_party_ai_active(&mgr) {
                 ON_ACTIVATE_DEFAULT.to_string()
             } else {
                 func
@@ -126,14 +129,56 @@ pub fn fire_on_moved(cbs: Vec<Rc<CallbackData>>) {
     }
 }
 
+/// Fires the `OnLevelUp` callbacks of the specified entity
+pub fn fire_on_level_up(entity: &Rc<RefCell<EntityState>>) {
+    let mgr = GameState::turn_manager();
+    let cbs = entity.borrow().callbacks(&mgr.borrow());
+    cbs.iter().for_each(|cb| cb.on_level_up());
+}
+
+/// An item ID and quantity, passed to `OnItemPickedUp` callbacks as a table
+/// with `id` and `quantity` fields
+#[derive(Clone)]
+pub struct PickedUpItem {
+    pub id: String,
+    pub quantity: u32,
+}
+
+impl<'lua> ToLua<'lua> for PickedUpItem {
+    fn to_lua(self, lua: Context<'lua>) -> rlua::Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("id", self.id)?;
+        table.set("quantity", self.quantity)?;
+        Ok(Value::Table(table))
+    }
+}
+
+/// Fires the `OnItemPickedUp` callbacks of each party member for the items
+/// picked up into the party stash since the last update
+pub(crate) fn fire_on_items_picked_up() {
+    let items = GameState::party_stash().borrow_mut().drain_picked_up();
+    if items.is_empty() {
+        return;
+    }
+
+    let mgr = GameState::turn_manager();
+    for member in GameState::party() {
+        let cbs = member.borrow().callbacks(&mgr.borrow());
+        for (id, quantity) in items.iter() {
+            let item = PickedUpItem {
+                id: id.to_string(),
+                quantity: *quantity,
+            };
+            cbs.iter().for_each(|cb| cb.on_item_picked_up(&item));
+        }
+    }
+}
+
 /// A type of callback function for a `CallbackData` object.
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialOrd, Ord, Hash, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
 pub enum FuncKind {
-    /// Save compatibility only.  TODO remove this after a while
-    OnSwapWeapons,
-
     /// Called when an entity swaps their weapon set
     OnHeldChanged,
 
@@ -197,6 +242,41 @@ pub enum FuncKind {
 
     /// Called whena an ability mode is deactivated
     OnDeactivated,
+
+    /// Called when the parent entity is reduced to zero hit points.  The
+    /// targets are the parent and the entity that dealt the damage.
+    OnDeath,
+
+    /// Called on the entity that reduced another to zero hit points.  The
+    /// targets are the parent and the killed entity.
+    OnKill,
+
+    /// Called when the parent entity is healed, with the amount healed
+    OnHealed,
+
+    /// Called when the parent entity gains one or more levels
+    OnLevelUp,
+
+    /// Called when the parent entity equips an item, with the item ID
+    OnItemEquipped,
+
+    /// Called for each party member when the party picks up an item into the
+    /// party stash, whether taken from a container, bought from a merchant, or
+    /// given by a script or conversation.  The argument is a table with the item
+    /// `id` and `quantity`.  Items returned to the stash when unequipped do not
+    /// count as picked up.
+    OnItemPickedUp,
+
+    /// Called for each party member and each active AI entity when
+    /// combat begins
+    OnCombatStarted,
+
+    /// Called at the start of each of the parent entity's turns in combat
+    OnTurnStarted,
+
+    /// Called for each party member when the party enters an area, with the
+    /// area ID
+    OnAreaEntered,
 }
 
 /// A trait representing a callback that will fire a script when called.  In lua scripts,
@@ -253,6 +333,24 @@ pub trait ScriptCallback {
     fn on_entered_surface(&self, _target: usize) {}
 
     fn on_exited_surface(&self, _target: usize) {}
+
+    fn on_death(&self, _targets: &ScriptEntitySet) {}
+
+    fn on_kill(&self, _targets: &ScriptEntitySet) {}
+
+    fn on_healed(&self, _amount: u32) {}
+
+    fn on_level_up(&self) {}
+
+    fn on_item_equipped(&self, _item_id: &str) {}
+
+    fn on_item_picked_up(&self, _item: &PickedUpItem) {}
+
+    fn on_combat_started(&self) {}
+
+    fn on_turn_started(&self) {}
+
+    fn on_area_entered(&self, _area_id: &str) {}
 }
 
 /// A callback that can be passed to various functions to be executed later.
@@ -291,6 +389,15 @@ pub trait ScriptCallback {
 /// # `set_on_moved_in_surface_fn(func: String)`
 /// # `set_on_entered_surface_fn(func: String)`
 /// # `set_on_exited_surface_fn(func: String)`
+/// # `set_on_death_fn(func: String)`
+/// # `set_on_kill_fn(func: String)`
+/// # `set_on_healed_fn(func: String)`
+/// # `set_on_level_up_fn(func: String)`
+/// # `set_on_item_equipped_fn(func: String)`
+/// # `set_on_item_picked_up_fn(func: String)`
+/// # `set_on_combat_started_fn(func: String)`
+/// # `set_on_turn_started_fn(func: String)`
+/// # `set_on_area_entered_fn(func: String)`
 /// Each of these methods causes a specified lua `func` to be called when the condition is met,
 /// as described in `FuncKind`.  Multiple of these methods may be added to one
 /// Callback.
@@ -451,7 +558,7 @@ impl CallbackData {
 
     fn exec_script_with_arg<T>(&self, targets: ScriptEntitySet, arg: T, func_kind: FuncKind)
     where
-        T: rlua::UserData + Send + 'static,
+        T: for<'a> ToLua<'a> + Send,
     {
         let func = match self.funcs.get(&func_kind) {
             None => return,
@@ -621,6 +728,54 @@ impl ScriptCallback for CallbackData {
         self.exec_standard_script(targets, FuncKind::OnExitedSurface);
     }
 
+    fn on_death(&self, targets: &ScriptEntitySet) {
+        self.exec_standard_script(self.get_targets(targets), FuncKind::OnDeath);
+    }
+
+    fn on_kill(&self, targets: &ScriptEntitySet) {
+        self.exec_standard_script(self.get_targets(targets), FuncKind::OnKill);
+    }
+
+    fn on_healed(&self, amount: u32) {
+        self.exec_script_with_arg(self.get_or_create_targets(), amount, FuncKind::OnHealed);
+    }
+
+    fn on_level_up(&self) {
+        self.exec_standard_script(self.get_or_create_targets(), FuncKind::OnLevelUp);
+    }
+
+    fn on_item_equipped(&self, item_id: &str) {
+        self.exec_script_with_arg(
+            self.get_or_create_targets(),
+            item_id.to_string(),
+            FuncKind::OnItemEquipped,
+        );
+    }
+
+    fn on_item_picked_up(&self, item: &PickedUpItem) {
+        self.exec_script_with_arg(
+            self.get_or_create_targets(),
+            item.clone(),
+            FuncKind::OnItemPickedUp,
+        );
+    }
+
+    fn on_combat_started(&self) {
+        self.exec_standard_script(self.get_or_create_targets(), FuncKind::OnCombatStarted);
+    }
+
+    fn on_turn_started(&self) {
+        self.exec_standard_script(self.get_or_create_targets(), FuncKind::OnTurnStarted);
+    }
+
+    fn on_area_entered(&self, area_id: &str) {
+        self.exec_script_with_arg(
+            self.get_or_create_targets(),
+            area_id.to_string(),
+            FuncKind::OnAreaEntered,
+        );
+    }
+
     fn after_defense(
         &self,
         targets: &ScriptEntitySet,
@@ -837,6 +992,42 @@ impl UserData for CallbackData {
             cb.add_func(FuncKind::OnExitedSurface, func);
             Ok(())
         });
+        methods.add_method_mut("set_on_death_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnDeath, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_kill_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnKill, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_healed_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnHealed, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_level_up_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnLevelUp, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_item_equipped_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnItemEquipped, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_item_picked_up_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnItemPickedUp, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_combat_started_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnCombatStarted, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_turn_started_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnTurnStarted, func);
+            Ok(())
+        });
+        methods.add_method_mut("set_on_area_entered_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnAreaEntered, func);
+            Ok(())
+        });
     }
 }
 
@@ -24,7 +24,8 @@ use rlua::{self, Context, UserData, UserDataMethods};
 
 use crate::{ability_state::DisabledReason, dist, is_within_attack_dist, is_within_touch_dist};
 use crate::{ai, animation, entity_attack_handler, script::*, AreaFeedbackText};
-use crate::{area_feedback_text::ColorKind, EntityState, GameState, Location};
+use crate::{area_feedback_text::ColorKind, detection, Awareness, EntityState, GameState, Location};
+use crate::tactical_planner::{self, PositionOptions};
 use sulis_core::config::Config;
 use sulis_core::resource::ResourceSet;
 use sulis_core::util::ExtInt;
@@ -104,6 +105,43 @@ use sulis_module::{
 /// `Hostile`, `Neutral`, or `Friendly`.  Hostiles will attack the player and
 /// friendlies on sight, but will not engage neutrals.
 ///
+/// # `get_party_ai_mode() -> String`
+/// Returns whether this party member's combat turns are taken by the AI, one of
+/// `Off`, `Script`, or `Auto`.  Always `Off` for entities outside the party.
+///
+/// # `set_party_ai_mode(mode: String, template: String (Optional))`
+/// Sets whether this party member's combat turns are taken by the AI.  In `Script`
+/// mode, the AI template from the actor's definition is used, while `Auto` uses the
+/// chosen template.  If `template` is specified, it is the ID of the AI template
+/// to choose.  Has no effect on entities outside the party.
+///
+/// # `ai_group() -> ScriptAIGroup`
+/// Returns the blackboard shared by this entity's AI group, or nil if the
+/// entity is not part of a group.  Creatures spawned by the same encounter
+/// share a group.
+///
+/// # `ai_role() -> String`
+/// Returns the role from this entity's AI template, one of `Tank`, `Melee`,
+/// `Ranged`, `Caster`, or `Healer`, or nil if it has none.
+///
+/// # `awareness() -> String`
+/// Returns this entity's awareness of hidden party members and noises, one of
+/// `Unaware`, `Suspicious`, or `Alerted`.  Reset to `Unaware` when combat ends.
+///
+/// # `last_seen() -> Table`
+/// Returns a table with the `x` and `y` of the point where this entity last
+/// noticed a hidden party member or heard a noise, or nil if it is unaware.
+///
+/// # `detection_chance(target: ScriptEntity) -> Int`
+/// Returns the percent chance that this entity notices the `target` in a
+/// single round, becoming at least suspicious of it.  This is zero if the
+/// target is out of sight.
+///
+/// # `make_noise(radius: Float)`
+/// Makes a noise at this entity's location, making hostile actors within the
+/// `radius` that are not in combat suspicious.  The radius is reduced if this
+/// entity is hidden.
+///
 /// # `set_flag(flag: String, value: String (Optional))`
 /// Sets a `flag` to be stored on this entity.  This value will persist as part of the
 /// save game and can be used to store custom state.  If the value is not specified,
@@ -454,6 +492,28 @@ use sulis_module::{
 /// # `is_threatened_by(target: ScriptEntity) -> Bool`
 /// Returns true if this entity is threatened by the speciied target with its
 /// melee weapon, false otherwise
+///
+/// # `best_position(opts: Table (Optional)) -> Table`
+/// Scores each square this entity can move to with its remaining AP (at most
+/// 20 squares away), including its current position, and returns the best as a
+/// table of `x`, `y`, `score`, `moves`, `attackable`, `flanking`,
+/// `threatened_by`, and `visible`.  Returns nil if the entity is not in an area.
+/// Squares score for each target within attack range, each target that would
+/// be flanked along with a friendly, each target in line of sight, and each
+/// hostile melee attacker the square is threatened by, plus a cost per square
+/// moved.  Squares claimed by other members of the entity's AI group are
+/// skipped.  Only hostiles that are not hidden and that this entity has line of
+/// sight to are considered.  `opts` may contain `target`, a `ScriptEntity` to
+/// score against rather than all visible hostiles, `max_moves`, and weights
+/// overriding the defaults of `attack = 1.0`, `flank = 2.0`, `threat = -1.5`,
+/// `los = 0.5`, and `moves = -0.1`.
+/// ## Examples
+/// ```lua
+///   local pos = parent:best_position({ target = target, threat = -3.0 })
+///   if pos.moves > 0 then
+///     parent:move_towards_point(pos.x, pos.y)
+///   end
+/// ```
 #[derive(Clone, Debug)]
 pub struct ScriptEntity {
     pub index: Option<usize>,
@@ -628,6 +688,10 @@ impl UserData for ScriptEntity {
             entity.borrow_mut().actor.replace_actor(actor);
             entity.borrow_mut().actor.init_day();
 
+            if levels > 0 {
+                script_callback::fire_on_level_up(&entity);
+            }
+
             Ok(())
         });
 
@@ -699,6 +763,82 @@ impl UserData for ScriptEntity {
             Ok(())
         });
 
+        methods.add_method("ai_group", |_, entity, ()| {
+            let entity = entity.try_unwrap()?;
+            let group = entity.borrow().ai_group();
+            Ok(group.map(ScriptAIGroup::new))
+        });
+
+        methods.add_method("ai_role", |_, entity, ()| {
+            let entity = entity.try_unwrap()?;
+            let role = entity.borrow().ai_role();
+            Ok(role.map(|role| role.to_str()))
+        });
+
+        methods.add_method("awareness", |_, entity, ()| {
+            let entity = entity.try_unwrap()?;
+            let awareness = entity.borrow().detection().awareness();
+            Ok(awareness.to_str())
+        });
+
+        methods.add_method("last_seen", |lua, entity, ()| {
+            let entity = entity.try_unwrap()?;
+            let p = match entity.borrow().detection().last_seen() {
+                None => return Ok(None),
+                Some(p) => p,
+            };
+            let table = lua.create_table()?;
+            table.set("x", p.x)?;
+            table.set("y", p.y)?;
+            Ok(Some(table))
+        });
+
+        methods.add_method("detection_chance", |_, entity, target: ScriptEntity| {
+            let entity = entity.try_unwrap()?;
+            let target = target.try_unwrap()?;
+            let area = GameState::area_state();
+            let chance = detection::chance(
+                &area.borrow(),
+                &entity.borrow(),
+                &target.borrow(),
+                Awareness::Suspicious,
+            );
+            Ok(chance)
+        });
+
+        methods.add_method("make_noise", |_, entity, radius: f32| {
+            let entity = entity.try_unwrap()?;
+            detection::add_noise(&entity.borrow(), radius);
+            Ok(())
+        });
+
+        methods.add_method("get_party_ai_mode", |_, entity, ()| {
+            let entity = entity.try_unwrap()?;
+            let mode = entity.borrow().party_ai_mode();
+            Ok(mode.to_str())
+        });
+
+        methods.add_method(
+            "set_party_ai_mode",
+            |_, entity, (mode, template): (String, Option<String>)| {
+                let entity = entity.try_unwrap()?;
+                let mut entity = entity.borrow_mut();
+
+                match ai::PartyAIMode::option_from_str(&mode) {
+                    None => warn!("Invalid party AI mode '{}' in script", mode),
+                    Some(mode) => entity.set_party_ai_mode(mode),
+                }
+
+                if let Some(id) = template {
+                    match Module::ai_template(&id) {
+                        None => warn!("Invalid AI template '{}' in script", id),
+                        Some(template) => entity.set_party_ai_template(template),
+                    }
+                }
+                Ok(())
+            },
+        );
+
         methods.add_method("get_num_flag", |_, entity, flag: String| {
             let entity = entity.try_unwrap()?;
             let val = entity.borrow().get_num_flag(&flag);
@@ -776,7 +916,7 @@ impl UserData for ScriptEntity {
                     }
                 }
                 let index = parent.borrow().index();
-                let func = get_on_activate_fn(parent.borrow().is_party_member(), ability.ai_data());
+                let func = get_on_activate_fn(&parent.borrow(), ability.ai_data());
                 Script::ability_on_activate(index, func, &ability.to_ability());
                 Ok(true)
             },
@@ -788,7 +928,7 @@ impl UserData for ScriptEntity {
             if !parent.borrow().actor.can_use_quick(slot) {
                 return Ok(false);
             }
-            let func = get_on_activate_fn(parent.borrow().is_party_member(), item.ai_data());
+            let func = get_on_activate_fn(&parent.borrow(), item.ai_data());
             Script::item_on_activate(&parent, func, ScriptItemKind::Quick(slot));
             Ok(true)
         });
@@ -1329,6 +1469,10 @@ impl UserData for ScriptEntity {
             feedback.add_entry(format!("{amount}"), ColorKind::Heal);
             area_state.borrow_mut().add_feedback_text(feedback);
 
+            let mgr = GameState::turn_manager();
+            let cbs = parent.borrow().callbacks(&mgr.borrow());
+            cbs.iter().for_each(|cb| cb.on_healed(amount));
+
             Ok(())
         });
 
@@ -1626,7 +1770,48 @@ impl UserData for ScriptEntity {
             let target = target.index.unwrap_or(std::usize::MAX);
             Ok(entity.actor.p_stats().is_threatened_by(target))
         });
+
+        methods.add_method("best_position", best_position);
+    }
+}
+
+fn best_position<'a>(
+    lua: Context<'a>,
+    parent: &ScriptEntity,
+    opts: Option<rlua::Table<'a>>,
+) -> Result<Option<rlua::Table<'a>>> {
+    let parent = parent.try_unwrap()?;
+
+    let mut options = PositionOptions::default();
+    if let Some(opts) = opts {
+        if let Some(target) = opts.get::<_, Option<ScriptEntity>>("target")? {
+            options.target = Some(target.try_unwrap()?);
+        }
+        options.max_moves = opts.get("max_moves")?;
+
+        let weights = &mut options.weights;
+        weights.attack = opts.get::<_, Option<f32>>("attack")?.unwrap_or(weights.attack);
+        weights.flank = opts.get::<_, Option<f32>>("flank")?.unwrap_or(weights.flank);
+        weights.threat = opts.get::<_, Option<f32>>("threat")?.unwrap_or(weights.threat);
+        weights.los = opts.get::<_, Option<f32>>("los")?.unwrap_or(weights.los);
+        weights.moves = opts.get::<_, Option<f32>>("moves")?.unwrap_or(weights.moves);
     }
+
+    let best = match tactical_planner::best_position(&parent, &options) {
+        None => return Ok(None),
+        Some(best) => best,
+    };
+
+    let table = lua.create_table()?;
+    table.set("x", best.x)?;
+    table.set("y", best.y)?;
+    table.set("score", best.score)?;
+    table.set("moves", best.moves)?;
+    table.set("attackable", best.attackable)?;
+    table.set("flanking", best.flanking)?;
+    table.set("threatened_by", best.threatened_by)?;
+    table.set("visible", best.visible)?;
+    Ok(Some(table))
 }
 
 #[allow(clippy::unnecessary_wraps)] // this must return a result to be added as a method in the LUA context
@@ -1823,8 +2008,9 @@ fn targets(_lua: Context, parent: &ScriptEntity, _args: ()) -> Result<ScriptEnti
     })
 }
 
-fn get_on_activate_fn(is_party_member: bool, ai_data: &AIData) -> String {
-    if is_party_member {
+fn get_on_activate_fn(parent: &EntityState, ai_data: &AIData) -> String {
+    let mgr = GameState::turn_manager();
+    if parent.is_party_member() && !parent.is_party_ai_active(&mgr.borrow()) {
         "on_activate".to_string()
     } else if let Some(func) = &ai_data.on_activate_fn {
         func.to_string()
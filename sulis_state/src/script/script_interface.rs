@@ -17,10 +17,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use log::Level;
 use rlua::{self, UserData, UserDataMethods};
 
 use crate::script::*;
-use crate::{animation::Anim, AreaState, EntityState, GameState, Location};
+use crate::{animation::Anim, hot_reload, AreaState, EntityState, GameState, Location};
+use sulis_core::resource::{YamlResourceKind, YamlResourceSet};
+use sulis_core::util::ActiveResources;
 use sulis_core::{config::Config};
 use sulis_module::on_trigger::{self, QuestEntryState};
 use sulis_module::{Faction, ItemState, Module, OnTrigger, Time};
@@ -168,6 +171,22 @@ use sulis_module::{Faction, ItemState, Module, OnTrigger, Time};
 /// # `trace(message: String)`
 /// Logs the specified string to game output at trace level.
 ///
+/// # `dump_resource(kind: String, id: String) -> String`
+/// Re-reads the YAML of the active campaign and mods and returns the final merged
+/// YAML of the specified resource, with comments noting which file set each value
+/// that was changed by a campaign or mod.  `kind` is the name of the directory the
+/// resource is read from, such as `abilities` or `actors`.  The result is also
+/// logged at info level.
+///
+/// # `reload_resources() -> String`
+/// Reloads any scripts, abilities, and items that have changed on disk, and returns
+/// a summary of what was reloaded.  Abilities of existing entities are updated,
+/// but existing items keep their previous definitions.
+///
+/// # `storage() -> ScriptStorage`
+/// Returns the table of data shared by all scripts for this campaign, which is saved
+/// with the game.  See `ScriptStorage`.
+///
 /// # `ap_display_factor() -> Int`
 /// Gets the ap display factor, which is the factor that the internal AP representation is
 /// divided by when displayed.  Any AP values that are displayed to the user must be
@@ -531,16 +550,19 @@ impl UserData for ScriptInterface {
 
         methods.add_method("warn", |_, _, val: String| {
             warn!("[LUA WARN]: {}", val);
+            console::log(Level::Warn, &val);
             Ok(())
         });
 
         methods.add_method("log", |_, _, val: String| {
             info!("[LUA]: {}", val);
+            console::log(Level::Info, &val);
             Ok(())
         });
 
         methods.add_method("debug", |_, _, val: String| {
             debug!("[LUA]: {}", val);
+            console::log(Level::Debug, &val);
             Ok(())
         });
 
@@ -549,6 +571,16 @@ impl UserData for ScriptInterface {
             Ok(())
         });
 
+        methods.add_method("dump_resource", |_, _, (kind, id): (String, String)| {
+            dump_resource(&kind, &id)
+        });
+
+        methods.add_method("storage", |_, _, ()| Ok(ScriptStorage::root()));
+
+        methods.add_method("reload_resources", |_, _, ()| {
+            Ok(hot_reload::reload(true, true))
+        });
+
         methods.add_method("ap_display_factor", |_, _, ()| {
             let rules = Module::rules();
             Ok(rules.display_ap)
@@ -1022,7 +1054,7 @@ impl UserData for ScriptInterface {
             };
 
             let item_state = ItemState::new(item, None);
-            let index = stash.borrow_mut().add_item(1, item_state);
+            let index = stash.borrow_mut().pick_up(1, item_state);
             Ok(ScriptStashItem { index })
         });
 
@@ -1067,6 +1099,34 @@ impl UserData for ScriptInterface {
     }
 }
 
+fn dump_resource(kind: &str, id: &str) -> Result<String> {
+    let yaml_kind = match YamlResourceKind::from_dir_name(kind) {
+        None => {
+            return Err(rlua::Error::ToLuaConversionError {
+                from: "String",
+                to: "YamlResourceKind",
+                message: Some(format!("Unknown resource kind '{kind}'")),
+            })
+        }
+        Some(kind) => kind,
+    };
+
+    let dirs = ActiveResources::read().directories();
+    let yaml = YamlResourceSet::from_dirs(&dirs).map_err(rlua::Error::external)?;
+
+    match yaml.dump_annotated(yaml_kind, id) {
+        None => Err(rlua::Error::ToLuaConversionError {
+            from: "String",
+            to: "Resource",
+            message: Some(format!("No {kind} with ID '{id}' found")),
+        }),
+        Some(dump) => {
+            info!("{}", dump);
+            Ok(dump)
+        }
+    }
+}
+
 fn get_area(id: Option<String>) -> Result<Rc<RefCell<AreaState>>> {
     match id {
         None => Ok(GameState::area_state()),
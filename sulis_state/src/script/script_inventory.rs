@@ -192,10 +192,15 @@ impl UserData for ScriptInventory {
                 Some(item) => item,
             };
 
+            let item_id = item.item.id.clone();
             let to_add = entity.borrow_mut().actor.equip(item, None);
             for item in to_add {
                 stash.borrow_mut().add_item(1, item);
             }
+
+            let mgr = GameState::turn_manager();
+            let cbs = entity.borrow().callbacks(&mgr.borrow());
+            cbs.iter().for_each(|cb| cb.on_item_equipped(&item_id));
             Ok(())
         });
 
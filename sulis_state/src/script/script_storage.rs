@@ -0,0 +1,360 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use rlua::{self, Context, MetaMethod, ToLua, UserData, UserDataMethods, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::script::Result;
+
+/// The maximum estimated size of all stored data.  The data is kept in memory
+/// for the whole campaign and written into every save, so it is limited to a
+/// fraction of the memory available to a single script by default.
+const STORAGE_LIMIT: usize = 1_048_576;
+
+/// The maximum depth of nested tables, which also stops tables that contain
+/// themselves
+const MAX_DEPTH: usize = 32;
+
+/// The estimated size of a single key or value, not counting string contents
+const ENTRY_SIZE: usize = 16;
+
+thread_local! {
+    static STORAGE: RefCell<Storage> = RefCell::new(Storage::default());
+}
+
+#[derive(Default)]
+struct Storage {
+    root: StorageTable,
+    size: usize,
+}
+
+impl Storage {
+    fn table(&self, path: &[StorageKey]) -> Result<&StorageTable> {
+        let mut table = &self.root;
+        for key in path {
+            table = match table.entries.get(key) {
+                Some(StorageValue::Table(table)) => table,
+                _ => return Err(removed_error()),
+            };
+        }
+        Ok(table)
+    }
+
+    fn table_mut(&mut self, path: &[StorageKey]) -> Result<&mut StorageTable> {
+        let mut table = &mut self.root;
+        for key in path {
+            table = match table.entries.get_mut(key) {
+                Some(StorageValue::Table(table)) => table,
+                _ => return Err(removed_error()),
+            };
+        }
+        Ok(table)
+    }
+
+    fn set(
+        &mut self,
+        path: &[StorageKey],
+        key: StorageKey,
+        value: Option<StorageValue>,
+    ) -> Result<()> {
+        let cur_size = self.size;
+        let table = self.table_mut(path)?;
+
+        let old = table.entries.get(&key).map_or(0, |v| key.size() + v.size());
+        let new = value.as_ref().map_or(0, |v| key.size() + v.size());
+        let size = cur_size + new - old;
+        if size > STORAGE_LIMIT {
+            return Err(rlua::Error::RuntimeError(format!(
+                "Game storage limit of {STORAGE_LIMIT} bytes reached"
+            )));
+        }
+
+        match value {
+            None => table.entries.remove(&key),
+            Some(value) => table.entries.insert(key, value),
+        };
+        self.size = size;
+        Ok(())
+    }
+}
+
+fn removed_error() -> rlua::Error {
+    rlua::Error::RuntimeError("This game storage table no longer exists".to_string())
+}
+
+/// Replaces all stored data, when starting or loading a game
+pub fn load(table: StorageTable) {
+    STORAGE.with(|s| {
+        let size = table.size();
+        *s.borrow_mut() = Storage { root: table, size };
+    });
+}
+
+/// Returns a copy of all stored data, for saving
+pub fn save() -> StorageTable {
+    STORAGE.with(|s| s.borrow().root.clone())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum StorageKey {
+    Int(i64),
+    Str(String),
+}
+
+impl StorageKey {
+    fn size(&self) -> usize {
+        match self {
+            StorageKey::Int(_) => ENTRY_SIZE,
+            StorageKey::Str(s) => ENTRY_SIZE + s.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum StorageValue {
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Str(String),
+    Table(StorageTable),
+}
+
+impl StorageValue {
+    fn size(&self) -> usize {
+        match self {
+            StorageValue::Str(s) => ENTRY_SIZE + s.len(),
+            StorageValue::Table(table) => ENTRY_SIZE + table.size(),
+            _ => ENTRY_SIZE,
+        }
+    }
+}
+
+/// A table of stored values.  This is saved as a list of key value pairs, so
+/// integer and string keys stay distinct.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(
+    from = "Vec<(StorageKey, StorageValue)>",
+    into = "Vec<(StorageKey, StorageValue)>"
+)]
+pub struct StorageTable {
+    entries: BTreeMap<StorageKey, StorageValue>,
+}
+
+impl StorageTable {
+    fn size(&self) -> usize {
+        self.entries.iter().map(|(k, v)| k.size() + v.size()).sum()
+    }
+
+    /// The number of levels of values below this table
+    fn depth(&self) -> usize {
+        let child = |value: &StorageValue| match value {
+            StorageValue::Table(table) => table.depth(),
+            _ => 0,
+        };
+
+        match self.entries.values().map(child).max() {
+            None => 0,
+            Some(depth) => depth + 1,
+        }
+    }
+}
+
+impl From<Vec<(StorageKey, StorageValue)>> for StorageTable {
+    fn from(entries: Vec<(StorageKey, StorageValue)>) -> StorageTable {
+        StorageTable {
+            entries: entries.into_iter().collect(),
+        }
+    }
+}
+
+impl From<StorageTable> for Vec<(StorageKey, StorageValue)> {
+    fn from(table: StorageTable) -> Vec<(StorageKey, StorageValue)> {
+        table.entries.into_iter().collect()
+    }
+}
+
+/// A table of data shared by all scripts, which persists for the whole campaign
+/// and is saved with the game.  Obtained with `game:storage()`.  It is used like
+/// a normal Lua table, and may hold booleans, numbers, strings, and nested
+/// tables, with string or integer keys.  Nested tables are also `ScriptStorage`
+/// objects, so changes to them are stored as well.  Assigning a table stores a
+/// copy of it.  `#` and `pairs` may be used as with a normal table.
+/// ## Examples
+/// ```lua
+///   storage = game:storage()
+///   if storage.bandits == nil then
+///     storage.bandits = { killed = 0, leaders = {} }
+///   end
+///   storage.bandits.killed = storage.bandits.killed + 1
+///   for key, value in pairs(storage.bandits) do
+///     game:log(key .. ": " .. tostring(value))
+///   end
+/// ```
+#[derive(Clone)]
+pub struct ScriptStorage {
+    path: Vec<StorageKey>,
+}
+
+impl ScriptStorage {
+    pub fn root() -> ScriptStorage {
+        ScriptStorage { path: Vec::new() }
+    }
+
+    fn child(&self, key: &StorageKey) -> ScriptStorage {
+        let mut path = self.path.clone();
+        path.push(key.clone());
+        ScriptStorage { path }
+    }
+
+    fn value_to_lua<'lua>(
+        &self,
+        lua: Context<'lua>,
+        key: &StorageKey,
+        value: &StorageValue,
+    ) -> Result<Value<'lua>> {
+        match value {
+            StorageValue::Bool(b) => Ok(Value::Boolean(*b)),
+            StorageValue::Int(i) => Ok(Value::Integer(*i)),
+            StorageValue::Num(n) => Ok(Value::Number(*n)),
+            StorageValue::Str(s) => s.as_str().to_lua(lua),
+            StorageValue::Table(_) => self.child(key).to_lua(lua),
+        }
+    }
+}
+
+impl UserData for ScriptStorage {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(MetaMethod::Index, |lua, storage, key: Value| {
+            let key = key_from_lua(key)?;
+            STORAGE.with(|s| {
+                let s = s.borrow();
+                match s.table(&storage.path)?.entries.get(&key) {
+                    None => Ok(Value::Nil),
+                    Some(value) => storage.value_to_lua(lua, &key, value),
+                }
+            })
+        });
+
+        methods.add_meta_method(
+            MetaMethod::NewIndex,
+            |_, storage, (key, value): (Value, Value)| {
+                let key = key_from_lua(key)?;
+                let value = value_from_lua(value, storage.path.len())?;
+                STORAGE.with(|s| s.borrow_mut().set(&storage.path, key, value))
+            },
+        );
+
+        methods.add_meta_method(MetaMethod::Len, |_, storage, ()| {
+            STORAGE.with(|s| {
+                let s = s.borrow();
+                let table = s.table(&storage.path)?;
+                let mut len = 0;
+                while table.entries.contains_key(&StorageKey::Int(len + 1)) {
+                    len += 1;
+                }
+                Ok(len)
+            })
+        });
+
+        methods.add_meta_method(MetaMethod::Pairs, |lua, storage, ()| {
+            let table = lua.create_table()?;
+            STORAGE.with(|s| {
+                let s = s.borrow();
+                for (key, value) in s.table(&storage.path)?.entries.iter() {
+                    let lua_key = match key {
+                        StorageKey::Int(i) => Value::Integer(*i),
+                        StorageKey::Str(s) => s.as_str().to_lua(lua)?,
+                    };
+                    table.set(lua_key, storage.value_to_lua(lua, key, value)?)?;
+                }
+                Ok(())
+            })?;
+
+            let next: rlua::Function = lua.globals().get("next")?;
+            Ok((next, table, Value::Nil))
+        });
+    }
+}
+
+fn key_from_lua(key: Value) -> Result<StorageKey> {
+    match key {
+        Value::Integer(i) => Ok(StorageKey::Int(i)),
+        Value::Number(n) if n.fract() == 0.0 => Ok(StorageKey::Int(n as i64)),
+        Value::String(s) => Ok(StorageKey::Str(s.to_str()?.to_string())),
+        _ => Err(rlua::Error::FromLuaConversionError {
+            from: key.type_name(),
+            to: "StorageKey",
+            message: Some("Game storage keys must be strings or integers".to_string()),
+        }),
+    }
+}
+
+fn depth_error() -> rlua::Error {
+    rlua::Error::RuntimeError(format!(
+        "Game storage tables may only be nested {MAX_DEPTH} deep"
+    ))
+}
+
+fn value_from_lua(value: Value, depth: usize) -> Result<Option<StorageValue>> {
+    if depth > MAX_DEPTH {
+        return Err(depth_error());
+    }
+
+    Ok(Some(match value {
+        Value::Nil => return Ok(None),
+        Value::Boolean(b) => StorageValue::Bool(b),
+        Value::Integer(i) => StorageValue::Int(i),
+        Value::Number(n) if !n.is_finite() => {
+            return Err(rlua::Error::RuntimeError(format!(
+                "Unable to store {n}, only finite numbers may be stored"
+            )))
+        }
+        Value::Number(n) => StorageValue::Num(n),
+        Value::String(s) => StorageValue::Str(s.to_str()?.to_string()),
+        Value::Table(table) => {
+            let mut entries = BTreeMap::new();
+            for pair in table.pairs::<Value, Value>() {
+                let (key, value) = pair?;
+                if let Some(value) = value_from_lua(value, depth + 1)? {
+                    entries.insert(key_from_lua(key)?, value);
+                }
+            }
+            StorageValue::Table(StorageTable { entries })
+        }
+        Value::UserData(ref data) if data.is::<ScriptStorage>() => {
+            let storage = data.borrow::<ScriptStorage>()?;
+            let table = STORAGE.with(|s| s.borrow().table(&storage.path).cloned())?;
+            if depth + table.depth() > MAX_DEPTH {
+                return Err(depth_error());
+            }
+            StorageValue::Table(table)
+        }
+        _ => {
+            return Err(rlua::Error::FromLuaConversionError {
+                from: value.type_name(),
+                to: "StorageValue",
+                message: Some(
+                    "Only booleans, numbers, strings, and tables may be stored".to_string(),
+                ),
+            })
+        }
+    }))
+}
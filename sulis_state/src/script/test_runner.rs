@@ -0,0 +1,383 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Runs Lua unit tests headlessly.  Test scripts are any scripts with an ID
+//! ending in `_test`, such as `scripts/abilities/fireball_test.lua`.  They are
+//! not loaded while playing.  Each global function starting with `test_` is
+//! run against a new game built from the script's `fixture` table:
+//!
+//! ```lua
+//! fixture = {
+//!   player = "hero",                 -- optional, defaults to the runner's player
+//!   party = { "companion" },
+//!   area = "forest01", x = 10, y = 12,
+//!   actors = { { id = "goblin", x = 14, y = 12, faction = "Hostile" } },
+//! }
+//! ```
+//!
+//! Tests may use the `player`, `party`, and `actors` globals, which hold the
+//! party and the spawned fixture actors in order, along with the `assert_eq`,
+//! `assert_ne`, `assert_true`, `assert_false`, `assert_nil`, `assert_not_nil`,
+//! `assert_near`, and `fail` helpers.  Shared scripts can be tested by pulling
+//! them in with `--INCLUDE`.
+
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind};
+use std::rc::Rc;
+
+use rlua::{Table, Value};
+
+use crate::script::{ScriptEntity, ScriptKind, ScriptState};
+use crate::{EntityState, GameState, Location, Simulation};
+use sulis_core::util::Point;
+use sulis_module::{Faction, Module, Time};
+
+/// The suffix of the IDs of test scripts
+pub const TEST_SUFFIX: &str = "_test";
+
+/// The prefix of the names of test functions
+const TEST_PREFIX: &str = "test_";
+
+const HELPERS_NAME: &str = "test_helpers";
+
+const HELPERS: &str = r#"
+local function format(value)
+  if type(value) == "string" then return string.format("%q", value) end
+  return tostring(value)
+end
+
+local function equal(a, b)
+  if a == b then return true end
+  if type(a) ~= "table" or type(b) ~= "table" then return false end
+  for k, v in pairs(a) do
+    if not equal(v, b[k]) then return false end
+  end
+  for k in pairs(b) do
+    if a[k] == nil then return false end
+  end
+  return true
+end
+
+local function check(passed, message, reason)
+  if passed then return end
+  if message then reason = message .. ": " .. reason end
+  error(reason, 3)
+end
+
+function assert_eq(actual, expected, message)
+  check(equal(actual, expected), message,
+    "expected " .. format(expected) .. " but was " .. format(actual))
+end
+
+function assert_ne(actual, unexpected, message)
+  check(not equal(actual, unexpected), message, "expected a value other than " .. format(unexpected))
+end
+
+function assert_true(value, message)
+  check(value == true, message, "expected true but was " .. format(value))
+end
+
+function assert_false(value, message)
+  check(value == false, message, "expected false but was " .. format(value))
+end
+
+function assert_nil(value, message)
+  check(value == nil, message, "expected nil but was " .. format(value))
+end
+
+function assert_not_nil(value, message)
+  check(value ~= nil, message, "expected a value but was nil")
+end
+
+function assert_near(actual, expected, tolerance, message)
+  tolerance = tolerance or 0.001
+  check(type(actual) == "number" and math.abs(actual - expected) <= tolerance, message,
+    "expected " .. format(expected) .. " +/- " .. format(tolerance) .. " but was " .. format(actual))
+end
+
+function fail(message)
+  error(message or "failed", 2)
+end
+"#;
+
+/// The outcome of a single test function
+pub struct TestResult {
+    pub name: String,
+    pub error: Option<String>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Default)]
+struct Fixture {
+    player: Option<String>,
+    party: Vec<String>,
+    area: Option<String>,
+    location: Option<Point>,
+    actors: Vec<FixtureActor>,
+}
+
+struct FixtureActor {
+    id: String,
+    x: i32,
+    y: i32,
+    faction: Option<String>,
+}
+
+/// Returns whether the script with the specified ID is a test script
+pub fn is_test_script(id: &str) -> bool {
+    id.ends_with(TEST_SUFFIX)
+}
+
+/// Returns the IDs of all test scripts in the loaded module, sorted
+pub fn test_scripts() -> Vec<String> {
+    let mut ids: Vec<String> = Module::all_scripts()
+        .into_iter()
+        .filter(|id| is_test_script(id))
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Runs each test function in the specified script, in alphabetical order.
+/// `default_player` is the actor used as the player when the fixture does not
+/// specify one.  Returns an error if the script or its fixture could not be
+/// loaded.
+pub fn run(id: &str, default_player: &str, seed: Option<u64>) -> Result<Vec<TestResult>, Error> {
+    let script = Module::script(id).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Test script '{id}' not found"),
+        )
+    })?;
+
+    let (names, fixture) = {
+        let state = load_state(id, &script)?;
+        state
+            .lua
+            .context(|lua| -> rlua::Result<_> {
+                let mut names = Vec::new();
+                for pair in lua.globals().pairs::<Value, Value>() {
+                    if let (Value::String(name), Value::Function(_)) = pair? {
+                        let name = name.to_str()?;
+                        if name.starts_with(TEST_PREFIX) {
+                            names.push(name.to_string());
+                        }
+                    }
+                }
+                names.sort();
+
+                let fixture = match lua.globals().get::<_, Option<Table>>("fixture")? {
+                    None => Fixture::default(),
+                    Some(table) => Fixture::from_table(table)?,
+                };
+                Ok((names, fixture))
+            })
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Error reading '{id}': {e}")))?
+    };
+
+    let mut results = Vec::new();
+    for name in names {
+        let error = run_test(id, &script, &name, &fixture, default_player, seed).err();
+        results.push(TestResult { name, error });
+    }
+
+    Ok(results)
+}
+
+fn run_test(
+    id: &str,
+    script: &str,
+    name: &str,
+    fixture: &Fixture,
+    default_player: &str,
+    seed: Option<u64>,
+) -> Result<(), String> {
+    let actors = fixture.setup(default_player, seed)?;
+
+    let state = load_state(id, script).map_err(|e| e.to_string())?;
+    state
+        .lua
+        .context(|lua| -> rlua::Result<()> {
+            let globals = lua.globals();
+            let party = GameState::party();
+            globals.set("player", ScriptEntity::from(&party[0]))?;
+            globals.set("party", table_of(lua, &party)?)?;
+            globals.set("actors", table_of(lua, &actors)?)?;
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+
+    state
+        .exec_func::<_, ()>(name, (), ScriptKind::Trigger, false)
+        .map_err(|e| match e {
+            rlua::Error::CallbackError { cause, traceback } => {
+                // errors in nested calls, such as from require, are wrapped once
+                // for each call
+                let mut cause = cause.as_ref();
+                while let rlua::Error::CallbackError { cause: inner, .. } = cause {
+                    cause = inner.as_ref();
+                }
+                format!("{cause}\n{}", trim_traceback(&traceback))
+            }
+            e => trim_traceback(&e.to_string()),
+        })
+}
+
+/// Removes the frames of the assertion helpers and of Rust functions from
+/// a Lua traceback
+fn trim_traceback(traceback: &str) -> String {
+    traceback
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.starts_with("[C]") && !line.contains(HELPERS_NAME)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn load_state(id: &str, script: &str) -> Result<ScriptState, Error> {
+    let mut state = ScriptState::default();
+    state
+        .load(id, script)
+        .and_then(|_| {
+            state
+                .lua
+                .context(|lua| lua.load(HELPERS).set_name(HELPERS_NAME)?.exec())
+        })
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Error loading '{id}': {e}")))?;
+    Ok(state)
+}
+
+fn table_of<'lua>(
+    lua: rlua::Context<'lua>,
+    entities: &[Rc<RefCell<EntityState>>],
+) -> rlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    for (index, entity) in entities.iter().enumerate() {
+        table.set(index + 1, ScriptEntity::from(entity))?;
+    }
+    Ok(table)
+}
+
+impl Fixture {
+    fn from_table(table: Table) -> rlua::Result<Fixture> {
+        let location = match (table.get("x")?, table.get("y")?) {
+            (Some(x), Some(y)) => Some(Point::new(x, y)),
+            _ => None,
+        };
+
+        let mut actors = Vec::new();
+        if let Some(list) = table.get::<_, Option<Table>>("actors")? {
+            for actor in list.sequence_values::<Table>() {
+                let actor = actor?;
+                actors.push(FixtureActor {
+                    id: actor.get("id")?,
+                    x: actor.get("x")?,
+                    y: actor.get("y")?,
+                    faction: actor.get("faction")?,
+                });
+            }
+        }
+
+        Ok(Fixture {
+            player: table.get("player")?,
+            party: table
+                .get::<_, Option<Vec<String>>>("party")?
+                .unwrap_or_default(),
+            area: table.get("area")?,
+            location,
+            actors,
+        })
+    }
+
+    /// Starts a new game with this fixture, returning the spawned actors
+    fn setup(
+        &self,
+        default_player: &str,
+        seed: Option<u64>,
+    ) -> Result<Vec<Rc<RefCell<EntityState>>>, String> {
+        let player_id = self.player.as_deref().unwrap_or(default_player);
+        let player = find_actor(player_id)?;
+        let party = self
+            .party
+            .iter()
+            .map(|id| find_actor(id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sim = Simulation::new_campaign(player, party, seed).map_err(|e| e.to_string())?;
+        sim.tick();
+
+        if self.area.is_some() || self.location.is_some() {
+            let time = Time {
+                day: 0,
+                hour: 0,
+                round: 0,
+                millis: 0,
+            };
+            GameState::transition_to(self.area.as_deref(), self.location, Point::default(), time);
+            if let Some(area) = &self.area {
+                if &GameState::area_state().borrow().area.area.id != area {
+                    return Err(format!("Unable to move the party to area '{area}'"));
+                }
+            }
+            sim.tick();
+        }
+
+        self.actors.iter().map(spawn).collect()
+    }
+}
+
+fn find_actor(id: &str) -> Result<Rc<sulis_module::Actor>, String> {
+    Module::actor(id).ok_or_else(|| format!("Fixture actor '{id}' not found"))
+}
+
+fn spawn(fixture: &FixtureActor) -> Result<Rc<RefCell<EntityState>>, String> {
+    let actor = find_actor(&fixture.id)?;
+    let area_state = GameState::area_state();
+    if !area_state
+        .borrow()
+        .is_passable_size(&actor.race.size, fixture.x, fixture.y)
+    {
+        return Err(format!(
+            "Unable to spawn fixture actor '{}' at {},{}: not passable",
+            fixture.id, fixture.x, fixture.y
+        ));
+    }
+
+    let location = Location::new(fixture.x, fixture.y, &area_state.borrow().area.area);
+    let index = area_state
+        .borrow_mut()
+        .add_actor(actor, location, None, false, None)
+        .map_err(|e| format!("Unable to spawn fixture actor '{}': {e}", fixture.id))?;
+    let entity = GameState::turn_manager().borrow().entity(index);
+
+    if let Some(faction) = &fixture.faction {
+        let faction = Faction::option_from_str(faction)
+            .ok_or_else(|| format!("Invalid fixture faction '{faction}'"))?;
+        entity.borrow_mut().actor.set_faction(faction);
+    }
+
+    let mgr = GameState::turn_manager();
+    mgr.borrow_mut()
+        .check_ai_activation(&entity, &mut area_state.borrow_mut());
+    Ok(entity)
+}
@@ -0,0 +1,317 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! A headless runner for the game state.  This drives `GameState::update` in fixed
+//! ticks without a window, renderer, or audio device, so that campaigns can be smoke
+//! tested and combat simulated from Rust code, Lua scripts, or the `sulis-sim` binary.
+//!
+//! UI callbacks that only modify game state (flags, coins, items, quests, scripts) are
+//! applied with `trigger_state::activate`, as in the UI.  Callbacks that need a window, such as conversations or merchants,
+//! are recorded and may be inspected with `unhandled_triggers`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Error;
+use std::rc::Rc;
+
+use sulis_core::config::Config;
+use sulis_core::io::Audio;
+use sulis_core::resource::ResourceSet;
+use sulis_module::{Actor, Module, OnTrigger};
+
+use crate::script::{entity_with_id, script_callback, ScriptEntity};
+use crate::{trigger_state, EntityState, GameState, SaveState, Script, UICallback};
+
+/// The default length of one tick, matching a 60 frames per second display
+pub const DEFAULT_TICK_MILLIS: u32 = 16;
+
+/// What the simulation does when a party member has the active turn in combat
+#[derive(Debug, Clone)]
+pub enum PartyTurn {
+    /// Nothing is done automatically; the caller is responsible for ending the turn
+    Manual,
+
+    /// The turn is ended immediately
+    EndTurn,
+
+    /// The specified script function is called once with the active party member
+    /// as its argument, and the turn is ended once all animations have completed
+    Script { id: String, func: String },
+}
+
+pub struct Simulation {
+    tick_millis: u32,
+    ticks: u64,
+    party_turn: PartyTurn,
+    turn_handled: Option<usize>,
+    unhandled_triggers: Vec<OnTrigger>,
+}
+
+impl Simulation {
+    /// Reads the base data and module resources from the specified directories.  The
+    /// first directory is the root data directory, the second the campaign, and any
+    /// further directories are mods.
+    pub fn load_resources(dirs: Vec<String>) -> Result<(), Error> {
+        let yaml = ResourceSet::load_resources(dirs.clone())?;
+        Module::load_resources(yaml, dirs)
+    }
+
+    /// Creates a new game with the specified player character and party.  If `seed`
+    /// is specified, all game rolls will be reproducible between runs.
+    pub fn new_campaign(
+        pc_actor: Rc<Actor>,
+        party_actors: Vec<Rc<Actor>>,
+        seed: Option<u64>,
+    ) -> Result<Simulation, Error> {
+        if seed.is_some() {
+            let mut config = Config::get_clone();
+            config.debug.rand_seed = seed;
+            Config::set(config);
+        }
+
+        GameState::init(pc_actor, party_actors, HashMap::new())?;
+        Ok(Simulation::new())
+    }
+
+    /// Loads the specified save and creates a simulation running from it
+    pub fn load(save_state: SaveState) -> Result<Simulation, Error> {
+        GameState::load(save_state)?;
+        Ok(Simulation::new())
+    }
+
+    fn new() -> Simulation {
+        Simulation {
+            tick_millis: DEFAULT_TICK_MILLIS,
+            ticks: 0,
+            party_turn: PartyTurn::EndTurn,
+            turn_handled: None,
+            unhandled_triggers: Vec::new(),
+        }
+    }
+
+    pub fn set_tick_millis(&mut self, millis: u32) {
+        self.tick_millis = millis;
+    }
+
+    pub fn set_party_turn(&mut self, party_turn: PartyTurn) {
+        self.party_turn = party_turn;
+    }
+
+    /// The total number of ticks that have been run
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Any UI only callbacks that could not be applied without a window, in the order
+    /// they were received
+    pub fn unhandled_triggers(&self) -> &[OnTrigger] {
+        &self.unhandled_triggers
+    }
+
+    /// Advances the game state by one tick
+    pub fn tick(&mut self) {
+        let ui_cb = GameState::update(self.tick_millis);
+        Audio::clear_queue();
+
+        if let Some(cb) = ui_cb {
+            self.handle_ui_callback(cb);
+        }
+
+        self.handle_party_turn();
+        self.ticks += 1;
+    }
+
+    pub fn run_ticks(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Runs ticks until `done` returns true or `max_ticks` have been run.  Returns
+    /// whether `done` was satisfied.
+    pub fn run_until<F: FnMut(&Simulation) -> bool>(
+        &mut self,
+        max_ticks: u64,
+        mut done: F,
+    ) -> bool {
+        for _ in 0..max_ticks {
+            if done(self) {
+                return true;
+            }
+            self.tick();
+        }
+
+        done(self)
+    }
+
+    /// Runs ticks until there are no blocking animations remaining, up to `max_ticks`
+    pub fn run_until_idle(&mut self, max_ticks: u64) -> bool {
+        self.run_until(max_ticks, |_| !GameState::has_any_blocking_animations())
+    }
+
+    /// Returns true if every member of the party is dead
+    pub fn is_party_defeated(&self) -> bool {
+        GameState::party()
+            .iter()
+            .all(|e| e.borrow().actor.is_dead())
+    }
+
+    pub fn entity(&self, unique_id: &str) -> Option<Rc<RefCell<EntityState>>> {
+        entity_with_id(unique_id.to_string())
+    }
+
+    /// Starts moving the entity towards the specified point.  Returns false if no
+    /// path could be found.
+    pub fn move_to(&mut self, entity: &Rc<RefCell<EntityState>>, x: f32, y: f32) -> bool {
+        let dest = GameState::get_point_dest(&entity.borrow(), x, y);
+        GameState::move_towards_dest(entity, &[], dest, None)
+    }
+
+    /// Starts a weapon attack from the entity against the target.  Returns false if
+    /// the target is not currently attackable.
+    pub fn attack(
+        &mut self,
+        entity: &Rc<RefCell<EntityState>>,
+        target: &Rc<RefCell<EntityState>>,
+    ) -> bool {
+        if !entity.borrow().can_attack(&target.borrow()) {
+            return false;
+        }
+
+        EntityState::attack(entity, target, None, true);
+        true
+    }
+
+    /// Activates the specified ability owned by the entity, in the same way as the
+    /// abilities bar.  If the ability creates a targeter, use `select_target` to
+    /// choose a target.  Returns false if the ability could not be activated.
+    pub fn activate_ability(
+        &mut self,
+        entity: &Rc<RefCell<EntityState>>,
+        ability_id: &str,
+    ) -> bool {
+        let ability = match Module::ability(ability_id) {
+            None => {
+                warn!("No ability '{}' found to activate", ability_id);
+                return false;
+            }
+            Some(ability) => ability,
+        };
+
+        if !entity.borrow().actor.can_activate(&ability.id) {
+            return false;
+        }
+
+        let index = entity.borrow().index();
+        Script::ability_on_activate(index, "on_activate".to_string(), &ability);
+        true
+    }
+
+    /// Selects the specified point with the current targeter, as if the player had
+    /// clicked on it.  Returns false if there is no targeter or the point is invalid.
+    pub fn select_target(&mut self, x: i32, y: i32) -> bool {
+        let targeter = match GameState::area_state().borrow().targeter() {
+            None => return false,
+            Some(targeter) => targeter,
+        };
+
+        let mut targeter = targeter.borrow_mut();
+        targeter.on_mouse_move(x, y);
+        if !targeter.is_valid_to_activate() {
+            return false;
+        }
+
+        targeter.on_activate();
+        true
+    }
+
+    /// Ends the current turn if it belongs to a party member
+    pub fn end_turn(&mut self) {
+        if !GameState::is_pc_current() {
+            return;
+        }
+
+        let mgr = GameState::turn_manager();
+        let cbs = mgr.borrow_mut().next();
+        script_callback::fire_round_elapsed(cbs);
+    }
+
+    /// Calls the specified script function with the player as the argument
+    pub fn run_script(&mut self, id: &str, func: &str) {
+        Script::trigger(id, func, ScriptEntity::from(&GameState::player()));
+    }
+
+    fn handle_party_turn(&mut self) {
+        let current = match GameState::turn_manager().borrow().current() {
+            None => {
+                self.turn_handled = None;
+                return;
+            }
+            Some(current) => current,
+        };
+
+        if !current.borrow().is_party_member()
+            || current
+                .borrow()
+                .is_party_ai_active(&GameState::turn_manager().borrow())
+            || GameState::is_modal_locked()
+        {
+            self.turn_handled = None;
+            return;
+        }
+
+        if GameState::has_any_blocking_animations() {
+            return;
+        }
+
+        let index = current.borrow().index();
+        match self.party_turn.clone() {
+            PartyTurn::Manual => (),
+            PartyTurn::EndTurn => self.end_turn(),
+            PartyTurn::Script { id, func } => {
+                if self.turn_handled == Some(index) {
+                    self.turn_handled = None;
+                    self.end_turn();
+                } else {
+                    self.turn_handled = Some(index);
+                    Script::trigger(&id, &func, ScriptEntity::from(&current));
+                }
+            }
+        }
+    }
+
+    fn handle_ui_callback(&mut self, cb: UICallback) {
+        let pc = &cb.parent;
+        let target = &cb.target;
+
+        use sulis_module::OnTrigger::*;
+        for trigger in cb.on_trigger {
+            if trigger_state::activate(&trigger, pc, target) {
+                continue;
+            }
+
+            match trigger {
+                // purely presentational, nothing to do without a window
+                BlockUI(_) | CheckEndTurn | SayLine(_) | ScrollView(..) | ScreenShake
+                | FadeOutIn => (),
+                other => {
+                    debug!("Simulation skipping UI trigger {:?}", other);
+                    self.unhandled_triggers.push(other);
+                }
+            }
+        }
+    }
+}
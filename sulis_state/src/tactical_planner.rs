@@ -0,0 +1,392 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Tactical searches that are too expensive to run from AI scripts.  Finds
+//! the best square for an entity to move to, and the best placement of an
+//! area of effect targeter.  Exposed to Lua as `ScriptEntity::best_position`
+//! and `ScriptAbility::best_aoe_target`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use sulis_core::util::Point;
+use sulis_module::area::LocationChecker;
+
+use crate::los_calculator::has_visibility_from;
+use crate::path_finder::StateLocationChecker;
+use crate::script::AreaTargeter;
+use crate::{center, dist, is_threat, EntityState, GameState, Locatable};
+
+/// The maximum number of squares searched away from the entity's position
+const MAX_SEARCH_MOVES: u32 = 20;
+
+/// How much each factor counts towards the score of a position.  Each is
+/// multiplied by the number of targets the factor applies to, or by the
+/// number of squares moved for `moves`
+#[derive(Clone, Copy, Debug)]
+pub struct PositionWeights {
+    pub attack: f32,
+    pub flank: f32,
+    pub threat: f32,
+    pub los: f32,
+    pub moves: f32,
+}
+
+impl Default for PositionWeights {
+    fn default() -> Self {
+        PositionWeights {
+            attack: 1.0,
+            flank: 2.0,
+            threat: -1.5,
+            los: 0.5,
+            moves: -0.1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PositionOptions {
+    /// Score against only this target, rather than all visible hostiles
+    pub target: Option<Rc<RefCell<EntityState>>>,
+    pub max_moves: Option<u32>,
+    pub weights: PositionWeights,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PositionScore {
+    pub x: i32,
+    pub y: i32,
+    pub score: f32,
+    pub moves: u32,
+    pub attackable: u32,
+    pub flanking: u32,
+    pub threatened_by: u32,
+    pub visible: u32,
+}
+
+/// The footprint the parent would have if standing at a candidate position
+struct Footprint {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+impl Locatable for Footprint {
+    fn size(&self) -> (f32, f32) {
+        (self.w as f32, self.h as f32)
+    }
+
+    fn pos(&self) -> (f32, f32) {
+        (self.x as f32, self.y as f32)
+    }
+}
+
+/// Scores every square the `parent` can reach this turn, including its
+/// current position, and returns the best one.  A position scores for
+/// targets it can attack, targets it flanks together with a friendly
+/// according to the parent's `flanking_angle`, targets in line of sight,
+/// hostiles threatening it with melee weapons, and the squares moved.  Only
+/// hostiles that are not hidden and that the parent currently has line of
+/// sight to are considered.  Returns `None` if the parent is not in an area.
+pub fn best_position(
+    parent: &Rc<RefCell<EntityState>>,
+    opts: &PositionOptions,
+) -> Option<PositionScore> {
+    let area = GameState::get_area_state(&parent.borrow().location.area_id)?;
+    let area = area.borrow();
+    let mgr = GameState::turn_manager();
+    let parent = parent.borrow();
+
+    let mut hostiles = Vec::new();
+    let mut friendlies = Vec::new();
+    let mut to_ignore = vec![parent.index()];
+    for index in area.entity_iter() {
+        if *index == parent.index() {
+            continue;
+        }
+
+        let entity = mgr.borrow().entity(*index);
+        let other = entity.borrow();
+        if other.actor.is_dead() {
+            continue;
+        }
+
+        if parent.is_hostile(&other) {
+            // only hostiles the parent can currently see are considered
+            if !other.actor.stats.hidden && area.has_visibility(&parent, &other) {
+                hostiles.push(Rc::clone(&entity));
+            }
+        } else if parent.is_friendly(&other) {
+            // allied members of the same group may be walked through, as when moving
+            if parent.ai_group() == other.ai_group() {
+                to_ignore.push(*index);
+            }
+            friendlies.push(Rc::clone(&entity));
+        }
+    }
+
+    let targets = match &opts.target {
+        None => hostiles.clone(),
+        Some(target) => vec![Rc::clone(target)],
+    };
+
+    let mut max_moves = opts
+        .max_moves
+        .unwrap_or(MAX_SEARCH_MOVES)
+        .min(MAX_SEARCH_MOVES);
+    if parent.actor.stats.move_disabled {
+        max_moves = 0;
+    } else if GameState::is_combat_active() {
+        max_moves = max_moves.min(parent.actor.ap() / parent.actor.get_move_ap_cost(1));
+    }
+
+    // squares claimed by other members of the group are left to them
+    let claimed: Vec<Point> = match parent.ai_group() {
+        None => Vec::new(),
+        Some(group) => match mgr.borrow().ai_blackboard(group) {
+            None => Vec::new(),
+            Some(blackboard) => blackboard.claimed_squares_except(parent.index()),
+        },
+    };
+
+    let checker = StateLocationChecker::new(&area, &parent, &to_ignore, parent.is_party_member());
+    let width = area.area.width;
+    let vis_grid = area.props().entire_vis_grid();
+    let attack_dist = parent.actor.stats.attack_distance();
+    let flanking_angle = parent.actor.stats.flanking_angle as f32;
+
+    let start = Point::new(parent.location.x, parent.location.y);
+    let mut moves = vec![None; (width * area.area.height) as usize];
+    moves[(start.x + start.y * width) as usize] = Some(0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+
+    let mut best: Option<PositionScore> = None;
+    while let Some(p) = queue.pop_front() {
+        let cur_moves = moves[(p.x + p.y * width) as usize].unwrap_or(0);
+
+        if cur_moves < max_moves {
+            for (dx, dy) in [(0, -1), (0, 1), (1, 0), (-1, 0)] {
+                let (x, y) = (p.x + dx, p.y + dy);
+                if !area.area.area.coords_valid(x, y) {
+                    continue;
+                }
+
+                let index = (x + y * width) as usize;
+                if moves[index].is_some() || !checker.passable(x, y) {
+                    continue;
+                }
+
+                moves[index] = Some(cur_moves + 1);
+                queue.push_back(Point::new(x, y));
+            }
+        }
+
+        // entities can move through friends but not stop on them
+        if cur_moves > 0 && checker.in_friend_space(p.x + p.y * width) {
+            continue;
+        }
+
+        if claimed.contains(&p) {
+            continue;
+        }
+
+        let footprint = Footprint {
+            x: p.x,
+            y: p.y,
+            w: parent.size.width,
+            h: parent.size.height,
+        };
+        let (cx, cy) = center(&footprint);
+
+        let mut score = PositionScore {
+            x: p.x,
+            y: p.y,
+            score: 0.0,
+            moves: cur_moves,
+            attackable: 0,
+            flanking: 0,
+            threatened_by: 0,
+            visible: 0,
+        };
+
+        for target in targets.iter() {
+            let target = target.borrow();
+            if has_visibility_from(&area.area, vis_grid, cx as i32, cy as i32, &target) {
+                score.visible += 1;
+            }
+
+            if dist(&footprint, &*target) > attack_dist {
+                continue;
+            }
+            score.attackable += 1;
+
+            if is_flanking_from(&footprint, &target, &friendlies, flanking_angle) {
+                score.flanking += 1;
+            }
+        }
+
+        for hostile in hostiles.iter() {
+            let hostile = hostile.borrow();
+            let stats = &hostile.actor.stats;
+            if !stats.attack_is_melee() || stats.attack_disabled {
+                continue;
+            }
+
+            if dist(&*hostile, &footprint) <= stats.attack_distance() {
+                score.threatened_by += 1;
+            }
+        }
+
+        let w = &opts.weights;
+        score.score = w.attack * score.attackable as f32
+            + w.flank * score.flanking as f32
+            + w.threat * score.threatened_by as f32
+            + w.los * score.visible as f32
+            + w.moves * cur_moves as f32;
+
+        // positions are visited in order of moves, so ties favor shorter paths
+        if best.is_none_or(|best| score.score > best.score) {
+            best = Some(score);
+        }
+    }
+
+    best
+}
+
+/// Returns whether attacking the `target` from the `footprint` would flank it,
+/// with one of the `friendlies` threatening it from the opposite side
+fn is_flanking_from(
+    footprint: &Footprint,
+    target: &EntityState,
+    friendlies: &[Rc<RefCell<EntityState>>],
+    flanking_angle: f32,
+) -> bool {
+    if target.actor.stats.flanked_immunity {
+        return false;
+    }
+
+    let p_target = center(target);
+    let p_parent = center(footprint);
+    let p1 = (p_target.0 - p_parent.0, p_target.1 - p_parent.1);
+
+    for friendly in friendlies.iter() {
+        let friendly = friendly.borrow();
+        if !is_threat(&friendly, target) {
+            continue;
+        }
+
+        let p_other = center(&*friendly);
+        let p2 = (p_target.0 - p_other.0, p_target.1 - p_other.1);
+
+        let cos_angle = (p1.0 * p2.0 + p1.1 * p2.1) / (p1.0.hypot(p1.1) * p2.0.hypot(p2.1));
+        let angle = cos_angle.clamp(-1.0, 1.0).acos().to_degrees();
+        if angle > flanking_angle {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// How much each affected entity counts towards the score of a targeter placement
+#[derive(Clone, Copy, Debug)]
+pub struct AoeWeights {
+    pub hostile: f32,
+    pub friendly: f32,
+}
+
+impl AoeWeights {
+    /// Weights for harmful effects, which should avoid hitting friendlies
+    pub fn harmful() -> AoeWeights {
+        AoeWeights {
+            hostile: 1.0,
+            friendly: -2.0,
+        }
+    }
+
+    /// Weights for beneficial effects, such as heals and buffs
+    pub fn beneficial() -> AoeWeights {
+        AoeWeights {
+            hostile: -1.0,
+            friendly: 1.0,
+        }
+    }
+}
+
+pub struct AoeOptions {
+    pub weights: AoeWeights,
+    /// Placements must score strictly more than this to be returned
+    pub min_score: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AoeScore {
+    pub x: i32,
+    pub y: i32,
+    pub score: f32,
+    pub hostiles: u32,
+    pub friendlies: u32,
+}
+
+/// Tries each of the `targeter`'s candidate positions and returns the one
+/// whose affected entities score highest.  The targeter is left at the best
+/// position, so it may be activated immediately.  Returns `None` if no valid
+/// position scored more than the minimum.
+pub fn best_aoe_target(targeter: &mut AreaTargeter, opts: &AoeOptions) -> Option<AoeScore> {
+    let parent = Rc::clone(targeter.parent());
+
+    let mut best: Option<AoeScore> = None;
+    for p in targeter.candidate_positions() {
+        targeter.on_mouse_move(p.x, p.y);
+        if !targeter.is_valid_to_activate() {
+            continue;
+        }
+
+        let mut score = AoeScore {
+            x: p.x,
+            y: p.y,
+            score: 0.0,
+            hostiles: 0,
+            friendlies: 0,
+        };
+
+        let parent = parent.borrow();
+        for entity in targeter.cur_affected().iter() {
+            let entity = entity.borrow();
+            if parent.is_hostile(&entity) {
+                score.hostiles += 1;
+            } else if parent.is_friendly(&entity) {
+                score.friendlies += 1;
+            }
+        }
+
+        score.score = opts.weights.hostile * score.hostiles as f32
+            + opts.weights.friendly * score.friendlies as f32;
+
+        if score.score > opts.min_score && best.is_none_or(|best| score.score > best.score) {
+            best = Some(score);
+        }
+    }
+
+    if let Some(best) = best {
+        targeter.on_mouse_move(best.x, best.y);
+    }
+
+    best
+}
@@ -17,6 +17,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::script::ScriptCallback;
 use crate::{AreaState, EntityState, GameState, Location, TurnManager};
 use sulis_core::{util::Point};
 use sulis_module::{
@@ -74,6 +75,12 @@ pub(crate) fn transition_to(area_id: Option<&str>, p: Option<Point>, offset: Poi
 
     transition_party(&mgr, &area, p, &party);
 
+    let area_id = area.borrow().area.area.id.clone();
+    for entity in party.iter() {
+        let cbs = entity.borrow().callbacks(&mgr.borrow());
+        cbs.iter().for_each(|cb| cb.on_area_entered(&area_id));
+    }
+
     let pc = GameState::player();
     area.borrow_mut().push_scroll_to_callback(Rc::clone(&pc));
 
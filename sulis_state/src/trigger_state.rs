@@ -0,0 +1,156 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Applies the `OnTrigger` kinds that only modify the game state.  These are
+//! shared by the UI and the headless `Simulation`, which each handle the
+//! remaining kinds themselves.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sulis_module::{on_trigger::QuestStateData, Actor, ItemState, Module, OnTrigger};
+
+use crate::script::{entity_with_id, ScriptEntity};
+use crate::{EntityState, GameState, Script};
+
+/// Applies the `trigger` if it only modifies the game state.  Returns false
+/// if the trigger needs a window, and so was not applied.
+pub fn activate(
+    trigger: &OnTrigger,
+    pc: &Rc<RefCell<EntityState>>,
+    target: &Rc<RefCell<EntityState>>,
+) -> bool {
+    use sulis_module::OnTrigger::*;
+    match trigger {
+        PlayerAbility(ref ability_id) => add_player_ability(pc, ability_id),
+        PlayerCoins(amount) => {
+            GameState::add_party_coins(*amount);
+        }
+        PartyMember(ref id) => match entity_with_id(id.to_string()) {
+            None => warn!(
+                "Attempted to add party member '{}' but entity does not exist",
+                id
+            ),
+            Some(entity) => GameState::add_party_member(entity, true),
+        },
+        PartyItem(ref id) => {
+            let stash = GameState::party_stash();
+            match ItemState::from(id) {
+                None => warn!("Attempted to add item '{}' but it does not exist", id),
+                Some(item) => {
+                    stash.borrow_mut().pick_up(1, item);
+                }
+            }
+        }
+        TargetNumFlag(ref data) => {
+            target.borrow_mut().add_num_flag(&data.flag, data.val);
+        }
+        PlayerNumFlag(ref data) => {
+            pc.borrow_mut().add_num_flag(&data.flag, data.val);
+        }
+        NotTargetNumFlag(ref data) => {
+            target.borrow_mut().clear_custom_flag(&data.flag);
+        }
+        NotPlayerNumFlag(ref data) => {
+            pc.borrow_mut().clear_custom_flag(&data.flag);
+        }
+        NotTargetFlag(ref flag) => {
+            target.borrow_mut().clear_custom_flag(flag);
+        }
+        NotPlayerFlag(ref flag) => {
+            pc.borrow_mut().clear_custom_flag(flag);
+        }
+        TargetFlag(ref flag) => {
+            target.borrow_mut().set_custom_flag(flag, "true");
+        }
+        PlayerFlag(ref flag) => {
+            pc.borrow_mut().set_custom_flag(flag, "true");
+        }
+        FireScript(ref script) => fire_script(&script.id, &script.func, pc, target),
+        QuestState(ref data) => {
+            verify_quest(data);
+
+            if let Some(ref entry) = data.entry {
+                GameState::set_quest_entry_state(
+                    data.quest.to_string(),
+                    entry.to_string(),
+                    data.state,
+                );
+            } else {
+                GameState::set_quest_state(data.quest.to_string(), data.state);
+            }
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+/// Calls the specified script function with the `parent` and `target`
+pub fn fire_script(
+    script_id: &str,
+    func: &str,
+    parent: &Rc<RefCell<EntityState>>,
+    target: &Rc<RefCell<EntityState>>,
+) {
+    Script::trigger(
+        script_id,
+        func,
+        (ScriptEntity::from(parent), ScriptEntity::from(target)),
+    );
+}
+
+fn add_player_ability(pc: &Rc<RefCell<EntityState>>, ability_id: &str) {
+    let ability = match Module::ability(ability_id) {
+        None => {
+            warn!(
+                "No ability found for '{}' when activating on_trigger",
+                ability_id
+            );
+            return;
+        }
+        Some(ability) => ability,
+    };
+
+    let mut pc = pc.borrow_mut();
+    let state = &mut pc.actor;
+    let new_actor = Actor::from(
+        &state.actor,
+        None,
+        state.actor.xp,
+        vec![ability],
+        Vec::new(),
+        state.actor.inventory.clone(),
+    );
+    state.replace_actor(new_actor);
+    state.init_day();
+}
+
+fn verify_quest(data: &QuestStateData) {
+    match Module::quest(&data.quest) {
+        None => warn!("Quest state for invalid quest '{}'", data.quest),
+        Some(quest) => {
+            if let Some(ref entry) = data.entry {
+                if !quest.entries.contains_key(entry) {
+                    warn!(
+                        "Quest entry state for invalid quest entry '{}' in '{}'",
+                        entry, data.quest
+                    );
+                }
+            }
+        }
+    }
+}
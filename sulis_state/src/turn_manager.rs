@@ -20,10 +20,13 @@ use std::rc::Rc;
 
 use serde::{Serialize, Deserialize};
 
-use crate::script::{CallbackData, FuncKind, TriggeredCallback};
-use crate::{AreaState, ChangeListener, ChangeListenerList, Effect, EntityState, GameState};
-use sulis_core::{config::Config, util::{gen_rand, Point}};
-use sulis_module::{Faction, Module, Time, ROUND_TIME_MILLIS, OnTrigger};
+use crate::script::{CallbackData, CampaignEvent, FuncKind, TriggeredCallback};
+use crate::{
+    AIBlackboard, AreaState, Awareness, ChangeListener, ChangeListenerList, Effect, EntityState,
+    GameState,
+};
+use sulis_core::{config::Config, util::{self, gen_rand, Point, ReproducibleRandom}};
+use sulis_module::{CampaignEventKind, Faction, Module, Time, ROUND_TIME_MILLIS, OnTrigger};
 
 fn add_campaign_elapsed_callback(cbs: &mut Vec<Rc<CallbackData>>) {
     let script_data = match Module::campaign().on_round_elapsed_script {
@@ -70,6 +73,7 @@ pub struct TurnManager {
 
     pub(crate) ai_groups: HashMap<usize, EncounterRef>,
     pub(crate) cur_ai_group_index: usize,
+    pub(crate) ai_blackboards: HashMap<usize, AIBlackboard>,
 
     total_elapsed_millis: usize,
 }
@@ -139,12 +143,24 @@ impl TurnManager {
         self.order.clear();
         self.cur_ai_group_index = 0;
         self.ai_groups.clear();
+        self.ai_blackboards.clear();
         self.total_elapsed_millis = total_elapsed_millis;
     }
 
-    pub(crate) fn finish_load(&mut self) {
+    pub(crate) fn finish_load(&mut self, rand: Option<ReproducibleRandom>) {
         // remove triggers that were created in the loading process
         self.triggered_cbs_next_update.clear();
+
+        // restore the random stream last, as setting up areas may have consumed rolls.
+        // older saves have no stored stream, so just start a new one
+        util::set_game_rand(rand.unwrap_or_else(|| ReproducibleRandom::new(None)));
+    }
+
+    /// Starts a new game random stream for a new game, using the debug seed from
+    /// the config if one is present
+    pub(crate) fn init_rand(&mut self) {
+        let seed = Config::debug().rand_seed.map(u128::from);
+        util::set_game_rand(ReproducibleRandom::new(seed));
     }
 
     pub fn effect_mut_checked(&mut self, index: usize) -> Option<&mut Effect> {
@@ -224,6 +240,16 @@ impl TurnManager {
         value
     }
 
+    /// Returns the blackboard shared by the specified AI group, if anything
+    /// has been written to it
+    pub fn ai_blackboard(&self, group: usize) -> Option<&AIBlackboard> {
+        self.ai_blackboards.get(&group)
+    }
+
+    pub fn ai_blackboard_mut(&mut self, group: usize) -> &mut AIBlackboard {
+        self.ai_blackboards.entry(group).or_default()
+    }
+
     pub fn entity_checked(&self, index: usize) -> Option<Rc<RefCell<EntityState>>> {
         if index >= self.entities.len() {
             return None;
@@ -409,6 +435,17 @@ impl TurnManager {
             GameState::add_ui_callback(vec![cb], current, current);
         }
 
+        for cb in current.borrow().callbacks(self) {
+            let cb = TriggeredCallback::new(cb, FuncKind::OnTurnStarted);
+            self.triggered_cbs_next_update.push(cb);
+        }
+
+        // squares are claimed for a single turn
+        let (index, group) = (current.borrow().index(), current.borrow().ai_group());
+        if let Some(blackboard) = group.and_then(|g| self.ai_blackboards.get_mut(&g)) {
+            blackboard.release_square(index);
+        }
+
         let mut current = current.borrow_mut();
         current.actor.init_turn();
         current.actor.elapse_time(ROUND_TIME_MILLIS, &self.effects);
@@ -499,10 +536,6 @@ impl TurnManager {
         mover: &Rc<RefCell<EntityState>>,
         area_state: &mut AreaState,
     ) {
-        if mover.borrow().actor.stats.hidden {
-            return;
-        }
-
         let mut groups_to_activate: HashSet<usize> = HashSet::new();
         let mut state_changed = false;
 
@@ -536,6 +569,9 @@ impl TurnManager {
             {
                 continue;
             }
+            if !is_noticed(&entity, &mover) || !is_noticed(&mover, &entity) {
+                continue;
+            }
 
             log::warn!("Found ai activation entity: {} at {:?}", entity.unique_id(), entity.location);
             self.activate_entity_ai(&mut entity, &mut groups_to_activate);
@@ -636,8 +672,10 @@ impl TurnManager {
 
         if !active {
             self.end_combat();
+            GameState::add_campaign_event(CampaignEvent::new(CampaignEventKind::CombatEnded));
         } else {
             self.initiate_combat();
+            GameState::add_campaign_event(CampaignEvent::new(CampaignEventKind::CombatStarted));
         }
     }
 
@@ -671,6 +709,10 @@ impl TurnManager {
     }
 
     fn end_combat(&mut self) {
+        for blackboard in self.ai_blackboards.values_mut() {
+            blackboard.end_combat();
+        }
+
         for entity in self.entities.iter() {
             let entity = match entity {
                 None => continue,
@@ -680,6 +722,7 @@ impl TurnManager {
             let mut entity = entity.borrow_mut();
 
             entity.set_ai_active(false);
+            entity.detection_mut().reset();
 
             if !entity.is_party_member() {
                 continue;
@@ -761,6 +804,17 @@ impl TurnManager {
 
             entity.borrow_mut().actor.end_turn();
             entity.borrow_mut().actor.set_overflow_ap(0);
+
+            let in_combat = {
+                let entity = entity.borrow();
+                entity.is_party_member() || entity.is_ai_active()
+            };
+            if in_combat {
+                for cb in entity.borrow().callbacks(self) {
+                    let cb = TriggeredCallback::new(cb, FuncKind::OnCombatStarted);
+                    self.triggered_cbs_next_update.push(cb);
+                }
+            }
         }
         GameState::set_clear_anims();
     }
@@ -1066,7 +1120,12 @@ impl TurnManager {
             self.set_combat_active(false);
         }
 
+        for blackboard in self.ai_blackboards.values_mut() {
+            blackboard.remove_entity(index);
+        }
+
         if let Some(ai_group) = self.check_encounter_cleared(&entity) {
+            self.ai_blackboards.remove(&ai_group);
             let enc_ref = self.ai_groups.get(&ai_group).unwrap().clone();
             let area_state = GameState::get_area_state(&enc_ref.area_id).unwrap();
             area_state
@@ -1154,3 +1213,12 @@ impl<'a> Iterator for EffectIterator<'a> {
         }
     }
 }
+
+/// Hidden party members are only noticed by AI observers that have detected them
+fn is_noticed(observer: &EntityState, target: &EntityState) -> bool {
+    if observer.is_party_member() || !target.is_party_member() || !target.actor.stats.hidden {
+        return true;
+    }
+
+    observer.detection().awareness() == Awareness::Alerted
+}
@@ -21,10 +21,12 @@ use crate::{action_kind, AreaMouseover};
 use sulis_core::image::Image;
 use sulis_core::io::{DrawList, GraphicsRenderer};
 use sulis_core::resource::{ResourceSet, Sprite};
-use sulis_core::ui::{animation_state, Cursor, LineRenderer, Theme, Widget};
+use sulis_core::ui::{animation_state, color, Color, Cursor, LineRenderer, Theme, Widget};
 use sulis_core::util::{Offset, Rect, Scale};
 use sulis_module::Module;
-use sulis_state::{area_feedback_text::Params, AreaState, EntityState, GameState};
+use sulis_state::{
+    area_feedback_text::Params, detection, AreaState, Awareness, EntityState, GameState,
+};
 
 pub struct HoverSprite {
     pub sprite: Rc<Sprite>,
@@ -48,6 +50,10 @@ pub struct AreaOverlayHandler {
     path_point_image: Option<Rc<dyn Image>>,
     path_point_end_image: Option<Rc<dyn Image>>,
     path_ap: Option<i32>,
+
+    suspicious_color: Color,
+    alerted_color: Color,
+    detection_risk_color: Color,
 }
 
 impl AreaOverlayHandler {
@@ -299,6 +305,11 @@ impl AreaOverlayHandler {
         if let Some(image_id) = theme.custom.get("path_point_end_image") {
             self.path_point_end_image = ResourceSet::image(image_id);
         }
+
+        self.suspicious_color = theme.get_custom_or_default("suspicious_text_color", color::YELLOW);
+        self.alerted_color = theme.get_custom_or_default("alerted_text_color", color::RED);
+        self.detection_risk_color =
+            theme.get_custom_or_default("detection_risk_text_color", color::LIGHT_GRAY);
     }
 
     pub fn hover_sprite(&self) -> Option<&HoverSprite> {
@@ -348,6 +359,59 @@ impl AreaOverlayHandler {
         Some(draw_list)
     }
 
+    /// Draws a cue above each visible hostile that is not yet in combat, showing
+    /// whether it is suspicious or alerted, or otherwise its chance to notice a
+    /// hidden party member as of the latest detection round
+    fn draw_detection_cues(
+        &self,
+        renderer: &mut dyn GraphicsRenderer,
+        params: &Params,
+        offset: Offset,
+        scale: Scale,
+    ) {
+        let area = GameState::area_state();
+        let area = area.borrow();
+        let pc = GameState::player();
+
+        let mgr = GameState::turn_manager();
+        let mgr = mgr.borrow();
+        let font_rend = LineRenderer::new(&params.font);
+        for index in area.entity_iter() {
+            let entity = mgr.entity(*index);
+            let entity = entity.borrow();
+            if !detection::is_observer(&entity) || !entity.is_hostile(&pc.borrow()) {
+                continue;
+            }
+            if !area.is_pc_visible(entity.location.x, entity.location.y) {
+                continue;
+            }
+
+            let (text, color) = match entity.detection().awareness() {
+                Awareness::Alerted => ("!".to_string(), self.alerted_color),
+                Awareness::Suspicious => ("?".to_string(), self.suspicious_color),
+                Awareness::Unaware => {
+                    let risk = entity.detection().risk();
+                    if risk == 0 {
+                        continue;
+                    }
+                    (format!("{risk}%"), self.detection_risk_color)
+                }
+            };
+
+            let width =
+                params.scale * params.font.get_width(&text) as f32 / params.font.line_height as f32;
+            let offset = Offset {
+                x: offset.x + entity.location.x as f32 + entity.size.width as f32 / 2.0
+                    - width / 2.0,
+                y: offset.y + entity.location.y as f32 - params.scale,
+            };
+            let (mut draw_list, _) = font_rend.get_draw_list(&text, offset, params.scale);
+            draw_list.set_color(color);
+            draw_list.set_scale(scale);
+            renderer.draw(draw_list);
+        }
+    }
+
     pub fn draw_top(
         &self,
         renderer: &mut dyn GraphicsRenderer,
@@ -356,6 +420,8 @@ impl AreaOverlayHandler {
         scale: Scale,
         millis: u32,
     ) {
+        self.draw_detection_cues(renderer, params, offset, scale);
+
         if let Some(ref image) = self.selection_box_image {
             if let Some((x, y, x_end, y_end)) = self.get_selection_box_coords() {
                 let w = x_end - x;
@@ -17,19 +17,20 @@
 use std::any::Any;
 use std::cell::{RefCell, RefMut};
 use std::cmp::{self, Ordering};
+use std::io;
 use std::mem;
 use std::rc::Rc;
 use std::time;
 
 use sulis_core::config::Config;
-use sulis_core::extern_image::ImageBuffer;
+use sulis_core::extern_image::{ImageBuffer, ImageFormat, Rgba, RgbaImage};
 use sulis_core::image::Image;
 use sulis_core::io::event::ClickKind;
 use sulis_core::io::*;
 use sulis_core::resource::{ResourceSet, Sprite};
 use sulis_core::ui::{animation_state, compute_area_scaling};
 use sulis_core::ui::{color, Color, Cursor, Scrollable, Widget, WidgetKind};
-use sulis_core::util::{self, Offset, Point, Rect, Scale};
+use sulis_core::util::{self, Offset, Point, Rect, Scale, Size};
 use sulis_core::widgets::Label;
 use sulis_module::{
     area::{Layer, Tile},
@@ -68,6 +69,9 @@ pub struct AreaView {
     screen_shake: Option<ScreenShake>,
 
     overlay_handler: AreaOverlayHandler,
+
+    thumbnail: Option<RgbaImage>,
+    thumbnail_requested: bool,
 }
 
 const TILE_CACHE_TEXTURE_SIZE: u32 = 2048;
@@ -78,6 +82,67 @@ const ENTITY_TEX_ID: &str = "__entities__";
 const VISIBILITY_TEX_ID: &str = "__visibility__";
 const BASE_LAYER_ID: &str = "__base_layer__";
 const AERIAL_LAYER_ID: &str = "__aerial_layer__";
+const THUMBNAIL_TEX_ID: &str = "__thumbnail__";
+
+const THUMBNAIL_SIZE: (u32, u32) = (240, 135);
+
+/// Redirects everything drawn to the screen into the thumbnail texture, mapping
+/// the area view widget onto the full texture
+struct ThumbnailRenderer<'a> {
+    renderer: &'a mut dyn GraphicsRenderer,
+    offset: (f32, f32),
+    zoom: (f32, f32),
+}
+
+impl<'a> GraphicsRenderer for ThumbnailRenderer<'a> {
+    fn draw(&mut self, mut draw_list: DrawList) {
+        if draw_list.texture.is_empty() {
+            return;
+        }
+
+        let (scale_x, scale_y) = (draw_list.scale[0], draw_list.scale[1]);
+        for vertex in draw_list.quads.iter_mut() {
+            vertex.position[0] -= self.offset.0 / scale_x;
+            vertex.position[1] += self.offset.1 / scale_y;
+        }
+        draw_list.scale = [scale_x * self.zoom.0, scale_y * self.zoom.1];
+        self.renderer.draw_to_texture(THUMBNAIL_TEX_ID, draw_list);
+    }
+
+    fn draw_to_texture(&mut self, texture_id: &str, draw_list: DrawList) {
+        self.renderer.draw_to_texture(texture_id, draw_list);
+    }
+
+    fn register_texture(
+        &mut self,
+        id: &str,
+        image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+        min_filter: TextureMinFilter,
+        mag_filter: TextureMagFilter,
+    ) {
+        self.renderer.register_texture(id, image, min_filter, mag_filter);
+    }
+
+    fn clear_texture(&mut self, id: &str) {
+        self.renderer.clear_texture(id);
+    }
+
+    fn clear_texture_region(&mut self, id: &str, min_x: i32, min_y: i32, max_x: i32, max_y: i32) {
+        self.renderer.clear_texture_region(id, min_x, min_y, max_x, max_y);
+    }
+
+    fn has_texture(&self, id: &str) -> bool {
+        self.renderer.has_texture(id)
+    }
+
+    fn read_texture(&self, id: &str) -> Option<RgbaImage> {
+        self.renderer.read_texture(id)
+    }
+
+    fn set_scissor(&mut self, _pos: Point, _size: Size) {}
+
+    fn clear_scissor(&mut self) {}
+}
 
 impl AreaView {
     pub fn new(scroll: Scrollable) -> Rc<RefCell<AreaView>> {
@@ -100,9 +165,36 @@ impl AreaView {
             scroll_target: None,
             screen_shake: None,
             overlay_handler: AreaOverlayHandler::default(),
+            thumbnail: None,
+            thumbnail_requested: false,
         }))
     }
 
+    /// Captures a thumbnail of the area the next time it is drawn
+    pub fn request_thumbnail(&mut self) {
+        self.thumbnail = None;
+        self.thumbnail_requested = true;
+    }
+
+    /// Returns true if a requested thumbnail has not yet been captured
+    pub fn is_thumbnail_pending(&self) -> bool {
+        self.thumbnail_requested
+    }
+
+    /// Takes the thumbnail captured after `request_thumbnail`, encoded as a PNG
+    pub fn take_thumbnail_png(&mut self) -> Option<Vec<u8>> {
+        let image = self.thumbnail.take()?;
+
+        let mut png = Vec::new();
+        match image.write_to(&mut io::Cursor::new(&mut png), ImageFormat::Png) {
+            Ok(()) => Some(png),
+            Err(e) => {
+                warn!("Unable to encode area thumbnail: {}", e);
+                None
+            }
+        }
+    }
+
     pub fn clear_mouse_state(&mut self) {
         self.overlay_handler.clear_mouse_state();
     }
@@ -423,6 +515,67 @@ impl AreaView {
         // info!("Entity & Prop draw time: {}", util::format_elapsed_secs(start_time.elapsed()));
     }
 
+    fn capture_thumbnail(
+        &mut self,
+        renderer: &mut dyn GraphicsRenderer,
+        widget: &Widget,
+        state: &AreaState,
+        color: Color,
+        millis: u32,
+    ) {
+        self.thumbnail_requested = false;
+
+        let size = widget.state.inner_size();
+        if size.width <= 0 || size.height <= 0 {
+            return;
+        }
+
+        if renderer.has_texture(THUMBNAIL_TEX_ID) {
+            renderer.clear_texture(THUMBNAIL_TEX_ID);
+        } else {
+            let (width, height) = THUMBNAIL_SIZE;
+            renderer.register_texture(
+                THUMBNAIL_TEX_ID,
+                ImageBuffer::new(width, height),
+                TextureMinFilter::Linear,
+                TextureMagFilter::Linear,
+            );
+        }
+
+        let (ui_x, ui_y) = Config::ui_size();
+        let p = widget.state.inner_position();
+        let scale = Scale {
+            x: self.scale.0,
+            y: self.scale.1,
+        };
+
+        {
+            let mut thumbnail = ThumbnailRenderer {
+                renderer: &mut *renderer,
+                offset: (p.x as f32, p.y as f32),
+                zoom: (
+                    ui_x as f32 / size.width as f32,
+                    ui_y as f32 / size.height as f32,
+                ),
+            };
+
+            self.draw_layer(&mut thumbnail, scale, widget, BASE_LAYER_ID, color);
+            self.draw_entities_props(&mut thumbnail, scale, color, widget, state, millis);
+            self.draw_layer(&mut thumbnail, scale, widget, AERIAL_LAYER_ID, color);
+            if Config::debug().limit_line_of_sight {
+                self.draw_layer(&mut thumbnail, scale, widget, VISIBILITY_TEX_ID, color::WHITE);
+            }
+        }
+
+        self.thumbnail = renderer.read_texture(THUMBNAIL_TEX_ID).map(|mut image| {
+            // the texture is cleared to transparent black, so make it opaque
+            for pixel in image.pixels_mut() {
+                pixel[3] = 255;
+            }
+            image
+        });
+    }
+
     fn draw_selection(
         &mut self,
         selected: &Rc<RefCell<EntityState>>,
@@ -771,6 +924,10 @@ impl WidgetKind for AreaView {
         let time = mgr.borrow().current_time();
         let area_color = rules.get_area_color(state.area.area.location_kind, time);
 
+        if self.thumbnail_requested {
+            self.capture_thumbnail(renderer, widget, &state, area_color, millis);
+        }
+
         let scale = Scale {
             x: scale_x,
             y: scale_y,
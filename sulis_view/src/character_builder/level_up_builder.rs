@@ -19,7 +19,7 @@ use std::rc::Rc;
 
 use sulis_core::ui::Widget;
 use sulis_module::Actor;
-use sulis_state::EntityState;
+use sulis_state::{script::script_callback, EntityState};
 
 use crate::character_builder::*;
 
@@ -95,18 +95,22 @@ impl BuilderSet for LevelUpBuilder {
             Some(ref class) => Rc::clone(class),
         };
 
-        let mut pc = self.pc.borrow_mut();
-        let state = &mut pc.actor;
-
-        let new_actor = Actor::from(
-            &state.actor,
-            Some((class, 1)),
-            state.xp(),
-            builder.abilities.clone(),
-            Vec::new(),
-            state.actor.inventory.clone(),
-        );
-        state.replace_actor(new_actor);
-        state.init_day();
+        {
+            let mut pc = self.pc.borrow_mut();
+            let state = &mut pc.actor;
+
+            let new_actor = Actor::from(
+                &state.actor,
+                Some((class, 1)),
+                state.xp(),
+                builder.abilities.clone(),
+                Vec::new(),
+                state.actor.inventory.clone(),
+            );
+            state.replace_actor(new_actor);
+            state.init_day();
+        }
+
+        script_callback::fire_on_level_up(&self.pc);
     }
 }
@@ -31,7 +31,7 @@ use sulis_module::{
     ActorBuilder, Attribute, DamageKind, InventoryBuilder, ItemListEntrySaveState, ItemSaveState,
     Module, QuickSlot, Slot,
 };
-use sulis_state::{ActorState, ChangeListener, Effect, EntityState, GameState};
+use sulis_state::{ActorState, ChangeListener, Effect, EntityState, GameState, PartyAIMode};
 
 use crate::ability_pane::add_ability_text_args;
 use crate::bonus_text_arg_handler::add_bonus_text_args;
@@ -160,7 +160,11 @@ impl WidgetKind for CharacterWindow {
                     .state
                     .set_visible(self.character.borrow_mut().actor.has_level_up());
                 let is_pc = Rc::ptr_eq(&self.character, &GameState::player());
-                create_details_text_box(&self.character.borrow().actor, is_pc)
+                let details = create_details_text_box(&self.character.borrow().actor, is_pc);
+                if self.character.borrow().is_party_member() {
+                    add_party_ai_buttons(&details, &self.character);
+                }
+                details
             }
             ActivePane::Ability { show_passives } => {
                 abilities_pane.borrow_mut().state.set_active(true);
@@ -183,6 +187,66 @@ impl WidgetKind for CharacterWindow {
     }
 }
 
+fn add_party_ai_buttons(details: &Rc<RefCell<Widget>>, character: &Rc<RefCell<EntityState>>) {
+    let mode = character.borrow().party_ai_mode();
+    let template = character.borrow().party_ai_template();
+
+    let ai_mode = Widget::with_theme(Button::empty(), "ai_mode");
+    ai_mode
+        .borrow_mut()
+        .state
+        .add_text_arg("mode", mode.to_str());
+    let char_ref = Rc::clone(character);
+    ai_mode
+        .borrow_mut()
+        .state
+        .add_callback(Callback::new(Rc::new(move |widget, _| {
+            let mode = char_ref.borrow().party_ai_mode().next();
+            char_ref.borrow_mut().set_party_ai_mode(mode);
+            let (parent, _) = Widget::parent::<CharacterWindow>(widget);
+            parent.borrow_mut().invalidate_children();
+        })));
+    Widget::add_child_to(details, ai_mode);
+
+    let ai_template = Widget::with_theme(Button::empty(), "ai_template");
+    if let Some(template) = &template {
+        ai_template
+            .borrow_mut()
+            .state
+            .add_text_arg("template", &template.id);
+    }
+    ai_template
+        .borrow_mut()
+        .state
+        .set_enabled(mode != PartyAIMode::Off);
+    let char_ref = Rc::clone(character);
+    ai_template
+        .borrow_mut()
+        .state
+        .add_callback(Callback::new(Rc::new(move |widget, _| {
+            let mut templates = Module::all_ai_templates();
+            if templates.is_empty() {
+                return;
+            }
+            templates.sort_by(|a, b| a.id.cmp(&b.id));
+
+            let cur = char_ref.borrow().party_ai_template();
+            let index = match cur {
+                None => 0,
+                Some(cur) => templates
+                    .iter()
+                    .position(|t| t.id == cur.id)
+                    .map_or(0, |i| (i + 1) % templates.len()),
+            };
+            char_ref
+                .borrow_mut()
+                .set_party_ai_template(Rc::clone(&templates[index]));
+            let (parent, _) = Widget::parent::<CharacterWindow>(widget);
+            parent.borrow_mut().invalidate_children();
+        })));
+    Widget::add_child_to(details, ai_template);
+}
+
 pub fn get_inventory(pc: &ActorState, include_stash: bool) -> InventoryBuilder {
     let coins = GameState::party_coins();
 
@@ -16,55 +16,272 @@
 
 use std::any::Any;
 use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use log::Level;
+use sulis_core::{benchmark, config};
 use sulis_core::io::InputActionKind;
 use sulis_core::ui::{Callback, Widget, WidgetKind};
-use sulis_core::widgets::{InputField, Label};
+use sulis_core::widgets::{InputField, Label, TextArea};
+use sulis_module::Module;
+use sulis_state::script::{console, script_cache};
 use sulis_state::{GameState, ScriptState};
 
 pub const NAME: &str = "console_window";
 
+/// The maximum number of lines of output kept
+const MAX_SCROLLBACK: usize = 500;
+
+/// The maximum number of commands kept in the history file
+const MAX_HISTORY: usize = 100;
+
+const HISTORY_FILENAME: &str = "console_history.txt";
+
+/// The number of script functions and benchmarks shown by `profile`
+const PROFILE_ENTRIES: usize = 10;
+
+const INPUT_COLOR: &str = "FFF";
+const RESULT_COLOR: &str = "CCC";
+const ERROR_COLOR: &str = "F44";
+
 pub struct ConsoleWindow {
     input: Rc<RefCell<InputField>>,
     input_widget: Rc<RefCell<Widget>>,
-    output: Rc<RefCell<Widget>>,
+    output: Rc<RefCell<TextArea>>,
+    output_widget: Rc<RefCell<Widget>>,
+    lines: VecDeque<(&'static str, String)>,
+    visible_lines: usize,
+    scroll: usize,
     history: Vec<String>,
     history_index: usize,
     script_state: ScriptState,
+    target_script: Option<String>,
 }
 
 impl ConsoleWindow {
     pub fn new() -> Rc<RefCell<ConsoleWindow>> {
         let input = InputField::new("");
+        let output = TextArea::empty();
+        let history = read_history();
         Rc::new(RefCell::new(ConsoleWindow {
             input: Rc::clone(&input),
             input_widget: Widget::with_theme(input, "input"),
-            output: Widget::with_theme(Label::empty(), "output"),
-            history: Vec::new(),
-            history_index: 0,
+            output: Rc::clone(&output),
+            output_widget: Widget::with_theme(output, "output"),
+            lines: VecDeque::new(),
+            visible_lines: 1,
+            scroll: 0,
+            history_index: history.len(),
+            history,
             script_state: ScriptState::default(),
+            target_script: None,
         }))
     }
 
     pub fn execute_script(&mut self, script: String) {
-        if script.trim().is_empty() {
+        let line = script.trim().to_string();
+        if line.is_empty() {
             return;
         }
 
-        self.history.push(script[0..script.len() - 1].to_string());
-        self.history_index = self.history.len();
+        self.add_history(&line);
+        let prompt = match self.target_script {
+            None => ">".to_string(),
+            Some(ref id) => format!("{id}>"),
+        };
+        self.push_line(INPUT_COLOR, format!("{prompt} {line}"));
+
+        let command = line.split_whitespace().next().unwrap_or_default();
+        let script = match command {
+            "help" => {
+                self.show_help();
+                return;
+            }
+            "script" => {
+                self.set_target_script(line.split_whitespace().nth(1));
+                return;
+            }
+            "profile" => {
+                self.profile(line.split_whitespace().nth(1));
+                return;
+            }
+            _ => match console::expand_command(&line) {
+                None => line,
+                Some(Ok(script)) => script,
+                Some(Err(e)) => {
+                    self.push_line(ERROR_COLOR, e);
+                    return;
+                }
+            },
+        };
 
         let party = GameState::party();
 
-        let result = match self.script_state.console(script, &party) {
-            Ok(result) => result,
-            Err(rlua::Error::FromLuaConversionError { .. }) => "Success".to_string(),
-            Err(e) => format!("{e}"),
+        let result = match self.target_script {
+            None => self.script_state.console(script, &party),
+            Some(ref id) => script_cache::console(id, script, &party),
         };
 
-        info!("Console result: {}", result);
-        self.output.borrow_mut().state.text = result;
+        self.take_log();
+        match result {
+            Ok(result) => {
+                info!("Console result: {}", result);
+                self.push_line(RESULT_COLOR, result);
+            }
+            Err(rlua::Error::FromLuaConversionError { .. }) => {
+                self.push_line(RESULT_COLOR, "Success".to_string())
+            }
+            Err(e) => {
+                info!("Console result: {}", e);
+                self.push_line(ERROR_COLOR, format!("{e}"));
+            }
+        }
+    }
+
+    fn show_help(&mut self) {
+        self.push_line(
+            RESULT_COLOR,
+            "Enter Lua to evaluate it, or one of the following commands.  \
+             Tab completes names and IDs."
+                .to_string(),
+        );
+        for (_, usage) in console::COMMANDS.iter() {
+            self.push_line(RESULT_COLOR, format!("  {usage}"));
+        }
+    }
+
+    fn profile(&mut self, action: Option<&str>) {
+        if !benchmark::profiling() {
+            self.push_line(
+                ERROR_COLOR,
+                "Profiling is disabled; set profile_scripts in the debug config".to_string(),
+            );
+            return;
+        }
+
+        match action {
+            None => {
+                for line in benchmark::profile_report(Some(PROFILE_ENTRIES)).lines() {
+                    self.push_line(RESULT_COLOR, line.to_string());
+                }
+            }
+            Some("clear") => {
+                benchmark::clear_profile();
+                self.push_line(RESULT_COLOR, "Cleared the profile".to_string());
+            }
+            Some("save") => match benchmark::write_profile() {
+                Ok(Some(path)) => {
+                    self.push_line(RESULT_COLOR, format!("Wrote the profile to {path:?}"))
+                }
+                Ok(None) => (),
+                Err(e) => self.push_line(ERROR_COLOR, format!("Unable to write the profile: {e}")),
+            },
+            Some(_) => self.push_line(ERROR_COLOR, "Usage: profile [clear|save]".to_string()),
+        }
+    }
+
+    fn set_target_script(&mut self, id: Option<&str>) {
+        match id {
+            None => {
+                self.target_script = None;
+                self.push_line(RESULT_COLOR, "Executing in the console".to_string());
+            }
+            Some(id) if Module::script(id).is_some() => {
+                self.target_script = Some(id.to_string());
+                self.push_line(RESULT_COLOR, format!("Executing in script '{id}'"));
+            }
+            Some(id) => self.push_line(ERROR_COLOR, format!("Script '{id}' does not exist")),
+        }
+    }
+
+    /// Completes the word before the end of the input, as far as it is the same
+    /// for all possible completions.  Lists the completions if there are several.
+    fn complete(&mut self, field: &mut InputField, widget: &Rc<RefCell<Widget>>) {
+        let text = field.text();
+        let (start, completions) = console::complete(&text);
+        if completions.is_empty() {
+            return;
+        }
+
+        let mut common = completions[0].clone();
+        for completion in completions.iter().skip(1) {
+            let len = common
+                .chars()
+                .zip(completion.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a.len_utf8())
+                .sum();
+            common.truncate(len);
+        }
+
+        if completions.len() > 1 {
+            self.push_line(RESULT_COLOR, completions.join("  "));
+        }
+        field.set_text(&format!("{}{}", &text[..start], common), widget);
+    }
+
+    fn take_log(&mut self) {
+        for (level, text) in console::take_log() {
+            let color = match level {
+                Level::Error => ERROR_COLOR,
+                Level::Warn => "FF0",
+                Level::Info => "8CF",
+                _ => "888",
+            };
+            self.push_line(color, text);
+        }
+    }
+
+    fn push_line(&mut self, color: &'static str, text: String) {
+        for line in text.lines() {
+            if self.lines.len() == MAX_SCROLLBACK {
+                self.lines.pop_front();
+            }
+            self.lines.push_back((color, line.to_string()));
+        }
+        self.scroll = 0;
+        self.update_output();
+    }
+
+    fn scroll(&mut self, delta: i32) {
+        let max = self.lines.len().saturating_sub(self.visible_lines);
+        let scroll = self.scroll as i32 + delta;
+        self.scroll = scroll.clamp(0, max as i32) as usize;
+        self.update_output();
+    }
+
+    fn update_output(&mut self) {
+        self.set_output_text();
+        self.output_widget.borrow_mut().invalidate_layout();
+    }
+
+    /// Shows the visible lines of output, based on the current scroll position
+    fn set_output_text(&mut self) {
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+        let start = end.saturating_sub(self.visible_lines);
+        let text: Vec<String> = self
+            .lines
+            .range(start..end)
+            .map(|(color, line)| format!("[c={}|{}]", color, escape(line)))
+            .collect();
+        self.output.borrow_mut().text = Some(text.join("\n"));
+    }
+
+    fn add_history(&mut self, line: &str) {
+        if self.history.last().map(|l| l.as_str()) != Some(line) {
+            self.history.push(line.to_string());
+        }
+        self.history_index = self.history.len();
+
+        let start = self.history.len().saturating_sub(MAX_HISTORY);
+        let mut contents = self.history[start..].join("\n");
+        contents.push('\n');
+        if let Err(e) = fs::write(history_path(), contents) {
+            warn!("Unable to write console history: {}", e);
+        }
     }
 
     pub fn current_history_text(&self) -> String {
@@ -81,9 +298,44 @@ impl ConsoleWindow {
     }
 }
 
+fn history_path() -> PathBuf {
+    let mut path = config::USER_DIR.clone();
+    path.push(HISTORY_FILENAME);
+    path
+}
+
+fn read_history() -> Vec<String> {
+    match fs::read_to_string(history_path()) {
+        Ok(contents) => contents.lines().map(|l| l.to_string()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Escapes the characters used by the markup renderer
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '|') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
 impl WidgetKind for ConsoleWindow {
     widget_kind!(NAME);
 
+    fn update(&mut self, _widget: &Rc<RefCell<Widget>>, _millis: u32) {
+        self.take_log();
+    }
+
+    fn layout(&mut self, widget: &mut Widget) {
+        self.visible_lines = widget.theme.get_custom_or_default("visible_lines", 8);
+        self.set_output_text();
+        widget.do_base_layout();
+    }
+
     fn on_add(&mut self, _widget: &Rc<RefCell<Widget>>) -> Vec<Rc<RefCell<Widget>>> {
         let prompt = Widget::with_theme(Label::empty(), "prompt");
 
@@ -108,6 +360,15 @@ impl WidgetKind for ConsoleWindow {
                             field.set_text(&console.current_history_text(), widget);
                         }
                     }
+                    InputActionKind::ConsoleComplete => console.complete(field, widget),
+                    InputActionKind::ConsoleScrollUp => {
+                        let delta = console.visible_lines as i32 / 2;
+                        console.scroll(delta);
+                    }
+                    InputActionKind::ConsoleScrollDown => {
+                        let delta = console.visible_lines as i32 / 2;
+                        console.scroll(-delta);
+                    }
                     _ => (),
                 }
             }));
@@ -127,6 +388,10 @@ impl WidgetKind for ConsoleWindow {
                 console.execute_script(text);
             })));
 
-        vec![prompt, self.input_widget.clone(), self.output.clone()]
+        vec![
+            self.output_widget.clone(),
+            prompt,
+            self.input_widget.clone(),
+        ]
     }
 }
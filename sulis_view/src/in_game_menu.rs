@@ -22,7 +22,7 @@ use sulis_core::ui::{Callback, Widget, WidgetKind};
 use sulis_core::widgets::{Button, ConfirmationWindow};
 use sulis_state::GameState;
 
-use crate::{LoadWindow, RootView};
+use crate::{LoadWindow, RootView, SaveWindow};
 
 const NAME: &str = "in_game_menu";
 
@@ -68,6 +68,24 @@ impl WidgetKind for InGameMenu {
             .state
             .set_enabled(!GameState::is_combat_active());
 
+        let save_as = Widget::with_theme(Button::empty(), "save_as");
+        save_as
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (parent, _) = Widget::parent::<InGameMenu>(widget);
+                parent.borrow_mut().mark_for_removal();
+
+                let root = Widget::get_root(widget);
+                let window = Widget::with_defaults(SaveWindow::new());
+                window.borrow_mut().state.set_modal(true);
+                Widget::add_child_to(&root, window);
+            })));
+        save_as
+            .borrow_mut()
+            .state
+            .set_enabled(!GameState::is_combat_active());
+
         let load = Widget::with_theme(Button::empty(), "load");
         load.borrow_mut()
             .state
@@ -111,6 +129,6 @@ impl WidgetKind for InGameMenu {
                 Widget::add_child_to(&root, window);
             })));
 
-        vec![back, save, load, menu, exit]
+        vec![back, save, save_as, load, menu, exit]
     }
 }
@@ -93,6 +93,7 @@ pub fn equip_item_cb(entity: &Rc<RefCell<EntityState>>, index: usize) -> Callbac
         };
 
         let slot = item.item.equippable.as_ref().map_or(Slot::Neck, |e| e.slot);
+        let item_id = item.item.id.clone();
 
         // equip with no preferred slot
         let to_add = entity.borrow_mut().actor.equip(item, None);
@@ -101,14 +102,15 @@ pub fn equip_item_cb(entity: &Rc<RefCell<EntityState>>, index: usize) -> Callbac
             stash.borrow_mut().add_item(1, item);
         }
 
+        let mgr = GameState::turn_manager();
+        let cbs = entity.borrow().callbacks(&mgr.borrow());
         match slot {
             Slot::HeldMain | Slot::HeldOff => {
-                let mgr = GameState::turn_manager();
-                let cbs = entity.borrow().callbacks(&mgr.borrow());
                 cbs.iter().for_each(|cb| cb.on_held_changed());
             },
             _ => (),
         }
+        cbs.iter().for_each(|cb| cb.on_item_equipped(&item_id));
     }))
 }
 
@@ -124,19 +126,9 @@ pub fn buy_item_cb(merchant_id: &str, index: usize) -> Callback {
             Some(ref mut merchant) => merchant,
         };
 
-        let value = match merchant.items().get(index) {
-            None => return,
-            Some((_, item_state)) => merchant.get_buy_price(item_state),
-        };
-
-        if GameState::party_coins() < value {
-            return;
-        }
-
-        if let Some(item_state) = merchant.remove(index) {
-            GameState::add_party_coins(-value);
+        if let Some(item_state) = merchant.buy(index) {
             let stash = GameState::party_stash();
-            stash.borrow_mut().add_item(1, item_state);
+            stash.borrow_mut().pick_up(1, item_state);
         }
     }))
 }
@@ -164,9 +156,7 @@ pub fn sell_item_cb(entity: &Rc<RefCell<EntityState>>, index: usize) -> Callback
         let stash = GameState::party_stash();
         let item_state = stash.borrow_mut().remove_item(index);
         if let Some(item_state) = item_state {
-            let value = merchant.get_sell_price(&item_state);
-            GameState::add_party_coins(value);
-            merchant.add(item_state);
+            merchant.sell(item_state);
         }
 
         let actor = &entity.borrow().actor;
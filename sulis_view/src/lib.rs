@@ -118,6 +118,9 @@ pub use self::race_pane::RacePane;
 mod root_view;
 pub use self::root_view::RootView;
 
+mod save_window;
+pub use self::save_window::SaveWindow;
+
 mod screen_shake;
 pub use self::screen_shake::ScreenShake;
 
@@ -18,20 +18,82 @@ use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use sulis_core::extern_image::RgbaImage;
+use sulis_core::io::{DrawList, GraphicsRenderer, TextureMagFilter, TextureMinFilter};
 use sulis_core::ui::{Callback, Widget, WidgetKind};
+use sulis_core::util::{Point, Rect};
 use sulis_core::widgets::{
     Button, ConfirmationWindow, Label, ScrollDirection, ScrollPane, TextArea,
 };
+use sulis_module::Module;
 use sulis_state::save_file::{delete_save, get_available_save_files, load_state};
-use sulis_state::{NextGameStep, SaveFileMetaData, SaveState};
+use sulis_state::{NextGameStep, SaveFileMetaData, SaveKind, SaveState};
 
 use crate::{main_menu::MainMenu, LoadingScreen, RootView};
 
 const NAME: &str = "load_window";
 
+const THUMBNAIL_TEX_ID: &str = "__save_thumbnail__";
+const THUMBNAIL_TEX_COORDS: [f32; 8] = [0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0];
+
+/// Draws the area screenshot embedded in the selected save
+struct SaveThumbnail {
+    image: Option<RgbaImage>,
+    has_texture: bool,
+}
+
+impl SaveThumbnail {
+    fn set_image(&mut self, image: Option<RgbaImage>) {
+        self.has_texture = false;
+        self.image = image;
+    }
+}
+
+impl WidgetKind for SaveThumbnail {
+    widget_kind!("thumbnail");
+
+    fn draw(
+        &mut self,
+        renderer: &mut dyn GraphicsRenderer,
+        _pixel_size: Point,
+        widget: &Widget,
+        _millis: u32,
+    ) {
+        if let Some(image) = self.image.take() {
+            renderer.register_texture(
+                THUMBNAIL_TEX_ID,
+                image,
+                TextureMinFilter::Linear,
+                TextureMagFilter::Linear,
+            );
+            self.has_texture = true;
+        }
+
+        if !self.has_texture {
+            return;
+        }
+
+        let pos = widget.state.inner_position();
+        let size = widget.state.inner_size();
+        let rect = Rect {
+            x: pos.x as f32,
+            y: pos.y as f32,
+            w: size.width as f32,
+            h: size.height as f32,
+        };
+        renderer.draw(DrawList::from_texture_id(
+            THUMBNAIL_TEX_ID,
+            &THUMBNAIL_TEX_COORDS,
+            rect,
+        ));
+    }
+}
+
 pub struct LoadWindow {
     accept: Rc<RefCell<Widget>>,
     delete: Rc<RefCell<Widget>>,
+    details: Rc<RefCell<Widget>>,
+    thumbnail: Rc<RefCell<SaveThumbnail>>,
     pub(crate) cancel: Rc<RefCell<Widget>>,
     pub(crate) entries: Vec<SaveFileMetaData>,
     pub(crate) selected_entry: Option<usize>,
@@ -43,6 +105,7 @@ impl LoadWindow {
         let accept = Widget::with_theme(Button::empty(), "accept");
         let cancel = Widget::with_theme(Button::empty(), "cancel");
         let delete = Widget::with_theme(Button::empty(), "delete");
+        let details = Widget::with_theme(TextArea::empty(), "details");
         let entries = match get_available_save_files() {
             Ok(files) => files,
             Err(e) => {
@@ -55,6 +118,11 @@ impl LoadWindow {
         Rc::new(RefCell::new(LoadWindow {
             accept,
             delete,
+            details,
+            thumbnail: Rc::new(RefCell::new(SaveThumbnail {
+                image: None,
+                has_texture: false,
+            })),
             cancel,
             entries,
             selected_entry: None,
@@ -68,34 +136,38 @@ impl LoadWindow {
             Some(index) => index,
         };
 
-        match load_state(&self.entries[index]) {
-            Err(e) => {
-                error!("Error reading game state");
-                error!("{}", e);
-            }
-            Ok(state) => {
-                self.set_load_step(state, root);
-            }
+        let meta = &self.entries[index];
+        let differences = meta.mod_differences();
+        if differences.is_empty() {
+            load_save(self.main_menu_mode, meta, root);
+            return;
         }
-    }
 
-    pub fn set_load_step(&self, save_state: SaveState, root: &Rc<RefCell<Widget>>) {
-        // TODO remove the bool flag passed in the constructor
-        if self.main_menu_mode {
-            let main_menu = Widget::kind_mut::<MainMenu>(root);
-            main_menu.next_step = Some(NextGameStep::LoadCampaign {
-                save_state: Box::new(save_state),
-            });
-        } else {
-            let root_view = Widget::kind_mut::<RootView>(root);
-            root_view.next_step = Some(NextGameStep::LoadCampaign {
-                save_state: Box::new(save_state),
-            });
+        // the save was created with a different set of mods, so ask before loading
+        for difference in differences.iter() {
+            warn!("{}", difference);
         }
 
-        let loading_screen = Widget::with_defaults(LoadingScreen::new());
-        loading_screen.borrow_mut().state.set_modal(true);
-        Widget::add_child_to(root, loading_screen);
+        let main_menu_mode = self.main_menu_mode;
+        let meta = meta.clone();
+        let accept_cb = Callback::new(Rc::new(move |widget, _| {
+            let (parent, _) = Widget::parent::<ConfirmationWindow>(widget);
+            parent.borrow_mut().mark_for_removal();
+
+            let root = Widget::get_root(widget);
+            load_save(main_menu_mode, &meta, &root);
+        }));
+
+        let window = ConfirmationWindow::new(accept_cb);
+        window
+            .borrow()
+            .title()
+            .borrow_mut()
+            .state
+            .add_text_arg("count", &differences.len().to_string());
+        let conf_window = Widget::with_theme(window, "load_mods_changed_confirmation");
+        conf_window.borrow_mut().state.set_modal(true);
+        Widget::add_child_to(root, conf_window);
     }
 
     pub fn delete_save(&mut self) {
@@ -125,6 +197,57 @@ impl LoadWindow {
 
         self.accept.borrow_mut().state.set_enabled(accept_enabled);
     }
+
+    /// Shows the thumbnail and extended meta data of the selected save
+    fn set_details(&self) {
+        let mut details = self.details.borrow_mut();
+        details.state.clear_text_args();
+        details.invalidate_layout();
+
+        let meta = match self.selected_entry {
+            Some(index) if self.entries[index].error.is_none() => &self.entries[index],
+            _ => {
+                self.thumbnail.borrow_mut().set_image(None);
+                return;
+            }
+        };
+
+        let state = &mut details.state;
+        if !meta.party.is_empty() {
+            let party: Vec<String> = meta
+                .party
+                .iter()
+                .map(|member| format!("{} ({} {})", member.name, member.level, member.class))
+                .collect();
+            state.add_text_arg("party", &party.join(", "));
+        }
+
+        if meta.play_time_millis > 0 {
+            let minutes = meta.play_time_millis / 60_000;
+            state.add_text_arg("play_time", &format!("{}h {}m", minutes / 60, minutes % 60));
+        }
+
+        if let Some(time) = meta.time {
+            let rules = Module::rules();
+            state.add_text_arg("day", &time.day.to_string());
+            if time.hour < rules.hours_per_day {
+                state.add_text_arg("hour", rules.get_hour_name(time.hour));
+            }
+        }
+
+        if let Some(quest) = &meta.active_quest {
+            state.add_text_arg("quest", quest);
+        }
+
+        if !meta.mods.is_empty() {
+            let mods: Vec<&str> = meta.mods.iter().map(|info| info.name.as_str()).collect();
+            state.add_text_arg("mods", &mods.join(", "));
+        }
+
+        self.thumbnail
+            .borrow_mut()
+            .set_image(meta.thumbnail_image());
+    }
 }
 
 impl WidgetKind for LoadWindow {
@@ -200,6 +323,16 @@ impl WidgetKind for LoadWindow {
                 if let Some(error) = &meta.error {
                     area.add_text_arg("error", error);
                 }
+
+                if meta.is_from_newer_version() {
+                    area.add_text_arg("newer_version", "true");
+                }
+
+                match &meta.kind {
+                    SaveKind::Quick => (),
+                    SaveKind::Named(name) => area.add_text_arg("slot_name", name),
+                    SaveKind::Auto => area.add_text_arg("autosave", "true"),
+                }
             }
 
             let widget = Widget::with_theme(Button::empty(), "entry");
@@ -211,6 +344,7 @@ impl WidgetKind for LoadWindow {
                     parent.borrow_mut().invalidate_layout();
                     load_window.selected_entry = Some(index);
                     load_window.set_button_state();
+                    load_window.set_details();
 
                     let content = Widget::direct_parent(widget);
                     for child in content.borrow().children.iter() {
@@ -230,6 +364,11 @@ impl WidgetKind for LoadWindow {
         }
 
         self.set_button_state();
+        self.set_details();
+
+        let thumbnail = Widget::with_theme(self.thumbnail.clone(), "thumbnail");
+        let details_pane = Widget::empty("details_pane");
+        Widget::add_children_to(&details_pane, vec![thumbnail, self.details.clone()]);
 
         vec![
             self.cancel.clone(),
@@ -237,6 +376,68 @@ impl WidgetKind for LoadWindow {
             self.accept.clone(),
             title,
             entries,
+            details_pane,
         ]
     }
 }
+
+/// Reads the state of the specified save and loads it, asking for confirmation
+/// first if any content in the save no longer exists
+fn load_save(main_menu_mode: bool, meta: &SaveFileMetaData, root: &Rc<RefCell<Widget>>) {
+    let state = match load_state(meta) {
+        Err(e) => {
+            error!("Error reading game state");
+            error!("{}", e);
+            return;
+        }
+        Ok(state) => state,
+    };
+
+    if state.removed_content().is_empty() {
+        set_load_step(main_menu_mode, state, root);
+        return;
+    }
+
+    // content referenced by the save no longer exists, so ask before loading
+    let count = state.removed_content().len();
+    let state = RefCell::new(Some(state));
+    let accept_cb = Callback::new(Rc::new(move |widget, _| {
+        let (parent, _) = Widget::parent::<ConfirmationWindow>(widget);
+        parent.borrow_mut().mark_for_removal();
+
+        if let Some(state) = state.borrow_mut().take() {
+            let root = Widget::get_root(widget);
+            set_load_step(main_menu_mode, state, &root);
+        }
+    }));
+
+    let window = ConfirmationWindow::new(accept_cb);
+    window
+        .borrow()
+        .title()
+        .borrow_mut()
+        .state
+        .add_text_arg("count", &count.to_string());
+    let conf_window = Widget::with_theme(window, "load_missing_content_confirmation");
+    conf_window.borrow_mut().state.set_modal(true);
+    Widget::add_child_to(root, conf_window);
+}
+
+fn set_load_step(main_menu_mode: bool, save_state: SaveState, root: &Rc<RefCell<Widget>>) {
+    // TODO remove the bool flag passed in the constructor
+    if main_menu_mode {
+        let main_menu = Widget::kind_mut::<MainMenu>(root);
+        main_menu.next_step = Some(NextGameStep::LoadCampaign {
+            save_state: Box::new(save_state),
+        });
+    } else {
+        let root_view = Widget::kind_mut::<RootView>(root);
+        root_view.next_step = Some(NextGameStep::LoadCampaign {
+            save_state: Box::new(save_state),
+        });
+    }
+
+    let loading_screen = Widget::with_defaults(LoadingScreen::new());
+    loading_screen.borrow_mut().state.set_modal(true);
+    Widget::add_child_to(root, loading_screen);
+}
@@ -21,7 +21,8 @@ use std::rc::Rc;
 use sulis_core::ui::*;
 use sulis_core::util::ActiveResources;
 use sulis_core::widgets::{Button, Label, ScrollDirection, ScrollPane, TextArea};
-use sulis_module::ModificationInfo;
+use sulis_module::modification::resolve_load_order;
+use sulis_module::{ModificationInfo, Module};
 use sulis_state::NextGameStep;
 
 use crate::main_menu::MainMenu;
@@ -30,6 +31,7 @@ use crate::LoadingScreen;
 pub struct ModsSelector {
     available_mods: Vec<ModificationInfo>,
     active_mods: Vec<ModificationInfo>,
+    problems: Vec<String>,
 }
 
 impl ModsSelector {
@@ -49,8 +51,27 @@ impl ModsSelector {
         Rc::new(RefCell::new(ModsSelector {
             available_mods,
             active_mods,
+            problems: Vec::new(),
         }))
     }
+
+    /// Sorts the active mods into a valid load order for the current campaign, or
+    /// records the problems preventing one
+    fn resolve_active_mods(&mut self) {
+        let campaign_id = if Module::is_initialized() {
+            Some(Module::campaign().id.clone())
+        } else {
+            None
+        };
+
+        match resolve_load_order(&self.active_mods, campaign_id.as_deref()) {
+            Ok(order) => {
+                self.active_mods = order;
+                self.problems.clear();
+            }
+            Err(problems) => self.problems = problems,
+        }
+    }
 }
 
 impl WidgetKind for ModsSelector {
@@ -69,6 +90,7 @@ impl WidgetKind for ModsSelector {
 
         self.available_mods
             .sort_by(|a, b| a.name.cmp(&b.name));
+        self.resolve_active_mods();
 
         let len = self.available_mods.len();
         for (index, modif) in self.available_mods.iter().enumerate() {
@@ -106,12 +128,28 @@ impl WidgetKind for ModsSelector {
                 root.borrow_mut().invalidate_children();
             })));
 
+        let problems = Widget::with_theme(TextArea::empty(), "problems");
+        if let Some(problem) = self.problems.first() {
+            let state = &mut problems.borrow_mut().state;
+            state.add_text_arg("problem", problem);
+            if self.problems.len() > 1 {
+                state.add_text_arg("more", &(self.problems.len() - 1).to_string());
+            }
+        }
+
         let apply = Widget::with_theme(Button::empty(), "apply");
+        apply
+            .borrow_mut()
+            .state
+            .set_enabled(self.problems.is_empty());
         apply
             .borrow_mut()
             .state
             .add_callback(Callback::new(Rc::new(|widget, _| {
                 let (_, sel) = Widget::parent_mut::<ModsSelector>(widget);
+                if !sel.problems.is_empty() {
+                    return;
+                }
 
                 let mut resources = ActiveResources::read();
                 resources.mods.clear();
@@ -138,6 +176,7 @@ impl WidgetKind for ModsSelector {
             clear,
             cancel,
             apply,
+            problems,
         ]
     }
 }
@@ -174,6 +213,9 @@ impl WidgetKind for ModPane {
         {
             let state = &mut description.borrow_mut().state;
             state.add_text_arg("name", &self.modif.name);
+            if !self.modif.version.is_empty() {
+                state.add_text_arg("version", &self.modif.version);
+            }
             state.add_text_arg("description", &self.modif.description);
             state.add_text_arg("dir", &self.modif.dir);
         }
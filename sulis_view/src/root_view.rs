@@ -30,9 +30,11 @@ use sulis_core::util;
 use sulis_core::widgets::{Button, ConfirmationWindow, Label};
 use sulis_module::{area::OnRest, Module};
 use sulis_state::{
-    area_feedback_text::ColorKind, save_file::create_save, script::script_callback,
-    script::ScriptEntity, AreaFeedbackText, ChangeListener, EntityState, GameState, NextGameStep,
-    Script,
+    area_feedback_text::ColorKind,
+    save_file::{create_save, PendingSave},
+    script::script_callback,
+    script::ScriptEntity,
+    AreaFeedbackText, ChangeListener, EntityState, GameState, NextGameStep, SaveKind, Script,
 };
 
 const WINDOW_NAMES: [&str; 7] = [
@@ -61,6 +63,12 @@ pub struct RootView {
     area: String,
 
     scroll_keys_down: Vec<InputActionKind>,
+
+    autosave_millis: u32,
+    autosave_pending: bool,
+
+    // a save waiting for the area view to capture its thumbnail
+    pending_save: Option<PendingSave>,
 }
 
 impl RootView {
@@ -72,7 +80,13 @@ impl RootView {
     }
 
     pub fn next_step(&mut self) -> Option<NextGameStep> {
-        self.next_step.take()
+        let step = self.next_step.take();
+
+        // the area view may not be drawn again to capture a thumbnail
+        if step.is_some() {
+            self.write_pending_save();
+        }
+        step
     }
 
     pub fn set_next_step(&mut self, step: NextGameStep) {
@@ -103,6 +117,9 @@ impl RootView {
             quick_item_bar: None,
             abilities_bar: None,
             scroll_keys_down: Vec::new(),
+            autosave_millis: 0,
+            autosave_pending: false,
+            pending_save: None,
         }))
     }
 
@@ -317,7 +334,7 @@ impl RootView {
         }
     }
 
-    pub fn rest(&self) {
+    pub fn rest(&mut self) {
         let area_state = GameState::area_state();
         let area = Rc::clone(&area_state.borrow().area.area);
 
@@ -330,23 +347,88 @@ impl RootView {
                 area_state.borrow_mut().add_feedback_text(feedback);
             }
             OnRest::FireScript { ref id, ref func } => {
+                if Config::saves_config().autosave_on_rest {
+                    self.autosave();
+                }
                 Script::trigger(id, func, ScriptEntity::from(&target));
             }
         }
     }
 
     pub fn save(&mut self) {
+        self.save_as(SaveKind::Quick);
+    }
+
+    pub fn save_as(&mut self, kind: SaveKind) {
         if GameState::is_combat_active() {
             self.add_status_text("Cannot save during combat.");
             return;
         }
 
-        if let Err(e) = create_save() {
-            error!("Error quick saving game");
-            error!("{}", e);
-            self.add_status_text("Error performing Save!");
-        } else {
-            self.add_status_text("Save Complete.");
+        match create_save(kind) {
+            Err(e) => {
+                error!("Error saving game");
+                error!("{}", e);
+                self.add_status_text("Error performing Save!");
+            }
+            Ok(save) => self.set_pending_save(save),
+        }
+    }
+
+    /// Creates an autosave, if autosaves are enabled and the game may be saved
+    pub fn autosave(&mut self) {
+        self.autosave_pending = false;
+        self.autosave_millis = 0;
+
+        if Config::saves_config().autosave_count == 0 || GameState::is_combat_active() {
+            return;
+        }
+
+        match create_save(SaveKind::Auto) {
+            Err(e) => {
+                error!("Error autosaving game");
+                error!("{}", e);
+                self.add_status_text("Error performing Autosave!");
+            }
+            Ok(save) => self.set_pending_save(save),
+        }
+    }
+
+    /// Holds the save until the area view has captured a thumbnail for it
+    fn set_pending_save(&mut self, save: PendingSave) {
+        // only one thumbnail is captured at a time, so write any earlier save now
+        if self.pending_save.is_some() {
+            self.write_pending_save();
+        }
+
+        self.pending_save = Some(save);
+        self.area_view.borrow_mut().request_thumbnail();
+    }
+
+    fn write_pending_save(&mut self) {
+        let save = match self.pending_save.take() {
+            None => return,
+            Some(save) => save,
+        };
+
+        let auto = *save.kind() == SaveKind::Auto;
+        let thumbnail = self.area_view.borrow_mut().take_thumbnail_png();
+        match save.write(thumbnail) {
+            Err(e) if auto => {
+                error!("Error autosaving game");
+                error!("{}", e);
+                self.add_status_text("Error performing Autosave!");
+            }
+            Err(e) => {
+                error!("Error saving game");
+                error!("{}", e);
+                self.add_status_text("Error performing Save!");
+            }
+            Ok(()) if auto => self.add_status_text("Autosave Complete."),
+            Ok(()) => {
+                self.autosave_millis = 0;
+                self.add_status_text("Save Complete.");
+            }
         }
     }
 
@@ -359,6 +441,17 @@ impl RootView {
     }
 }
 
+impl Drop for RootView {
+    fn drop(&mut self) {
+        if let Some(save) = self.pending_save.take() {
+            if let Err(e) = save.write(None) {
+                error!("Error saving game");
+                error!("{}", e);
+            }
+        }
+    }
+}
+
 impl WidgetKind for RootView {
     widget_kind!(NAME);
 
@@ -367,6 +460,10 @@ impl WidgetKind for RootView {
         let root = Widget::get_root(widget);
         let area = area_state.borrow().area.area.id.clone();
         if area != self.area {
+            // the area is initially empty, so don't autosave right after loading
+            if !self.area.is_empty() && Config::saves_config().autosave_on_area_transition {
+                self.autosave_pending = true;
+            }
             self.area = area;
             root.borrow_mut().invalidate_children();
         }
@@ -383,6 +480,21 @@ impl WidgetKind for RootView {
         let has_modal = root.borrow().has_modal();
         GameState::set_modal_locked(has_modal);
 
+        let interval = Config::saves_config().autosave_interval_minutes;
+        self.autosave_millis = self.autosave_millis.saturating_add(millis);
+        if interval > 0 && self.autosave_millis >= interval.saturating_mul(60_000) {
+            self.autosave_pending = true;
+        }
+
+        if self.pending_save.is_some() && !self.area_view.borrow().is_thumbnail_pending() {
+            self.write_pending_save();
+        }
+
+        // wait for any open windows to close and combat to end before autosaving
+        if self.autosave_pending && !has_modal && !GameState::is_combat_active() {
+            self.autosave();
+        }
+
         let (cx, cy) = (Cursor::get_x(), Cursor::get_y());
         let mut area_view_updated = false;
         if !has_modal {
@@ -570,7 +682,10 @@ impl WidgetKind for RootView {
                 Box::new(move |timer| {
                     let enabled = match timer.current() {
                         None => false,
-                        Some(entity) => entity.borrow().is_party_member(),
+                        Some(entity) => {
+                            let entity = entity.borrow();
+                            entity.is_party_member() && !entity.is_party_ai_active(timer)
+                        }
                     };
                     end_turn_button_ref.borrow_mut().state.set_enabled(enabled);
                 }),
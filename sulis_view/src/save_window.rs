@@ -0,0 +1,177 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sulis_core::ui::{Callback, Widget, WidgetKind};
+use sulis_core::widgets::{
+    Button, ConfirmationWindow, InputField, Label, ScrollDirection, ScrollPane,
+};
+use sulis_state::save_file::get_available_save_files;
+use sulis_state::SaveKind;
+
+use crate::RootView;
+
+const NAME: &str = "save_window";
+
+/// Allows the player to save to a named slot, either a new one or one of the
+/// existing named slots
+pub struct SaveWindow {
+    accept: Rc<RefCell<Widget>>,
+    name_field: Rc<RefCell<InputField>>,
+    name: String,
+    slots: Vec<String>,
+}
+
+impl SaveWindow {
+    pub fn new() -> Rc<RefCell<SaveWindow>> {
+        let accept = Widget::with_theme(Button::empty(), "accept");
+        let slots = match get_available_save_files() {
+            Ok(files) => files
+                .into_iter()
+                .filter_map(|meta| match meta.kind {
+                    SaveKind::Named(name) => Some(name),
+                    _ => None,
+                })
+                .collect(),
+            Err(e) => {
+                warn!("Unable to read saved files");
+                warn!("{}", e);
+                Vec::new()
+            }
+        };
+
+        Rc::new(RefCell::new(SaveWindow {
+            accept,
+            name_field: InputField::new(""),
+            name: String::new(),
+            slots,
+        }))
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.trim().to_string();
+        self.accept
+            .borrow_mut()
+            .state
+            .set_enabled(!self.name.is_empty());
+    }
+}
+
+/// Saves to the named slot from the save window containing `widget`, asking for
+/// confirmation first if that would overwrite an existing save
+fn save_to_slot(widget: &Rc<RefCell<Widget>>, name: &str) {
+    let (parent, window) = Widget::parent::<SaveWindow>(widget);
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return;
+    }
+
+    let root = Widget::get_root(&parent);
+    if !window.slots.contains(&name) {
+        parent.borrow_mut().mark_for_removal();
+        let view = Widget::kind_mut::<RootView>(&root);
+        view.save_as(SaveKind::Named(name));
+        return;
+    }
+
+    let save_window = Rc::clone(&parent);
+    let overwrite_cb = Callback::new(Rc::new(move |widget, _| {
+        let (conf_window, _) = Widget::parent::<ConfirmationWindow>(widget);
+        conf_window.borrow_mut().mark_for_removal();
+        save_window.borrow_mut().mark_for_removal();
+
+        let root = Widget::get_root(widget);
+        let view = Widget::kind_mut::<RootView>(&root);
+        view.save_as(SaveKind::Named(name.clone()));
+    }));
+
+    let conf_window = Widget::with_theme(
+        ConfirmationWindow::new(overwrite_cb),
+        "overwrite_save_confirmation",
+    );
+    conf_window.borrow_mut().state.set_modal(true);
+    Widget::add_child_to(&root, conf_window);
+}
+
+impl WidgetKind for SaveWindow {
+    widget_kind!(NAME);
+
+    fn on_add(&mut self, _widget: &Rc<RefCell<Widget>>) -> Vec<Rc<RefCell<Widget>>> {
+        let title = Widget::with_theme(Label::empty(), "title");
+
+        let cancel = Widget::with_theme(Button::empty(), "cancel");
+        cancel
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (parent, _) = Widget::parent::<SaveWindow>(widget);
+                parent.borrow_mut().mark_for_removal();
+            })));
+
+        self.accept
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (_, window) = Widget::parent::<SaveWindow>(widget);
+                save_to_slot(widget, &window.name);
+            })));
+
+        self.name_field
+            .borrow_mut()
+            .set_enter_callback(Callback::new(Rc::new(|widget, kind| {
+                let field = Widget::downcast::<InputField>(kind);
+                save_to_slot(widget, &field.text);
+            })));
+        let name_field = Widget::with_theme(self.name_field.clone(), "name_field");
+        name_field
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, kind| {
+                let (_, window) = Widget::parent_mut::<SaveWindow>(widget);
+                let field = Widget::downcast::<InputField>(kind);
+                window.set_name(&field.text);
+            })));
+
+        let scrollpane = ScrollPane::new(ScrollDirection::Vertical);
+        let slots = Widget::with_theme(scrollpane.clone(), "slots");
+        for name in self.slots.iter() {
+            let slot = Widget::with_theme(Button::empty(), "slot");
+            slot.borrow_mut().state.add_text_arg("name", name);
+
+            let name = name.to_string();
+            let field_widget = Rc::clone(&name_field);
+            slot.borrow_mut()
+                .state
+                .add_callback(Callback::new(Rc::new(move |widget, _| {
+                    let (_, window) = Widget::parent_mut::<SaveWindow>(widget);
+                    window
+                        .name_field
+                        .borrow_mut()
+                        .set_text(&name, &field_widget);
+                    window.set_name(&name);
+                })));
+            scrollpane.borrow().add_to_content(slot);
+        }
+
+        let name = self.name.clone();
+        self.set_name(&name);
+
+        vec![title, slots, name_field, cancel, self.accept.clone()]
+    }
+}
@@ -19,13 +19,13 @@ use std::rc::Rc;
 
 use sulis_core::ui::{Callback, Widget};
 use sulis_module::{
-    on_trigger::{self, Kind, ModuleLoadData, QuestStateData},
-    Actor, ItemState, MerchantData, Module, OnTrigger,
+    on_trigger::{self, Kind, ModuleLoadData},
+    Actor, MerchantData, Module, OnTrigger,
 };
 use sulis_state::{
     area_feedback_text::ColorKind,
-    script::{entity_with_id, CallbackData, FuncKind, ScriptEntity},
-    AreaFeedbackText, EntityState, GameState, NextGameStep, Script,
+    script::{CallbackData, FuncKind},
+    trigger_state, AreaFeedbackText, EntityState, GameState, NextGameStep,
 };
 
 use crate::{
@@ -150,6 +150,10 @@ pub fn activate(
 ) {
     use sulis_module::OnTrigger::*;
     for trigger in on_select.iter() {
+        if trigger_state::activate(trigger, pc, target) {
+            continue;
+        }
+
         match trigger {
             BlockUI(millis) => {
                 let root = Widget::get_root(widget);
@@ -159,74 +163,6 @@ pub fn activate(
             CheckEndTurn => {
                 ap_bar::check_end_turn(widget);
             }
-            PlayerAbility(ref ability_id) => {
-                let ability = match Module::ability(ability_id) {
-                    None => {
-                        warn!(
-                            "No ability found for '{}' when activating on_trigger",
-                            ability_id
-                        );
-                        return;
-                    }
-                    Some(ability) => ability,
-                };
-
-                let mut pc = pc.borrow_mut();
-                let state = &mut pc.actor;
-                let new_actor = Actor::from(
-                    &state.actor,
-                    None,
-                    state.actor.xp,
-                    vec![ability],
-                    Vec::new(),
-                    state.actor.inventory.clone(),
-                );
-                state.replace_actor(new_actor);
-                state.init_day();
-            }
-            PlayerCoins(amount) => {
-                GameState::add_party_coins(*amount);
-            }
-            PartyMember(ref id) => match entity_with_id(id.to_string()) {
-                None => warn!(
-                    "Attempted to add party member '{}' but entity does not exist",
-                    id
-                ),
-                Some(entity) => GameState::add_party_member(entity, true),
-            },
-            PartyItem(ref id) => {
-                let stash = GameState::party_stash();
-                match ItemState::from(id) {
-                    None => warn!("Attempted to add item '{}' but it does not exist", id),
-                    Some(item) => {
-                        stash.borrow_mut().add_item(1, item);
-                    }
-                }
-            }
-            TargetNumFlag(ref data) => {
-                target.borrow_mut().add_num_flag(&data.flag, data.val);
-            }
-            PlayerNumFlag(ref data) => {
-                pc.borrow_mut().add_num_flag(&data.flag, data.val);
-            }
-            NotTargetNumFlag(ref data) => {
-                target.borrow_mut().clear_custom_flag(&data.flag);
-            }
-            NotPlayerNumFlag(ref data) => {
-                pc.borrow_mut().clear_custom_flag(&data.flag);
-            }
-            NotTargetFlag(ref flag) => {
-                target.borrow_mut().clear_custom_flag(flag);
-            }
-            NotPlayerFlag(ref flag) => {
-                pc.borrow_mut().clear_custom_flag(flag);
-            }
-            TargetFlag(ref flag) => {
-                target.borrow_mut().set_custom_flag(flag, "true");
-            }
-            PlayerFlag(ref flag) => {
-                pc.borrow_mut().set_custom_flag(flag, "true");
-            }
             ShowMerchant(ref merch) => show_merchant(widget, merch),
             StartConversation(ref convo) => start_convo(widget, convo, pc, target),
             SayLine(ref line) => {
@@ -237,7 +173,6 @@ pub fn activate(
                 area.borrow_mut().add_feedback_text(feedback);
             }
             ShowCutscene(ref cutscene) => show_cutscene(widget, cutscene),
-            FireScript(ref script) => fire_script(&script.id, &script.func, pc, target),
             GameOverWindow(ref text) => game_over_window(widget, text.to_string()),
             ExitToMenu => exit_to_menu(widget),
             ScrollView(x, y) => scroll_view(widget, *x, *y),
@@ -246,38 +181,11 @@ pub fn activate(
             ShowConfirm(ref data) => show_confirm(widget, data),
             ShowMenu(ref data) => show_menu(widget, data),
             FadeOutIn => fade_out_in(widget),
-            QuestState(ref data) => {
-                verify_quest(data);
-
-                if let Some(ref entry) = data.entry {
-                    GameState::set_quest_entry_state(
-                        data.quest.to_string(),
-                        entry.to_string(),
-                        data.state,
-                    );
-                } else {
-                    GameState::set_quest_state(data.quest.to_string(), data.state);
-                }
-            }
             NotQuestState(_) => {
                 warn!("NotQuestState invalid for trigger/dialog on_activate");
             }
-        }
-    }
-}
-
-fn verify_quest(data: &QuestStateData) {
-    match Module::quest(&data.quest) {
-        None => warn!("Quest state for invalid quest '{}'", data.quest),
-        Some(quest) => {
-            if let Some(ref entry) = data.entry {
-                if !quest.entries.contains_key(entry) {
-                    warn!(
-                        "Quest entry state for invalid quest entry '{}' in '{}'",
-                        entry, data.quest
-                    );
-                }
-            }
+            // all other kinds only modify the game state
+            _ => (),
         }
     }
 }
@@ -306,7 +214,7 @@ fn show_confirm(widget: &Rc<RefCell<Widget>>, data: &on_trigger::DialogData) {
         let func = on_accept.func.to_string();
         Callback::new(Rc::new(move |widget, _| {
             let target = GameState::player();
-            fire_script(&id, &func, &target, &target);
+            trigger_state::fire_script(&id, &func, &target, &target);
 
             let (parent, _) = Widget::parent::<ConfirmationWindow>(widget);
             parent.borrow_mut().mark_for_removal();
@@ -458,19 +366,6 @@ fn game_over_window(widget: &Rc<RefCell<Widget>>, text: String) {
     Widget::add_child_to(&root, window);
 }
 
-fn fire_script(
-    script_id: &str,
-    func: &str,
-    parent: &Rc<RefCell<EntityState>>,
-    target: &Rc<RefCell<EntityState>>,
-) {
-    Script::trigger(
-        script_id,
-        func,
-        (ScriptEntity::from(parent), ScriptEntity::from(target)),
-    );
-}
-
 fn show_merchant(widget: &Rc<RefCell<Widget>>, merch: &MerchantData) {
     let id = &merch.id;
     let loot = match Module::loot_list(&merch.loot_list) {